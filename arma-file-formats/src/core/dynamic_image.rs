@@ -0,0 +1,21 @@
+use image::{DynamicImage, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+
+use super::types::PixelType;
+
+/// Wraps raw decoded pixel data, `None` if the pixel type is unknown or the size doesn't match.
+pub fn to_dynamic_image(
+    pixel_type: &PixelType,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+) -> Option<DynamicImage> {
+    match pixel_type {
+        PixelType::Rgba => RgbaImage::from_raw(width, height, data).map(DynamicImage::ImageRgba8),
+        PixelType::Rgb => RgbImage::from_raw(width, height, data).map(DynamicImage::ImageRgb8),
+        PixelType::Gray => GrayImage::from_raw(width, height, data).map(DynamicImage::ImageLuma8),
+        PixelType::GrayAlpha => {
+            GrayAlphaImage::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+        }
+        PixelType::Unknown => None,
+    }
+}
@@ -1,4 +1,7 @@
 pub(crate) mod binrw_utils;
+#[cfg(feature = "image")]
+pub(crate) mod dynamic_image;
+pub(crate) mod json;
 mod lzss;
 pub mod read;
 pub mod types;
@@ -0,0 +1,32 @@
+use image::DynamicImage;
+
+use crate::{core::dynamic_image::to_dynamic_image, errors::AffError};
+
+use super::Edds;
+
+impl Edds {
+    /// Converts a decoded mipmap, mipmaps are stored smallest first.
+    pub fn mipmap_to_image(&self, index: usize) -> Result<DynamicImage, AffError> {
+        let mipmap = self
+            .mipmaps
+            .get(index)
+            .ok_or(AffError::MipmapNotFound(index))?;
+
+        to_dynamic_image(
+            &self.pixel_type,
+            mipmap.width as u32,
+            mipmap.height as u32,
+            mipmap.data.clone(),
+        )
+        .ok_or_else(|| AffError::UnsupportedPixelType(self.pixel_type.clone()))
+    }
+}
+
+impl TryFrom<&Edds> for DynamicImage {
+    type Error = AffError;
+
+    /// Converts the largest mipmap.
+    fn try_from(edds: &Edds) -> Result<Self, Self::Error> {
+        edds.mipmap_to_image(edds.mipmaps.len().saturating_sub(1))
+    }
+}
@@ -1,5 +1,7 @@
 mod dds_header;
 mod dds_header_enums;
+#[cfg(feature = "image")]
+mod dynamic_image;
 mod edds;
 
 pub use self::{dds_header::DdsHeader, dds_header_enums::*, edds::*};
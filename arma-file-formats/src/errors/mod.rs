@@ -2,6 +2,10 @@ use std::{io, string::FromUtf8Error};
 
 use thiserror::Error;
 
+use crate::core::types::PixelType;
+#[cfg(feature = "real_virtuality")]
+use crate::real_virtuality::p3d::Resolution;
+
 #[derive(Debug, Error)]
 pub enum PaaError {
     #[error("No mipmaps were set")]
@@ -22,6 +26,9 @@ pub enum PaaError {
     #[error("Invalid state")]
     InvalidState,
 
+    #[error("Unsupported image dimensions `{0}x{1}`")]
+    InvalidDimensions(u32, u32),
+
     #[error("unknown decoding error")]
     Unknown,
 }
@@ -48,6 +55,26 @@ pub enum OdolError {
 
     #[error("Unsupported Version: `{0}`")]
     UnsupportedVersion(u32),
+
+    #[cfg(feature = "real_virtuality")]
+    #[error("Resolution `{0}` not found")]
+    ResolutionNotFound(Resolution),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Error)]
+pub enum WrpError {
+    #[error("Unsupported Version: `{0}`")]
+    UnsupportedVersion(u32),
+
+    #[error("`{field}` has {actual} elements, expected {expected}")]
+    InvalidLength {
+        field: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("Unknown map info type `{0}`")]
+    UnknownMapType(u32),
 }
 
 #[derive(Debug, Error)]
@@ -67,12 +94,28 @@ pub enum AffError {
     #[error("ODOL Error")]
     OdolError(#[from] OdolError),
 
+    #[error("WRP Error {0}")]
+    WrpError(#[from] WrpError),
+
+    #[error("PAA Error {0}")]
+    PaaError(#[from] PaaError),
+
+    #[cfg(feature = "image")]
+    #[error("Image coding failed {0}")]
+    ImageError(#[from] image::ImageError),
+
     #[error("Invalid file")]
     InvalidFileError,
 
     #[error("PBO Entry {0} not found")]
     PboEntryNotFound(String),
 
+    #[error("Mipmap {0} not found")]
+    MipmapNotFound(usize),
+
+    #[error("LOD {0} not found")]
+    LodNotFound(usize),
+
     #[error("unknown decoding error")]
     Unknown,
 
@@ -84,4 +127,7 @@ pub enum AffError {
 
     #[error("Unknown image data type: `{0}`!\nPlease report this error at https://github.com/arma-tools/arma-file-formats-rs/issues")]
     UnknownImageDataType(String),
+
+    #[error("Unsupported pixel type `{0:?}`")]
+    UnsupportedPixelType(PixelType),
 }
@@ -1,9 +1,9 @@
-use std::io::{Cursor, Read, Seek};
+use std::io::{Cursor, Read, Seek, Write};
 
-use binrw::{BinRead, BinResult, Endian};
-use lzokay_native::decompress;
+use binrw::{BinRead, BinResult, BinWrite, Endian};
+use lzokay_native::{compress, decompress};
 
-use crate::{core::decompress_lzss, real_virtuality::p3d::ODOLArgs};
+use crate::{core::decompress_lzss, errors::OdolError, real_virtuality::p3d::ODOLArgs};
 
 use super::types::{STPair, XYZTriplet};
 
@@ -143,6 +143,82 @@ fn decompress_data(
     Ok(data)
 }
 
+// Writers get a reference to the field type
+#[allow(clippy::ptr_arg, clippy::ref_option)]
+#[binrw::writer(writer, endian)]
+pub fn write_compressed_size_cond(data: &Option<Vec<u8>>, args: ODOLArgs) -> BinResult<()> {
+    data.as_ref()
+        .map_or(Ok(()), |data| compress_data(writer, endian, data, args))
+}
+
+#[allow(clippy::ptr_arg, clippy::ref_option)]
+#[binrw::writer(writer, endian)]
+pub fn write_compressed(data: &Vec<u8>, args: ODOLArgs) -> BinResult<()> {
+    (data.len() as u32).write_options(writer, endian, ())?;
+    compress_data(writer, endian, data, args)
+}
+
+#[allow(clippy::ptr_arg, clippy::ref_option)]
+#[binrw::writer(writer, endian)]
+pub fn write_compressed_array<T, 'a>(arr: &Vec<T>, args: ODOLArgs) -> BinResult<()>
+where
+    T: BinWrite<Args<'a> = ()>,
+{
+    (arr.len() as u32).write_options(writer, endian, ())?;
+    compress_array(writer, endian, arr, args)
+}
+
+pub fn compress_array<'a, T>(
+    writer: &mut (impl Write + Seek),
+    endian: Endian,
+    arr: &[T],
+    args: ODOLArgs,
+) -> BinResult<()>
+where
+    T: BinWrite<Args<'a> = ()>,
+{
+    let mut data = Cursor::new(Vec::new());
+    for el in arr {
+        el.write_options(&mut data, endian, ())?;
+    }
+    compress_data(writer, endian, &data.into_inner(), args)
+}
+
+/// Counterpart of `decompress_data`, LZO compresses arrays of at least 1024 bytes.
+fn compress_data(
+    writer: &mut (impl Write + Seek),
+    endian: Endian,
+    data: &[u8],
+    args: ODOLArgs,
+) -> BinResult<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    let pre_pos = writer.stream_position()?;
+    let compressed = data.len() >= 1024;
+
+    if !args.use_lzo && compressed {
+        return Err(binrw::Error::Custom {
+            err: Box::new(OdolError::UnsupportedVersion(args.version)),
+            pos: pre_pos,
+        });
+    }
+    if args.use_compression_flag {
+        u8::from(compressed).write_options(writer, endian, ())?;
+    }
+
+    if compressed {
+        let data = compress(data).map_err(|e| binrw::Error::Custom {
+            err: Box::new(e),
+            pos: pre_pos,
+        })?;
+        writer.write_all(&data)?;
+    } else {
+        writer.write_all(data)?;
+    }
+    Ok(())
+}
+
 #[binrw::parser(reader, endian)]
 pub fn read_condensed_array_cond<T, 'a>(
     cond: bool,
@@ -182,6 +258,30 @@ where
     Ok(res)
 }
 
+/// Arrays with all values equal are written as a single default fill value.
+pub fn write_condensed_array<'a, T>(
+    writer: &mut (impl Write + Seek),
+    endian: Endian,
+    arr: &[T],
+    args: ODOLArgs,
+) -> BinResult<()>
+where
+    T: BinWrite<Args<'a> = ()> + PartialEq,
+{
+    (arr.len() as u32).write_options(writer, endian, ())?;
+
+    match arr.first() {
+        Some(first) if arr.iter().all(|v| v == first) => {
+            1_u8.write_options(writer, endian, ())?;
+            first.write_options(writer, endian, ())
+        }
+        _ => {
+            0_u8.write_options(writer, endian, ())?;
+            compress_array(writer, endian, arr, args)
+        }
+    }
+}
+
 #[binrw::parser(reader, endian)]
 pub fn read_vertex_index_array(args: ODOLArgs, count: usize) -> BinResult<Vec<u32>> {
     let mut res = Vec::with_capacity(count);
@@ -205,6 +305,19 @@ fn read_vertex_index(
     }
 }
 
+#[allow(clippy::ptr_arg, clippy::ref_option)]
+#[binrw::writer(writer, endian)]
+pub fn write_vertex_index_array(indices: &Vec<u32>, args: ODOLArgs) -> BinResult<()> {
+    for index in indices {
+        if args.version >= 69 {
+            index.write_options(writer, endian, ())?;
+        } else {
+            (*index as u16).write_options(writer, endian, ())?;
+        }
+    }
+    Ok(())
+}
+
 #[binrw::parser(reader, endian)]
 pub fn read_normals_parse(args: ODOLArgs) -> BinResult<Vec<XYZTriplet>> {
     read_normals(reader, endian, args)
@@ -223,6 +336,20 @@ pub fn read_normals(
     }
 }
 
+pub fn write_normals(
+    writer: &mut (impl Write + Seek),
+    endian: Endian,
+    normals: &[XYZTriplet],
+    args: ODOLArgs,
+) -> BinResult<()> {
+    if args.version >= 45 {
+        let comp: Vec<i32> = normals.iter().map(compress_xyz).collect();
+        write_condensed_array(writer, endian, &comp, args)
+    } else {
+        write_condensed_array(writer, endian, normals, args)
+    }
+}
+
 pub fn decompress_xyz(val: i32) -> XYZTriplet {
     let mut x = val & 1023;
     let mut y = val >> 10 & 1023;
@@ -249,6 +376,14 @@ pub fn decompress_xyz(val: i32) -> XYZTriplet {
     }
 }
 
+/// Inverse of [`decompress_xyz`], components are clamped to the 10 bit range.
+pub fn compress_xyz(val: &XYZTriplet) -> i32 {
+    let factor = -0.001_956_947_1_f32;
+    let pack = |v: f32| ((v / factor).round() as i32).clamp(-511, 511) & 1023;
+
+    pack(val.x) | pack(val.y) << 10 | pack(val.z) << 20
+}
+
 #[binrw::parser(reader, endian)]
 pub fn read_st_parse(args: ODOLArgs) -> BinResult<Vec<STPair>> {
     read_st(reader, endian, args)
@@ -268,7 +403,22 @@ pub fn read_st(
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, BinRead)]
+pub fn write_st(
+    writer: &mut (impl Write + Seek),
+    endian: Endian,
+    st_coords: &[STPair],
+    args: ODOLArgs,
+) -> BinResult<()> {
+    (st_coords.len() as u32).write_options(writer, endian, ())?;
+    if args.version >= 45 {
+        let comp: Vec<STPairCompress> = st_coords.iter().map(Into::into).collect();
+        compress_array(writer, endian, &comp, args)
+    } else {
+        compress_array(writer, endian, st_coords, args)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, BinRead, BinWrite)]
 struct STPairCompress {
     s: i32,
     t: i32,
@@ -283,6 +433,15 @@ impl From<STPairCompress> for STPair {
     }
 }
 
+impl From<&STPair> for STPairCompress {
+    fn from(val: &STPair) -> Self {
+        Self {
+            s: compress_xyz(&val.s),
+            t: compress_xyz(&val.t),
+        }
+    }
+}
+
 pub fn read_8wvr_material_names(
     reader: &mut (impl Read + Seek),
     endian: Endian,
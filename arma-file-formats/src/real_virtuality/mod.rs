@@ -3,6 +3,7 @@ pub mod paa;
 pub mod pbo;
 pub mod rap;
 pub mod sign;
+pub mod tex_headers;
 pub mod wrp;
 
 mod binrw_utils;
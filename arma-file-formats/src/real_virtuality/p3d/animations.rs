@@ -1,19 +1,25 @@
-use binrw::{BinRead, NullString};
+use binrw::{binrw, BinRead, BinWrite, NullString};
 use derivative::Derivative;
 
 use crate::real_virtuality::types::XYZTriplet;
 
 use super::ODOLArgs;
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[binrw]
+#[derive(Debug, Default, PartialEq, Clone)]
 #[br(import(args: ODOLArgs))]
+#[bw(import(args: ODOLArgs))]
 pub struct Animations {
+    #[bw(map = |_: &u32| animation_classes.len() as u32)]
     pub animation_class_count: u32,
 
     #[br(count = animation_class_count)]
     #[br(args { inner: (args.version,) })]
+    #[bw(args(args.version,))]
     pub animation_classes: Vec<AnimationClass>,
 
+    #[br(temp)]
+    #[bw(calc = anims_2_bones.len() as i32)]
     resolution_count: i32,
 
     #[br(count = resolution_count)]
@@ -21,11 +27,13 @@ pub struct Animations {
 
     #[br(count = resolution_count)]
     #[br(args { inner: (animation_class_count, animation_classes.clone(),) })]
+    #[bw(args_raw = animation_classes.as_slice())]
     pub anims_2_bones: Vec<Anims2Bones>,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, BinRead, BinWrite)]
 #[br(import(version: u32))]
+#[bw(import(version: u32))]
 pub struct AnimationClass {
     pub anim_transform_type: AnimType,
     pub anim_class_name: NullString,
@@ -36,8 +44,12 @@ pub struct AnimationClass {
     pub max_value: f32,
 
     #[br(if(version >= 56))]
+    #[bw(if(version >= 56))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub anim_period: Option<f32>,
     #[br(if(version >= 56))]
+    #[bw(if(version >= 56))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub init_phase: Option<f32>,
 
     pub source_address: AnimAddress,
@@ -48,6 +60,8 @@ pub struct AnimationClass {
         anim_transform_type == AnimType::RotationY ||
         anim_transform_type == AnimType::RotationZ
     ))]
+    #[bw(if(anim_transform_type.is_rotation()))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub angle_0: Option<f32>,
     #[br(if(
         anim_transform_type == AnimType::Rotation ||
@@ -55,6 +69,8 @@ pub struct AnimationClass {
         anim_transform_type == AnimType::RotationY ||
         anim_transform_type == AnimType::RotationZ
     ))]
+    #[bw(if(anim_transform_type.is_rotation()))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub angle_1: Option<f32>,
 
     #[br(if(
@@ -63,6 +79,8 @@ pub struct AnimationClass {
         anim_transform_type == AnimType::TranslationY ||
         anim_transform_type == AnimType::TranslationZ
     ))]
+    #[bw(if(anim_transform_type.is_translation()))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub offset_0: Option<f32>,
     #[br(if(
         anim_transform_type == AnimType::Translation ||
@@ -70,26 +88,41 @@ pub struct AnimationClass {
         anim_transform_type == AnimType::TranslationY ||
         anim_transform_type == AnimType::TranslationZ
     ))]
+    #[bw(if(anim_transform_type.is_translation()))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub offset_1: Option<f32>,
 
     #[br(if(anim_transform_type == AnimType::Direct))]
+    #[bw(if(*anim_transform_type == AnimType::Direct))]
+    #[bw(map = |x: &Option<XYZTriplet>| x.unwrap_or_default())]
     pub axis_pos: Option<XYZTriplet>,
     #[br(if(anim_transform_type == AnimType::Direct))]
+    #[bw(if(*anim_transform_type == AnimType::Direct))]
+    #[bw(map = |x: &Option<XYZTriplet>| x.unwrap_or_default())]
     pub axis_dir: Option<XYZTriplet>,
     #[br(if(anim_transform_type == AnimType::Direct))]
+    #[bw(if(*anim_transform_type == AnimType::Direct))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub axis_angle: Option<f32>,
     #[br(if(anim_transform_type == AnimType::Direct))]
+    #[bw(if(*anim_transform_type == AnimType::Direct))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub axis_offset: Option<f32>,
 
     #[br(if(anim_transform_type == AnimType::Hide))]
+    #[bw(if(*anim_transform_type == AnimType::Hide))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub hide_value: Option<f32>,
+    /// Phase from which the bone is shown again.
     #[br(if(anim_transform_type == AnimType::Hide && version >= 55))]
-    pub unknown_hide: Option<f32>,
+    #[bw(if(*anim_transform_type == AnimType::Hide && version >= 55))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
+    pub unhide_value: Option<f32>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, BinWrite, Derivative)]
 #[derivative(Default)]
-#[br(repr = u32)]
+#[brw(repr = u32)]
 pub enum AnimType {
     #[derivative(Default)]
     Rotation = 0,
@@ -104,9 +137,27 @@ pub enum AnimType {
     Hide = 9,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative)]
+impl AnimType {
+    #[must_use]
+    pub const fn is_rotation(self) -> bool {
+        matches!(
+            self,
+            Self::Rotation | Self::RotationX | Self::RotationY | Self::RotationZ
+        )
+    }
+
+    #[must_use]
+    pub const fn is_translation(self) -> bool {
+        matches!(
+            self,
+            Self::Translation | Self::TranslationX | Self::TranslationY | Self::TranslationZ
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, BinWrite, Derivative)]
 #[derivative(Default)]
-#[br(repr = u32)]
+#[brw(repr = u32)]
 pub enum AnimAddress {
     #[derivative(Default)]
     AnimClamp = 0,
@@ -115,16 +166,22 @@ pub enum AnimAddress {
     NAnimAddress = 3,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[binrw]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub struct Bones2Anims {
+    #[br(temp)]
+    #[bw(calc = bone_2_anim_class_list.len() as u32)]
     bone_count: u32,
 
     #[br(count = bone_count)]
     pub bone_2_anim_class_list: Vec<Bone2AnimClassList>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[binrw]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub struct Bone2AnimClassList {
+    #[br(temp)]
+    #[bw(calc = animation_class_index.len() as u32)]
     anim_class_count: u32,
 
     #[br(count = anim_class_count)]
@@ -158,10 +215,7 @@ impl BinRead for Anims2Bones {
 
         for anim_class in animation_classes {
             let skeleton_bone_name_index = i32::read_options(reader, endian, ())?;
-            if skeleton_bone_name_index != -1
-                && anim_class.anim_transform_type != AnimType::Direct
-                && anim_class.anim_transform_type != AnimType::Hide
-            {
+            if has_axis(&anim_class, skeleton_bone_name_index) {
                 let axis_pos = XYZTriplet::read_options(reader, endian, ())?;
                 let axis_dir = XYZTriplet::read_options(reader, endian, ())?;
                 animation_class_indices.push(AnimBones {
@@ -183,3 +237,35 @@ impl BinRead for Anims2Bones {
         })
     }
 }
+
+impl BinWrite for Anims2Bones {
+    type Args<'a> = &'a [AnimationClass];
+
+    /// Writes one entry per animation class, classes without a bone entry get `-1`.
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        animation_classes: Self::Args<'_>,
+    ) -> binrw::BinResult<()> {
+        for (i, anim_class) in animation_classes.iter().enumerate() {
+            let bone = self.animation_class_indices.get(i);
+            let skeleton_bone_name_index = bone.map_or(-1, |b| b.skeleton_bone_name_index);
+            skeleton_bone_name_index.write_options(writer, endian, ())?;
+            if has_axis(anim_class, skeleton_bone_name_index) {
+                let axis_pos = bone.and_then(|b| b.axis_pos).unwrap_or_default();
+                let axis_dir = bone.and_then(|b| b.axis_dir).unwrap_or_default();
+                axis_pos.write_options(writer, endian, ())?;
+                axis_dir.write_options(writer, endian, ())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bones of transforming animations store their axis, direct and hide animations don't.
+fn has_axis(anim_class: &AnimationClass, skeleton_bone_name_index: i32) -> bool {
+    skeleton_bone_name_index != -1
+        && anim_class.anim_transform_type != AnimType::Direct
+        && anim_class.anim_transform_type != AnimType::Hide
+}
@@ -0,0 +1,123 @@
+use super::{LodMaterial, Rvmat, MLOD, ODOL};
+
+const PROXY_PREFIX: &str = "proxy:";
+
+/// External files referenced by a model.
+///
+/// Paths keep the casing of their first occurrence and are deduplicated case insensitive,
+/// leading backslashes are removed.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ModelDependencies {
+    pub textures: Vec<String>,
+    pub materials: Vec<String>,
+    pub surfaces: Vec<String>,
+
+    /// Proxy models with their `.p3d` extension.
+    pub proxies: Vec<String>,
+
+    /// Skeleton name as defined in the `model.cfg`.
+    pub skeleton: Option<String>,
+}
+
+impl ModelDependencies {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn from_odol(odol: &ODOL) -> Self {
+        let mut deps = Self::new();
+
+        for lod in &odol.lods {
+            for texture in &lod.textures {
+                add_path(&mut deps.textures, &texture.to_string());
+            }
+            for material in &lod.materials {
+                deps.add_lod_material(material);
+            }
+            for section in &lod.sections {
+                if let Some(material) = &section.material {
+                    add_path(&mut deps.materials, &material.to_string());
+                }
+            }
+            for proxy in &lod.proxies {
+                add_path(
+                    &mut deps.proxies,
+                    &proxy_path(&proxy.proxy_model.to_string()),
+                );
+            }
+        }
+
+        let skeleton = odol.model_info.skeleton.name.to_string();
+        deps.skeleton = (!skeleton.is_empty()).then_some(skeleton);
+        deps
+    }
+
+    /// Stage textures and surfaces of MLOD materials are only known after adding their rvmats.
+    #[must_use]
+    pub fn from_mlod(mlod: &MLOD) -> Self {
+        let mut deps = Self::new();
+
+        for lod in &mlod.lods {
+            for face in &lod.faces {
+                add_path(&mut deps.textures, &face.texture.to_string());
+                add_path(&mut deps.materials, &face.material.to_string());
+            }
+            for selection in &lod.taggs.named_selections {
+                let name = selection.name.to_string();
+                if let Some(model) = name.strip_prefix(PROXY_PREFIX) {
+                    // Proxy selections end with their index, e.g. `proxy:\a3\gunner.001`
+                    let model = model.rsplit_once('.').map_or(model, |(m, _)| m);
+                    add_path(&mut deps.proxies, &proxy_path(model));
+                }
+            }
+        }
+        deps
+    }
+
+    /// Adds the stage textures and surface of a loaded material.
+    pub fn add_rvmat(&mut self, rvmat: &Rvmat) {
+        for texture in rvmat.textures() {
+            add_path(&mut self.textures, texture);
+        }
+        if let Some(surface) = &rvmat.surface_info {
+            add_path(&mut self.surfaces, surface);
+        }
+    }
+
+    fn add_lod_material(&mut self, material: &LodMaterial) {
+        add_path(&mut self.materials, &material.material_name.to_string());
+        self.add_rvmat(&Rvmat::from(material));
+    }
+
+    /// All referenced files, the skeleton isn't a file and is skipped.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.textures
+            .iter()
+            .chain(&self.materials)
+            .chain(&self.surfaces)
+            .chain(&self.proxies)
+            .map(String::as_str)
+    }
+}
+
+/// Adds a path if it isn't empty, procedural or already known.
+fn add_path(paths: &mut Vec<String>, path: &str) {
+    let path = path.trim().trim_start_matches('\\');
+    // Procedural textures like `#(argb,8,8,3)color(1,1,1,1)` aren't files
+    if path.is_empty() || path.starts_with('#') {
+        return;
+    }
+    if !paths.iter().any(|p| p.eq_ignore_ascii_case(path)) {
+        paths.push(path.to_string());
+    }
+}
+
+fn proxy_path(model: &str) -> String {
+    if model.to_ascii_lowercase().ends_with(".p3d") {
+        model.to_string()
+    } else {
+        format!("{model}.p3d")
+    }
+}
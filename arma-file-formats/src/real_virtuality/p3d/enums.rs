@@ -2,6 +2,7 @@ use std::marker::PhantomData;
 
 use binrw::BinRead;
 use derivative::Derivative;
+use num_enum::TryFromPrimitive;
 
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy, BinRead)]
 pub struct ClipFlags {
@@ -111,9 +112,10 @@ pub enum EMainLight {
 }
 
 #[allow(clippy::enum_clike_unportable_variant)]
-#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative, TryFromPrimitive)]
 #[derivative(Default)]
 #[br(repr = i32)]
+#[repr(i32)]
 pub enum PixelShaderID {
     #[derivative(Default)]
     PSNormal,
@@ -250,6 +252,21 @@ pub enum PixelShaderID {
     PSUninitialized = -1,
 }
 
+impl PixelShaderID {
+    #[must_use]
+    pub fn from_value(value: i32) -> Self {
+        Self::try_from(value).unwrap_or(Self::PSUninitialized)
+    }
+
+    /// Shader by its rvmat name, e.g. `Super` for [`PixelShaderID::PSSuper`].
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..Self::NPixelShaderID as i32)
+            .map(Self::from_value)
+            .find(|ps| matches_name(&format!("{ps:?}"), "PS", name))
+    }
+}
+
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy, BinRead)]
 pub struct VertexShaderID {
     pub value: i32,
@@ -258,6 +275,71 @@ pub struct VertexShaderID {
     pub e: VertexShaderIDEnum,
 }
 
+impl VertexShaderID {
+    #[must_use]
+    pub fn from_value(value: i32) -> Self {
+        let e = usize::try_from(value)
+            .ok()
+            .and_then(|i| VERTEX_SHADERS.get(i))
+            .copied()
+            .unwrap_or_default();
+
+        Self { value, e }
+    }
+
+    /// Shader by its rvmat name, e.g. `Super` for [`VertexShaderIDEnum::VSSuper`].
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        VERTEX_SHADERS
+            .iter()
+            .position(|vs| {
+                *vs != VertexShaderIDEnum::NVertexShaderID
+                    && matches_name(&format!("{vs:?}"), "VS", name)
+            })
+            .map(|i| Self::from_value(i as i32))
+    }
+}
+
+/// Vertex shaders indexed by their value, up to [`VertexShaderIDEnum::NVertexShaderID`].
+const VERTEX_SHADERS: [VertexShaderIDEnum; 36] = [
+    VertexShaderIDEnum::VSBasic,
+    VertexShaderIDEnum::VSNormalMap,
+    VertexShaderIDEnum::VSNormalMapDiffuse,
+    VertexShaderIDEnum::VSGrass,
+    VertexShaderIDEnum::VSDummy1,
+    VertexShaderIDEnum::VSDummy2,
+    VertexShaderIDEnum::VSShadowVolume,
+    VertexShaderIDEnum::VSWater,
+    VertexShaderIDEnum::VSWaterSimple,
+    VertexShaderIDEnum::VSSprite,
+    VertexShaderIDEnum::VSPoint,
+    VertexShaderIDEnum::VSNormalMapThrough,
+    VertexShaderIDEnum::VSDummy3,
+    VertexShaderIDEnum::VSTerrain,
+    VertexShaderIDEnum::VSBasicAS,
+    VertexShaderIDEnum::VSNormalMapAS,
+    VertexShaderIDEnum::VSNormalMapDiffuseAS,
+    VertexShaderIDEnum::VSGlass,
+    VertexShaderIDEnum::VSNormalMapSpecularThrough,
+    VertexShaderIDEnum::VSNormalMapThroughNoFade,
+    VertexShaderIDEnum::VSNormalMapSpecularThroughNoFade,
+    VertexShaderIDEnum::VSShore,
+    VertexShaderIDEnum::VSTerrainGrass,
+    VertexShaderIDEnum::VSSuper,
+    VertexShaderIDEnum::VSMulti,
+    VertexShaderIDEnum::VSTree,
+    VertexShaderIDEnum::VSTreeNoFade,
+    VertexShaderIDEnum::VSTreePRT,
+    VertexShaderIDEnum::VSTreePRTNoFade,
+    VertexShaderIDEnum::VSSkin,
+    VertexShaderIDEnum::VSCalmWater,
+    VertexShaderIDEnum::VSTreeAdv,
+    VertexShaderIDEnum::VSTreeAdvTrunk,
+    VertexShaderIDEnum::VSSimulWeatherClouds,
+    VertexShaderIDEnum::VSSimulWeatherCloudsCPU,
+    VertexShaderIDEnum::NVertexShaderID,
+];
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative)]
 #[derivative(Default)]
 #[br(import { value: i32 })]
@@ -339,9 +421,10 @@ pub enum VertexShaderIDEnum {
     Unknown(PhantomData<f32>),
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative, TryFromPrimitive)]
 #[derivative(Default)]
 #[br(repr = u32)]
+#[repr(u32)]
 pub enum UVSource {
     #[derivative(Default)]
     UVNone,
@@ -366,3 +449,42 @@ pub enum TextureFilterType {
     Triliniear,
     Anisotropic,
 }
+
+impl UVSource {
+    #[must_use]
+    pub fn from_value(value: u32) -> Option<Self> {
+        Self::try_from(value).ok()
+    }
+
+    /// Source by its rvmat name, e.g. `tex` for [`UVSource::UVTex`].
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..Self::NUVSource as u32)
+            .filter_map(Self::from_value)
+            .find(|uv| matches_name(&format!("{uv:?}"), "UV", name))
+    }
+}
+
+impl TextureFilterType {
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Point,
+            Self::Linear,
+            Self::Triliniear,
+            Self::Anisotropic,
+        ]
+        .into_iter()
+        .find(|f| format!("{f:?}").eq_ignore_ascii_case(name))
+        .or_else(|| {
+            name.eq_ignore_ascii_case("trilinear")
+                .then_some(Self::Triliniear)
+        })
+    }
+}
+
+fn matches_name(variant: &str, prefix: &str, name: &str) -> bool {
+    variant
+        .strip_prefix(prefix)
+        .is_some_and(|v| v.eq_ignore_ascii_case(name))
+}
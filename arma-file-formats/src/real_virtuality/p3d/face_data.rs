@@ -1,9 +1,10 @@
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 
 use super::ODOLArgs;
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 #[br(import(args: ODOLArgs))]
+#[bw(import(args: ODOLArgs))]
 pub struct FaceData {
     pub header_face_count: u32,
     pub color: u32,
@@ -12,10 +13,14 @@ pub struct FaceData {
 
     #[br(if(args.version >= 39))]
     #[br(map = |x: u8| Some(x != 0))]
+    #[bw(if(args.version >= 39))]
+    #[bw(map = |x: &Option<bool>| u8::from(x.unwrap_or_default()))]
     pub has_skeleton: Option<bool>,
 
     #[br(if(args.version >= 51))]
+    #[bw(if(args.version >= 51))]
     pub vertices_count: i32,
     #[br(if(args.version >= 51))]
+    #[bw(if(args.version >= 51))]
     pub face_area: f32,
 }
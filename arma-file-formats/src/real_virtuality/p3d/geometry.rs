@@ -0,0 +1,10 @@
+/// Texture path as relative uri with forward slashes, optionally with a different extension.
+pub fn texture_uri(path: &str, extension: Option<&str>) -> String {
+    let uri = path.replace('\\', "/").trim_start_matches('/').to_string();
+    match (extension, uri.rsplit_once('.')) {
+        (Some(extension), Some((stem, _))) => {
+            format!("{stem}.{}", extension.trim_start_matches('.'))
+        }
+        _ => uri,
+    }
+}
@@ -0,0 +1,602 @@
+use std::{collections::HashMap, fs::File, io::Write, path::Path};
+
+use crate::{
+    core::json::Json,
+    errors::AffError,
+    real_virtuality::{paa::TextureSuffix, types::XYZTriplet},
+};
+
+use super::{
+    geometry::texture_uri, Bone, Lod, LodSection, ResolutionEnum, Rvmat, SkeletonTree, ODOL,
+};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: &[u8; 4] = b"JSON";
+const GLB_CHUNK_BIN: &[u8; 4] = b"BIN\0";
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const COMPONENT_UNSIGNED_SHORT: u32 = 5123;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const COMPONENT_FLOAT: u32 = 5126;
+
+const MAX_WEIGHTS: usize = 4;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct GltfOptions {
+    /// Replaces the extension of texture paths, e.g. `png` if the textures were converted beforehand.
+    pub texture_extension: Option<String>,
+
+    /// Export geometry, memory and other non graphical LODs as well.
+    pub all_lods: bool,
+}
+
+/// glTF 2.0 document with a single binary buffer.
+///
+/// Arma is left handed, X is mirrored on export so models keep their orientation with Y up and Z forward.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Gltf {
+    document: Json,
+    pub buffer: Vec<u8>,
+}
+
+impl Gltf {
+    /// Exports every LOD with faces as its own scene, the first one is the default scene.
+    pub fn from_odol(odol: &ODOL, options: &GltfOptions) -> Result<Self, AffError> {
+        let lods: Vec<usize> = (0..odol.lods.len())
+            .filter(|i| {
+                options.all_lods
+                    || odol
+                        .resolutions
+                        .get(*i)
+                        .is_none_or(|r| r.res == ResolutionEnum::GraphicalLod)
+            })
+            .collect();
+
+        Self::build(odol, &lods, options)
+    }
+
+    pub fn from_lod(
+        odol: &ODOL,
+        lod_index: usize,
+        options: &GltfOptions,
+    ) -> Result<Self, AffError> {
+        if lod_index >= odol.lods.len() {
+            return Err(AffError::LodNotFound(lod_index));
+        }
+        Self::build(odol, &[lod_index], options)
+    }
+
+    fn build(odol: &ODOL, lods: &[usize], options: &GltfOptions) -> Result<Self, AffError> {
+        let mut builder = Builder::new(options);
+        let bones = &odol.model_info.skeleton.skeleton_bones;
+
+        for &index in lods {
+            let lod = odol.lods.get(index).ok_or(AffError::LodNotFound(index))?;
+            let name = odol
+                .resolutions
+                .get(index)
+                .map_or_else(|| format!("LOD {index}"), ToString::to_string);
+            builder.add_lod(&name, lod, bones);
+        }
+
+        Ok(builder.finish())
+    }
+
+    /// JSON part of the document, the buffer has no uri as expected by GLB.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        self.with_buffer(None).to_string()
+    }
+
+    /// `.gltf` document with the buffer embedded as a base64 data uri.
+    #[must_use]
+    pub fn to_gltf_embedded(&self) -> String {
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64(&self.buffer)
+        );
+        self.with_buffer(Some(uri)).to_string()
+    }
+
+    #[must_use]
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut json = self.to_json().into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = self.buffer.clone();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let mut glb = Vec::with_capacity(28 + json.len() + bin.len());
+        glb.extend_from_slice(GLB_MAGIC);
+        glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+        glb.extend_from_slice(&((28 + json.len() + bin.len()) as u32).to_le_bytes());
+
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(GLB_CHUNK_JSON);
+        glb.extend_from_slice(&json);
+
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(GLB_CHUNK_BIN);
+        glb.extend_from_slice(&bin);
+
+        glb
+    }
+
+    pub fn write_glb<P: AsRef<Path>>(&self, path: P) -> Result<(), AffError> {
+        let mut file = File::create(path)?;
+        file.write_all(&self.to_glb())?;
+        Ok(())
+    }
+
+    pub fn write_gltf<P: AsRef<Path>>(&self, path: P) -> Result<(), AffError> {
+        let mut file = File::create(path)?;
+        file.write_all(self.to_gltf_embedded().as_bytes())?;
+        Ok(())
+    }
+
+    fn with_buffer(&self, uri: Option<String>) -> Json {
+        let mut document = self.document.clone();
+        if !self.buffer.is_empty() {
+            let mut buffer = Json::object([("byteLength", self.buffer.len().into())]);
+            if let Some(uri) = uri {
+                buffer.insert("uri", uri.into());
+            }
+            document.insert("buffers", Json::Array(vec![buffer]));
+        }
+        document
+    }
+}
+
+struct Builder<'a> {
+    options: &'a GltfOptions,
+    buffer: Vec<u8>,
+    buffer_views: Vec<Json>,
+    accessors: Vec<Json>,
+    meshes: Vec<Json>,
+    materials: Vec<Json>,
+    material_lookup: HashMap<String, usize>,
+    textures: Vec<Json>,
+    images: Vec<Json>,
+    image_lookup: HashMap<String, usize>,
+    nodes: Vec<Json>,
+    skins: Vec<Json>,
+    scenes: Vec<Json>,
+    bone_nodes: Option<(Vec<usize>, Vec<usize>)>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(options: &'a GltfOptions) -> Self {
+        Self {
+            options,
+            buffer: Vec::new(),
+            buffer_views: Vec::new(),
+            accessors: Vec::new(),
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            material_lookup: HashMap::new(),
+            textures: Vec::new(),
+            images: Vec::new(),
+            image_lookup: HashMap::new(),
+            nodes: Vec::new(),
+            skins: Vec::new(),
+            scenes: Vec::new(),
+            bone_nodes: None,
+        }
+    }
+
+    fn add_lod(&mut self, name: &str, lod: &Lod, bones: &[Bone]) {
+        let vertex_count = lod.vertices.len();
+        let mut scene_nodes = Vec::new();
+
+        let has_faces = vertex_count > 0 && !lod.faces.is_empty();
+
+        if has_faces {
+            let skinned = !bones.is_empty() && lod.vertex_bone_ref.len() == vertex_count;
+            let attributes = self.vertex_attributes(lod, skinned);
+
+            let mut primitives = Vec::new();
+            for (section, indices) in lod.section_indices() {
+                if indices.is_empty() {
+                    continue;
+                }
+
+                let index_data: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+                let accessor = self.push_accessor(
+                    &index_data,
+                    TARGET_ELEMENT_ARRAY_BUFFER,
+                    COMPONENT_UNSIGNED_INT,
+                    indices.len(),
+                    "SCALAR",
+                    None,
+                );
+
+                let mut primitive = Json::object([
+                    ("attributes", attributes.clone()),
+                    ("indices", accessor.into()),
+                ]);
+                if let Some(material) = section.and_then(|s| self.material(lod, s)) {
+                    primitive.insert("material", material.into());
+                }
+                primitives.push(primitive);
+            }
+
+            self.meshes.push(Json::object([
+                ("name", name.into()),
+                ("primitives", primitives.into()),
+            ]));
+
+            let mut node = Json::object([
+                ("name", name.into()),
+                ("mesh", (self.meshes.len() - 1).into()),
+            ]);
+
+            if skinned {
+                let (joints, roots) = self.bone_nodes(bones);
+                self.skins.push(Json::object([
+                    ("name", name.into()),
+                    ("joints", Json::array(joints)),
+                ]));
+                node.insert("skin", (self.skins.len() - 1).into());
+                scene_nodes.extend(roots);
+            }
+
+            self.nodes.push(node);
+            scene_nodes.insert(0, self.nodes.len() - 1);
+        }
+
+        self.scenes.push(Json::object([
+            ("name", name.into()),
+            ("nodes", Json::array(scene_nodes)),
+        ]));
+    }
+
+    fn vertex_attributes(&mut self, lod: &Lod, skinned: bool) -> Json {
+        let vertex_count = lod.vertices.len();
+        let mut attributes = Vec::new();
+
+        let positions: Vec<[f32; 3]> = lod.vertices.iter().map(to_gltf_space).collect();
+        let (min, max) = bounds(&positions);
+        let position = self.push_accessor(
+            &flatten(&positions),
+            TARGET_ARRAY_BUFFER,
+            COMPONENT_FLOAT,
+            vertex_count,
+            "VEC3",
+            Some((min.to_vec(), max.to_vec())),
+        );
+        attributes.push(("POSITION".to_string(), position.into()));
+
+        if lod.normals.len() == vertex_count {
+            let normals: Vec<[f32; 3]> = lod.normals.iter().map(to_gltf_space).collect();
+            let normal = self.push_accessor(
+                &flatten(&normals),
+                TARGET_ARRAY_BUFFER,
+                COMPONENT_FLOAT,
+                vertex_count,
+                "VEC3",
+                None,
+            );
+            attributes.push(("NORMAL".to_string(), normal.into()));
+        }
+
+        for (i, uv) in (0..lod.uv_set_count())
+            .filter_map(|i| lod.uv_coords(i))
+            .filter(|uv| uv.len() == vertex_count)
+            .enumerate()
+        {
+            let texcoord = self.push_accessor(
+                &flatten(&uv),
+                TARGET_ARRAY_BUFFER,
+                COMPONENT_FLOAT,
+                vertex_count,
+                "VEC2",
+                None,
+            );
+            attributes.push((format!("TEXCOORD_{i}"), texcoord.into()));
+        }
+
+        if skinned {
+            let (joints, weights) = skin_weights(lod);
+            let joints_data: Vec<u8> = joints
+                .iter()
+                .flatten()
+                .flat_map(|j| j.to_le_bytes())
+                .collect();
+            let joints = self.push_accessor(
+                &joints_data,
+                TARGET_ARRAY_BUFFER,
+                COMPONENT_UNSIGNED_SHORT,
+                vertex_count,
+                "VEC4",
+                None,
+            );
+            let weights = self.push_accessor(
+                &flatten(&weights),
+                TARGET_ARRAY_BUFFER,
+                COMPONENT_FLOAT,
+                vertex_count,
+                "VEC4",
+                None,
+            );
+            attributes.push(("JOINTS_0".to_string(), joints.into()));
+            attributes.push(("WEIGHTS_0".to_string(), weights.into()));
+        }
+
+        Json::Object(attributes)
+    }
+
+    /// Creates the bone nodes once, returns all joint nodes and the root bone nodes.
+    fn bone_nodes(&mut self, bones: &[Bone]) -> (Vec<usize>, Vec<usize>) {
+        if let Some(nodes) = &self.bone_nodes {
+            return nodes.clone();
+        }
+
+        let first = self.nodes.len();
+        let tree = SkeletonTree::from_bones(bones);
+        for bone in &tree.bones {
+            let mut node = Json::object([("name", bone.name.as_str().into())]);
+            if !bone.children.is_empty() {
+                node.insert(
+                    "children",
+                    Json::array(bone.children.iter().map(|c| first + c)),
+                );
+            }
+            self.nodes.push(node);
+        }
+        let roots = tree.roots().map(|b| first + b.index).collect();
+
+        let nodes = ((first..first + bones.len()).collect(), roots);
+        self.bone_nodes = Some(nodes.clone());
+        nodes
+    }
+
+    fn material(&mut self, lod: &Lod, section: &LodSection) -> Option<usize> {
+        let texture = usize::try_from(section.common_texture_index)
+            .ok()
+            .and_then(|i| lod.textures.get(i))
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        let lod_material = usize::try_from(section.material_index)
+            .ok()
+            .and_then(|i| lod.materials.get(i));
+        let material_name = lod_material
+            .map(|m| m.material_name.to_string())
+            .or_else(|| section.material.as_ref().map(ToString::to_string))
+            .unwrap_or_default();
+
+        if texture.is_empty() && material_name.is_empty() {
+            return None;
+        }
+
+        let key = format!("{texture}|{material_name}");
+        if let Some(index) = self.material_lookup.get(&key) {
+            return Some(*index);
+        }
+
+        let mut pbr = Json::object([
+            ("metallicFactor", 0.0_f32.into()),
+            ("roughnessFactor", 1.0_f32.into()),
+        ]);
+        let rvmat = lod_material.map(Rvmat::from);
+        if let Some(rvmat) = &rvmat {
+            let d = rvmat.diffuse;
+            pbr.insert("baseColorFactor", Json::array([d.r, d.g, d.b, d.a]));
+        }
+        if let Some(texture) = self.texture(&texture) {
+            pbr.insert(
+                "baseColorTexture",
+                Json::object([("index", texture.into())]),
+            );
+        }
+
+        let name = if material_name.is_empty() {
+            texture.clone()
+        } else {
+            material_name.clone()
+        };
+        let mut extras = Json::object([
+            ("texture", texture.into()),
+            ("material", material_name.into()),
+        ]);
+        // glTF has no equivalent of the specular / gloss maps, they are kept for the importer
+        if let Some(smdi) = rvmat
+            .as_ref()
+            .and_then(|m| m.texture_of(TextureSuffix::Smdi))
+        {
+            extras.insert("specularTexture", smdi.into());
+        }
+        let mut material = Json::object([
+            ("name", name.into()),
+            ("pbrMetallicRoughness", pbr),
+            ("extras", extras),
+        ]);
+        if let Some(rvmat) = &rvmat {
+            let e = rvmat.emissive;
+            if e.r > 0.0 || e.g > 0.0 || e.b > 0.0 {
+                material.insert("emissiveFactor", Json::array([e.r, e.g, e.b]));
+            }
+            if let Some(normal) = rvmat
+                .texture_of(TextureSuffix::Nohq)
+                .and_then(|t| self.texture(t))
+            {
+                material.insert("normalTexture", Json::object([("index", normal.into())]));
+            }
+        }
+
+        self.materials.push(material);
+        self.material_lookup.insert(key, self.materials.len() - 1);
+        Some(self.materials.len() - 1)
+    }
+
+    fn texture(&mut self, path: &str) -> Option<usize> {
+        // Procedural textures like `#(argb,8,8,3)color(1,1,1,1)` can't be referenced
+        if path.is_empty() || path.starts_with('#') {
+            return None;
+        }
+
+        let uri = texture_uri(path, self.options.texture_extension.as_deref());
+
+        if let Some(index) = self.image_lookup.get(&uri) {
+            return Some(*index);
+        }
+
+        self.images
+            .push(Json::object([("uri", uri.clone().into())]));
+        self.textures
+            .push(Json::object([("source", (self.images.len() - 1).into())]));
+        self.image_lookup.insert(uri, self.textures.len() - 1);
+        Some(self.textures.len() - 1)
+    }
+
+    fn push_accessor(
+        &mut self,
+        data: &[u8],
+        target: u32,
+        component_type: u32,
+        count: usize,
+        kind: &str,
+        bounds: Option<(Vec<f32>, Vec<f32>)>,
+    ) -> usize {
+        self.buffer.resize(self.buffer.len().next_multiple_of(4), 0);
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(data);
+
+        self.buffer_views.push(Json::object([
+            ("buffer", 0_u32.into()),
+            ("byteOffset", offset.into()),
+            ("byteLength", data.len().into()),
+            ("target", target.into()),
+        ]));
+
+        let mut accessor = Json::object([
+            ("bufferView", (self.buffer_views.len() - 1).into()),
+            ("componentType", component_type.into()),
+            ("count", count.into()),
+            ("type", kind.into()),
+        ]);
+        if let Some((min, max)) = bounds {
+            accessor.insert("min", Json::array(min));
+            accessor.insert("max", Json::array(max));
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn finish(self) -> Gltf {
+        let mut document = Json::object([(
+            "asset",
+            Json::object([
+                ("version", "2.0".into()),
+                ("generator", "arma-file-formats".into()),
+            ]),
+        )]);
+
+        if !self.scenes.is_empty() {
+            document.insert("scene", 0_u32.into());
+        }
+        for (key, values) in [
+            ("scenes", self.scenes),
+            ("nodes", self.nodes),
+            ("meshes", self.meshes),
+            ("skins", self.skins),
+            ("materials", self.materials),
+            ("textures", self.textures),
+            ("images", self.images),
+            ("accessors", self.accessors),
+            ("bufferViews", self.buffer_views),
+        ] {
+            if !values.is_empty() {
+                document.insert(key, Json::Array(values));
+            }
+        }
+
+        Gltf {
+            document,
+            buffer: self.buffer,
+        }
+    }
+}
+
+const fn to_gltf_space(v: &XYZTriplet) -> [f32; 3] {
+    [-v.x, v.y, v.z]
+}
+
+fn flatten<const N: usize>(values: &[[f32; N]]) -> Vec<u8> {
+    values
+        .iter()
+        .flatten()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+fn bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in positions {
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    (min, max)
+}
+
+/// Up to four skeleton bone indices and normalized weights per vertex.
+fn skin_weights(lod: &Lod) -> (Vec<[u16; MAX_WEIGHTS]>, Vec<[f32; MAX_WEIGHTS]>) {
+    lod.vertex_bone_ref
+        .iter()
+        .map(|weight| {
+            let mut joints = [0_u16; MAX_WEIGHTS];
+            let mut weights = [0_f32; MAX_WEIGHTS];
+
+            for (i, pair) in weight
+                .animation_rt_pairs
+                .iter()
+                .take(MAX_WEIGHTS)
+                .enumerate()
+            {
+                joints[i] = lod
+                    .lod_items
+                    .get(pair.selection_index as usize)
+                    .copied()
+                    .unwrap_or_default() as u16;
+                weights[i] = f32::from(pair.weight) / 255.0;
+            }
+
+            let sum: f32 = weights.iter().sum();
+            if sum > f32::EPSILON {
+                for w in &mut weights {
+                    *w /= sum;
+                }
+            } else {
+                weights = [1.0, 0.0, 0.0, 0.0];
+            }
+
+            (joints, weights)
+        })
+        .unzip()
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or_default(),
+            chunk.get(2).copied().unwrap_or_default(),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - i * 6) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
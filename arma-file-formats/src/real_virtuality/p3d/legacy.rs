@@ -0,0 +1,298 @@
+use binrw::{binread, BinRead, NullString};
+
+use crate::real_virtuality::{
+    binrw_utils::{read_compressed, read_compressed_array, read_vertex_index_array},
+    types::{RGBAColor, TransformMatrix, XYZTriplet},
+};
+
+use super::{
+    Lod, LodEdges, LodFace, LodFrame, LodNameSelection, LodNamedProperty, LodSection, ModelInfo,
+    ODOLArgs, Proxy,
+};
+
+/// LOD of the layout used before v28, e.g. by the v7 models of OFP.
+///
+/// Arrays of at least 1024 bytes are LZSS compressed.
+#[binread]
+#[derive(Debug, Default, PartialEq, Clone)]
+#[br(import(args: ODOLArgs))]
+pub struct LegacyLod {
+    #[br(args(4, args))]
+    #[br(parse_with = read_compressed_array)]
+    pub point_flags: Vec<i32>,
+
+    /// Float UV pairs, one per vertex.
+    #[br(args(8, args))]
+    #[br(parse_with = read_compressed)]
+    pub uv_data: Vec<u8>,
+
+    #[br(args(12, args))]
+    #[br(parse_with = read_compressed_array)]
+    pub vertices: Vec<XYZTriplet>,
+
+    #[br(args(12, args))]
+    #[br(parse_with = read_compressed_array)]
+    pub normals: Vec<XYZTriplet>,
+
+    pub or_hints: i32,
+    pub and_hints: i32,
+    pub b_min: XYZTriplet,
+    pub b_max: XYZTriplet,
+    pub b_center: XYZTriplet,
+    pub b_radius: f32,
+
+    #[br(temp)]
+    texture_count: u32,
+    #[br(count = texture_count)]
+    pub textures: Vec<NullString>,
+
+    #[br(args(args))]
+    pub lod_edges: LodEdges,
+
+    #[br(temp)]
+    face_count: u32,
+    #[br(temp)]
+    offset_to_sections: u32,
+
+    #[br(count = face_count)]
+    #[br(args { inner: (args,) })]
+    pub faces: Vec<LegacyFace>,
+
+    #[br(temp)]
+    section_count: u32,
+    #[br(count = section_count)]
+    pub sections: Vec<LegacySection>,
+
+    #[br(temp)]
+    named_selection_count: u32,
+    #[br(count = named_selection_count)]
+    #[br(args { inner: (args,) })]
+    pub named_selection: Vec<LodNameSelection>,
+
+    #[br(temp)]
+    named_properties_count: u32,
+    #[br(count = named_properties_count)]
+    pub named_properties: Vec<LodNamedProperty>,
+
+    #[br(temp)]
+    frame_count: u32,
+    #[br(count = frame_count)]
+    pub frames: Vec<LodFrame>,
+
+    pub icon_color: u32,
+    pub selected_color: u32,
+    pub special: u32,
+
+    #[br(temp)]
+    proxy_count: u32,
+    #[br(count = proxy_count)]
+    pub proxies: Vec<LegacyProxy>,
+}
+
+/// Faces still carry their texture and flags, sections repeat them for ranges of faces.
+#[binread]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[br(import(args: ODOLArgs))]
+pub struct LegacyFace {
+    pub flags: u32,
+    pub texture_index: i16,
+
+    #[br(temp)]
+    face_type: u8,
+    #[br(args(args, face_type as usize))]
+    #[br(parse_with = read_vertex_index_array)]
+    pub vertex_indices: Vec<u32>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+pub struct LegacySection {
+    /// Index of the first face, unlike the face data offsets of [`LodSection`].
+    pub face_lower_index: u32,
+    pub face_upper_index: u32,
+
+    pub common_point_user_value: u32,
+    pub common_texture_index: i16,
+    pub common_face_flag: u32,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+pub struct LegacyProxy {
+    pub proxy_model: NullString,
+    pub transofrmation: TransformMatrix,
+    pub sequence_id: i32,
+    pub named_selection_index: i32,
+}
+
+/// Model info of the layout used before v28, stored after the LOD resolutions.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+pub struct LegacyModelInfo {
+    pub index: u32,
+    pub mem_lod_sphere: f32,
+    pub geo_lod_sphere: f32,
+    pub remarks: u32,
+    pub and_hints: u32,
+    pub or_hints: u32,
+    pub aiming_center: XYZTriplet,
+    pub map_icon_color: RGBAColor,
+    pub map_selected_color: RGBAColor,
+    pub view_density: f32,
+    pub bbox_min_pos: XYZTriplet,
+    pub bbox_max_pos: XYZTriplet,
+    pub bounding_center: XYZTriplet,
+    pub geometry_center: XYZTriplet,
+    pub center_of_mass: XYZTriplet,
+
+    #[br(count = 3)]
+    pub inv_intertia: Vec<XYZTriplet>,
+
+    #[br(map = |x: u8| x != 0)]
+    pub auto_center: bool,
+    #[br(map = |x: u8| x != 0)]
+    pub lock_auto_center: bool,
+    #[br(map = |x: u8| x != 0)]
+    pub can_occlude: bool,
+    #[br(map = |x: u8| x != 0)]
+    pub can_be_occlude: bool,
+    #[br(map = |x: u8| x != 0)]
+    pub animated: bool,
+
+    #[br(args(4, ODOLArgs::default()))]
+    #[br(parse_with = read_compressed_array)]
+    pub mass_array: Vec<f32>,
+
+    pub mass: f32,
+    pub mass_reciprocal: f32,
+    pub alt_mass: f32,
+    pub alt_mass_reciprocal: f32,
+
+    pub memory: u8,
+    pub geometry: u8,
+    pub geometry_fire: u8,
+    pub geometry_view: u8,
+    pub geometry_view_pilot: u8,
+    pub geometry_view_gunner: u8,
+    pub unknown_signedbyte: i8,
+    pub geometry_view_cargo: u8,
+    pub land_contact: u8,
+    pub roadway: u8,
+    pub paths: u8,
+    pub hitpoints: u8,
+}
+
+impl From<LegacyLod> for Lod {
+    /// Sections are converted to face data offsets, proxies are not attached to bones.
+    fn from(legacy: LegacyLod) -> Self {
+        let faces: Vec<LodFace> = legacy
+            .faces
+            .into_iter()
+            .map(|face| LodFace {
+                face_type: face.vertex_indices.len() as u8,
+                vertex_indices: face.vertex_indices,
+            })
+            .collect();
+
+        let mut offsets = Vec::with_capacity(faces.len() + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+        for face in &faces {
+            offset += face.memory_size(2);
+            offsets.push(offset);
+        }
+        let face_offset = |face: u32| offsets.get(face as usize).copied().unwrap_or(offset);
+
+        let mut lod = Self::default();
+        lod.sections = legacy
+            .sections
+            .into_iter()
+            .map(|section| LodSection {
+                short_indices: true,
+                face_lower_index: face_offset(section.face_lower_index),
+                face_upper_index: face_offset(section.face_upper_index),
+                common_point_user_value: section.common_point_user_value,
+                common_texture_index: section.common_texture_index,
+                common_face_flag: section.common_face_flag,
+                material_index: -1,
+                ..Default::default()
+            })
+            .collect();
+        lod.faces = faces;
+        lod.proxies = legacy
+            .proxies
+            .into_iter()
+            .map(|proxy| Proxy {
+                proxy_model: proxy.proxy_model,
+                transofrmation: proxy.transofrmation,
+                sequence_id: proxy.sequence_id,
+                named_selection_index: proxy.named_selection_index,
+                bone_index: -1,
+                section_index: 0,
+            })
+            .collect();
+        lod.clip_old_format = Some(legacy.point_flags);
+        lod.default_uv_set.uv_data = Some(legacy.uv_data);
+        lod.vertices = legacy.vertices;
+        lod.normals = legacy.normals;
+        lod.or_hints = legacy.or_hints;
+        lod.and_hints = legacy.and_hints;
+        lod.b_min = legacy.b_min;
+        lod.b_max = legacy.b_max;
+        lod.b_center = legacy.b_center;
+        lod.b_radius = legacy.b_radius;
+        lod.textures = legacy.textures;
+        lod.lod_edges = legacy.lod_edges;
+        lod.named_selection = legacy.named_selection;
+        lod.named_properties = legacy.named_properties;
+        lod.frames = legacy.frames;
+        lod.icon_color = legacy.icon_color;
+        lod.selected_color = legacy.selected_color;
+        lod.special = legacy.special;
+        lod
+    }
+}
+
+impl From<LegacyModelInfo> for ModelInfo {
+    fn from(legacy: LegacyModelInfo) -> Self {
+        Self {
+            index: legacy.index,
+            mem_lod_sphere: legacy.mem_lod_sphere,
+            geo_lod_sphere: legacy.geo_lod_sphere,
+            remarks: legacy.remarks,
+            and_hints: legacy.and_hints,
+            or_hints: legacy.or_hints,
+            aiming_center: legacy.aiming_center,
+            map_icon_color: legacy.map_icon_color,
+            map_selected_color: legacy.map_selected_color,
+            view_density: legacy.view_density,
+            bbox_min_pos: legacy.bbox_min_pos,
+            bbox_max_pos: legacy.bbox_max_pos,
+            bounding_center: legacy.bounding_center,
+            geometry_center: legacy.geometry_center,
+            center_of_mass: legacy.center_of_mass,
+            inv_intertia: legacy.inv_intertia,
+            auto_center: legacy.auto_center,
+            lock_auto_center: legacy.lock_auto_center,
+            can_occlude: legacy.can_occlude,
+            can_be_occlude: legacy.can_be_occlude,
+            animated: legacy.animated,
+            mass_array: legacy.mass_array,
+            mass: legacy.mass,
+            mass_reciprocal: legacy.mass_reciprocal,
+            alt_mass: legacy.alt_mass,
+            alt_mass_reciprocal: legacy.alt_mass_reciprocal,
+            memory: legacy.memory,
+            geometry: legacy.geometry,
+            geometry_fire: legacy.geometry_fire,
+            geometry_view: legacy.geometry_view,
+            geometry_view_pilot: legacy.geometry_view_pilot,
+            geometry_view_gunner: legacy.geometry_view_gunner,
+            unknown_signedbyte: legacy.unknown_signedbyte,
+            geometry_view_cargo: legacy.geometry_view_cargo,
+            land_contact: legacy.land_contact,
+            roadway: legacy.roadway,
+            paths: legacy.paths,
+            hitpoints: legacy.hitpoints,
+            ..Default::default()
+        }
+    }
+}
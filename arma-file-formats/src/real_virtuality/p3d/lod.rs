@@ -1,11 +1,16 @@
+use std::io::{Seek, SeekFrom, Write};
+
 use crate::real_virtuality::{
     binrw_utils::{
-        decompress_array, read_compressed, read_compressed_array, read_compressed_size_cond,
-        read_condensed_array_cond, read_normals_parse, read_st_parse, read_vertex_index_array,
+        compress_array, decompress_array, read_compressed, read_compressed_array,
+        read_compressed_size_cond, read_condensed_array_cond, read_normals_parse, read_st_parse,
+        read_vertex_index_array, write_compressed, write_compressed_array,
+        write_compressed_size_cond, write_condensed_array, write_normals, write_st,
+        write_vertex_index_array,
     },
     types::{D3DColorValue, STPair, TransformMatrix, XYZTriplet},
 };
-use binrw::{BinRead, BinResult, NullString};
+use binrw::{binrw, BinRead, BinResult, BinWrite, Endian, NullString};
 
 use super::ODOLArgs;
 
@@ -134,8 +139,398 @@ pub struct Lod {
     unk_end_2: Option<u8>,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+/// Counts are taken from the written vectors, `offset_to_sections` and `size_of_rest_data` are
+/// recalculated.
+impl BinWrite for Lod {
+    type Args<'a> = (ODOLArgs,);
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: Endian,
+        (args,): Self::Args<'_>,
+    ) -> BinResult<()> {
+        (self.proxies.len() as i32).write_options(writer, endian, ())?;
+        for proxy in &self.proxies {
+            proxy.write_options(writer, endian, (args,))?;
+        }
+
+        (self.lod_items.len() as u32).write_options(writer, endian, ())?;
+        self.lod_items.write_options(writer, endian, ())?;
+
+        (self.bone_links.len() as u32).write_options(writer, endian, ())?;
+        self.bone_links.write_options(writer, endian, ())?;
+
+        if args.version >= 50 {
+            (self.vertices.len() as u32).write_options(writer, endian, ())?;
+        } else {
+            let clip = self.clip_old_format.as_deref().unwrap_or_default();
+            write_condensed_array(writer, endian, clip, args)?;
+        }
+
+        if args.version >= 51 {
+            self.face_area
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+        }
+
+        self.or_hints.write_options(writer, endian, ())?;
+        self.and_hints.write_options(writer, endian, ())?;
+        self.b_min.write_options(writer, endian, ())?;
+        self.b_max.write_options(writer, endian, ())?;
+        self.b_center.write_options(writer, endian, ())?;
+        self.b_radius.write_options(writer, endian, ())?;
+
+        (self.textures.len() as u32).write_options(writer, endian, ())?;
+        self.textures.write_options(writer, endian, ())?;
+
+        (self.materials.len() as u32).write_options(writer, endian, ())?;
+        self.materials.write_options(writer, endian, ())?;
+
+        self.lod_edges.write_options(writer, endian, (args,))?;
+
+        (self.faces.len() as u32).write_options(writer, endian, ())?;
+        // Size of the faces in memory, the same layout the section bounds refer to
+        let index_size = if args.version >= 69 { 4 } else { 2 };
+        self.faces
+            .iter()
+            .map(|face| face.memory_size(index_size))
+            .sum::<u32>()
+            .write_options(writer, endian, ())?;
+        self.always_zero.write_options(writer, endian, ())?;
+        for face in &self.faces {
+            face.write_options(writer, endian, (args,))?;
+        }
+
+        (self.sections.len() as u32).write_options(writer, endian, ())?;
+        for section in &self.sections {
+            section.write_options(writer, endian, (args,))?;
+        }
+
+        (self.named_selection.len() as u32).write_options(writer, endian, ())?;
+        for selection in &self.named_selection {
+            selection.write_options(writer, endian, (args,))?;
+        }
+
+        (self.named_properties.len() as u32).write_options(writer, endian, ())?;
+        self.named_properties.write_options(writer, endian, ())?;
+
+        (self.frames.len() as u32).write_options(writer, endian, ())?;
+        self.frames.write_options(writer, endian, ())?;
+
+        self.icon_color.write_options(writer, endian, ())?;
+        self.selected_color.write_options(writer, endian, ())?;
+        self.special.write_options(writer, endian, ())?;
+        u8::from(self.vertex_bone_ref_is_simple).write_options(writer, endian, ())?;
+
+        // Patched once the rest of the LOD is written, counts the bytes up to the end of the LOD
+        let size_pos = writer.stream_position()?;
+        0_u32.write_options(writer, endian, ())?;
+
+        if args.version >= 50 {
+            let clip = self.clip.as_deref().unwrap_or_default();
+            write_condensed_array(writer, endian, clip, args)?;
+        }
+
+        let vertex_count = self.vertices.len() as u32;
+        self.default_uv_set
+            .write_options(writer, endian, (args, vertex_count))?;
+        (self.uv_sets.len() as u32 + 1).write_options(writer, endian, ())?;
+        for uv_set in &self.uv_sets {
+            uv_set.write_options(writer, endian, (args, vertex_count))?;
+        }
+
+        write_compressed_array(&self.vertices, writer, endian, (args,))?;
+        write_normals(writer, endian, &self.normals, args)?;
+        write_st(writer, endian, &self.st_coords, args)?;
+        write_compressed_array(&self.vertex_bone_ref, writer, endian, (args,))?;
+        write_compressed_array(&self.neighbour_bone_ref, writer, endian, (args,))?;
+
+        if args.version >= 67 {
+            self.unk_end
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+        }
+        if args.version >= 68 {
+            self.unk_end_2
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+        }
+
+        let end_pos = writer.stream_position()?;
+        writer.seek(SeekFrom::Start(size_pos))?;
+        ((end_pos - size_pos - 4) as u32).write_options(writer, endian, ())?;
+        writer.seek(SeekFrom::Start(end_pos))?;
+
+        Ok(())
+    }
+}
+
+impl Lod {
+    /// Faces per section, a single unsectioned range if the LOD has no sections.
+    ///
+    /// Section bounds are offsets into the face data, every face taking `(vertex count + 1) * index size` bytes.
+    #[must_use]
+    pub fn section_faces(&self) -> Vec<(Option<&LodSection>, Vec<usize>)> {
+        if self.sections.is_empty() {
+            return vec![(None, (0..self.faces.len()).collect())];
+        }
+
+        let index_size = if self.sections[0].short_indices { 2 } else { 4 };
+        let mut offsets = Vec::with_capacity(self.faces.len());
+        let mut offset = 0_u32;
+        for face in &self.faces {
+            offsets.push(offset);
+            offset += face.memory_size(index_size);
+        }
+
+        self.sections
+            .iter()
+            .map(|section| {
+                let faces = offsets
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, o)| {
+                        (section.face_lower_index..section.face_upper_index).contains(o)
+                    })
+                    .map(|(i, _)| i)
+                    .collect();
+                (Some(section), faces)
+            })
+            .collect()
+    }
+
+    /// Triangle list for the given faces, quads are split along their first diagonal.
+    #[must_use]
+    pub fn triangulate(&self, faces: &[usize]) -> Vec<u32> {
+        let mut indices = Vec::with_capacity(faces.len() * 6);
+        for face in faces.iter().filter_map(|i| self.faces.get(*i)) {
+            let v = &face.vertex_indices;
+            match v.len() {
+                3 => indices.extend_from_slice(&[v[0], v[1], v[2]]),
+                4 => indices.extend_from_slice(&[v[0], v[1], v[2], v[0], v[2], v[3]]),
+                _ => {}
+            }
+        }
+        indices
+    }
+
+    /// Triangle list of every section, see [`Lod::section_faces`].
+    #[must_use]
+    pub fn section_indices(&self) -> Vec<(Option<&LodSection>, Vec<u32>)> {
+        self.section_faces()
+            .into_iter()
+            .map(|(section, faces)| (section, self.triangulate(&faces)))
+            .collect()
+    }
+
+    /// UV set by index, `0` being the default UV set.
+    #[must_use]
+    pub fn uv_set(&self, index: usize) -> Option<&UVSet> {
+        match index {
+            0 => Some(&self.default_uv_set),
+            i => self.uv_sets.get(i - 1),
+        }
+    }
+
+    /// Number of UV sets including the default one.
+    #[must_use]
+    pub const fn uv_set_count(&self) -> usize {
+        self.uv_sets.len() + 1
+    }
+
+    /// Dequantized UV coordinates of a UV set, `0` being the default UV set.
+    #[must_use]
+    pub fn uv_coords(&self, index: usize) -> Option<Vec<[f32; 2]>> {
+        self.uv_set(index)
+            .map(|set| set.coords(self.vertices.len()))
+    }
+
+    /// Position, normal, UVs and bone weights per vertex.
+    ///
+    /// Attributes that don't cover every vertex are left out.
+    pub fn vertices_iter(&self) -> impl Iterator<Item = LodVertex<'_>> {
+        let vertex_count = self.vertices.len();
+        let uv_sets: Vec<Vec<[f32; 2]>> = (0..self.uv_set_count())
+            .filter_map(|i| self.uv_coords(i))
+            .filter(|uvs| uvs.len() == vertex_count)
+            .collect();
+        let has_normals = self.normals.len() == vertex_count;
+        let has_weights = self.vertex_bone_ref.len() == vertex_count;
+
+        self.vertices
+            .iter()
+            .enumerate()
+            .map(move |(index, position)| LodVertex {
+                index,
+                position: *position,
+                normal: has_normals.then(|| self.normals[index]),
+                uvs: uv_sets.iter().map(|uvs| uvs[index]).collect(),
+                bone_weights: if has_weights {
+                    &self.vertex_bone_ref[index].animation_rt_pairs
+                } else {
+                    &[]
+                },
+            })
+    }
+}
+
+impl Lod {
+    /// Skeleton bone of a LOD bone, as referenced by [`AnimationRTPair::selection_index`].
+    #[must_use]
+    pub fn skeleton_bone(&self, lod_bone: usize) -> Option<usize> {
+        self.lod_items.get(lod_bone).map(|b| *b as usize)
+    }
+
+    /// LOD bones of a skeleton bone, empty if the bone isn't used by this LOD.
+    #[must_use]
+    pub fn lod_bones(&self, skeleton_bone: usize) -> &[u32] {
+        self.bone_links
+            .get(skeleton_bone)
+            .map_or(&[], |link| link.values.as_slice())
+    }
+
+    /// Skeleton bones of a section, sections reference a range of LOD bones starting at `min_bone_index`.
+    #[must_use]
+    pub fn section_skeleton_bones(&self, section: &LodSection) -> Vec<usize> {
+        let start = section.min_bone_index as usize;
+        (start..start + section.bone_count as usize)
+            .filter_map(|b| self.skeleton_bone(b))
+            .collect()
+    }
+}
+
+impl Lod {
+    /// Named selection by case insensitive name.
+    #[must_use]
+    pub fn named_selection(&self, name: &str) -> Option<&LodNameSelection> {
+        self.named_selection
+            .iter()
+            .find(|s| s.name.to_string().eq_ignore_ascii_case(name))
+    }
+
+    /// Faces and weighted vertices of a named selection, e.g. `zasleh`.
+    #[must_use]
+    pub fn selection(&self, name: &str) -> Option<LodSelection<'_>> {
+        self.named_selection(name).map(LodSelection::from)
+    }
+
+    /// Proxies with their selection names, the transform places the proxy model in the model space.
+    #[must_use]
+    pub fn proxies(&self) -> Vec<LodProxy<'_>> {
+        self.proxies
+            .iter()
+            .map(|proxy| LodProxy {
+                model: proxy.proxy_model.to_string(),
+                selection: usize::try_from(proxy.named_selection_index)
+                    .ok()
+                    .and_then(|i| self.named_selection.get(i))
+                    .map(|s| s.name.to_string()),
+                transform: proxy.transofrmation,
+                bone: usize::try_from(proxy.bone_index).ok(),
+                proxy,
+            })
+            .collect()
+    }
+
+    /// Named property value by case insensitive name, e.g. `autocenter` or `lodnoshadow`.
+    #[must_use]
+    pub fn named_property(&self, name: &str) -> Option<String> {
+        self.named_properties
+            .iter()
+            .find(|p| p.property.to_string().eq_ignore_ascii_case(name))
+            .map(|p| p.value.to_string())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct LodSelection<'a> {
+    pub name: String,
+    pub faces: &'a [u32],
+
+    /// Vertex indices with their weight between 0 and 1.
+    pub vertices: Vec<(u32, f32)>,
+
+    pub is_sectional: bool,
+    /// Sections fully covered by the selection.
+    pub sections: &'a [i32],
+}
+
+impl<'a> From<&'a LodNameSelection> for LodSelection<'a> {
+    fn from(selection: &'a LodNameSelection) -> Self {
+        let weights = &selection.selected_vertices_weights;
+        let vertices = selection
+            .selected_vertices
+            .edges
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                // Selections without weights select their vertices fully
+                let weight = weights.get(i).map_or(1.0, |w| f32::from(*w) / 255.0);
+                (*v, weight)
+            })
+            .collect();
+
+        Self {
+            name: selection.name.to_string(),
+            faces: &selection.selected_faces.edges,
+            vertices,
+            is_sectional: selection.is_sectional,
+            sections: &selection.vertex_indices,
+        }
+    }
+}
+
+impl LodSelection<'_> {
+    #[must_use]
+    pub fn contains_face(&self, face: u32) -> bool {
+        self.faces.contains(&face)
+    }
+
+    /// Weight of a vertex, 0 if it isn't selected.
+    #[must_use]
+    pub fn vertex_weight(&self, vertex: u32) -> f32 {
+        self.vertices
+            .iter()
+            .find(|(v, _)| *v == vertex)
+            .map_or(0.0, |(_, w)| *w)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LodProxy<'a> {
+    pub model: String,
+    pub selection: Option<String>,
+    pub transform: TransformMatrix,
+    /// Skeleton bone the proxy is attached to.
+    pub bone: Option<usize>,
+
+    pub proxy: &'a Proxy,
+}
+
+impl LodProxy<'_> {
+    #[must_use]
+    pub const fn position(&self) -> XYZTriplet {
+        self.transform.3
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct LodVertex<'a> {
+    pub index: usize,
+    pub position: XYZTriplet,
+    pub normal: Option<XYZTriplet>,
+
+    /// One coordinate per UV set, starting with the default UV set.
+    pub uvs: Vec<[f32; 2]>,
+
+    /// Selection indices into [`Lod::lod_items`] with weights.
+    pub bone_weights: &'a [AnimationRTPair],
+}
+
+#[derive(Debug, Default, PartialEq, Clone, BinRead, BinWrite)]
 #[br(import(args: ODOLArgs))]
+#[bw(import(args: ODOLArgs))]
 pub struct Proxy {
     pub proxy_model: NullString,
     pub transofrmation: TransformMatrix,
@@ -144,18 +539,22 @@ pub struct Proxy {
     pub bone_index: i32,
 
     #[br(if(args.version >= 40))]
+    #[bw(if(args.version >= 40))]
     pub section_index: i32,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[binrw]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub struct BoneLink {
+    #[br(temp)]
+    #[bw(calc = values.len() as u32)]
     link_count: u32,
 
     #[br(count = link_count)]
     pub values: Vec<u32>,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, BinRead, BinWrite)]
 pub struct LodMaterial {
     pub material_name: NullString,
 
@@ -183,24 +582,37 @@ pub struct LodMaterial {
 
     #[br(if(version == 3))]
     #[br(map = |x: u8| Some(x != 0))]
+    #[bw(if(*version == 3))]
+    #[bw(map = |x: &Option<bool>| u8::from(x.unwrap_or_default()))]
     unk_bool: Option<bool>,
 
     #[br(if(version >= 6))]
+    #[bw(if(*version >= 6))]
+    #[bw(map = |x: &Option<NullString>| x.clone().unwrap_or_default())]
     pub surface_file: Option<NullString>,
 
     #[br(if(version >= 4))]
+    #[bw(if(*version >= 4))]
+    #[bw(map = |x: &Option<u32>| x.unwrap_or_default())]
     pub n_render_flags: Option<u32>,
     #[br(if(version >= 4))]
+    #[bw(if(*version >= 4))]
+    #[bw(map = |x: &Option<u32>| x.unwrap_or_default())]
     pub render_flags: Option<u32>,
 
     #[br(if(version > 6))]
+    #[bw(if(*version > 6))]
+    #[bw(map = |_: &u32| stage_textures.len() as u32)]
     pub texture_count: u32,
 
     #[br(if(version > 8))]
+    #[bw(if(*version > 8))]
+    #[bw(map = |_: &u32| stage_transforms.len() as u32)]
     pub transform_count: u32,
 
     #[br(count = texture_count)]
     #[br(args { inner: (version,) })]
+    #[bw(args(*version,))]
     pub stage_textures: Vec<StageTexture>,
 
     #[br(count = transform_count)]
@@ -208,38 +620,47 @@ pub struct LodMaterial {
 
     #[br(if(version >= 10))]
     #[br(args(version,))]
+    #[bw(args(*version,))]
     pub dummy_stage_textures: Option<StageTexture>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 #[br(import(mat_version: u32))]
+#[bw(import(mat_version: u32))]
 pub struct StageTexture {
     #[br(if(mat_version >= 5))]
+    #[bw(if(mat_version >= 5))]
+    #[bw(map = |x: &Option<u32>| x.unwrap_or_default())]
     pub render_flags: Option<u32>,
 
     pub texture: NullString,
 
     #[br(if(mat_version >= 8))]
+    #[bw(if(mat_version >= 8))]
+    #[bw(map = |x: &Option<u32>| x.unwrap_or_default())]
     pub stage_id: Option<u32>,
 
     #[br(if(mat_version >= 11))]
     #[br(map = |x: u8| Some(x != 0))]
+    #[bw(if(mat_version >= 11))]
+    #[bw(map = |x: &Option<bool>| u8::from(x.unwrap_or_default()))]
     pub use_world_env: Option<bool>,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 pub struct StageTransform {
     pub uv_source: u32,
     pub transformation: TransformMatrix,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 #[br(import(args: ODOLArgs))]
+#[bw(import(args: ODOLArgs))]
 pub struct LodEdges {
-    #[br(args_raw(args))]
+    #[brw(args_raw(args))]
     pub mlod_index: CompressedVertexIndexArray,
 
-    #[br(args_raw(args))]
+    #[brw(args_raw(args))]
     pub vertex_index: CompressedVertexIndexArray,
 }
 
@@ -270,20 +691,52 @@ impl BinRead for CompressedVertexIndexArray {
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+impl BinWrite for CompressedVertexIndexArray {
+    type Args<'a> = ODOLArgs;
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        (self.edges.len() as u32).write_options(writer, endian, ())?;
+        if args.version >= 69 {
+            compress_array(writer, endian, &self.edges, args)
+        } else {
+            let edges: Vec<u16> = self.edges.iter().map(|e| *e as u16).collect();
+            compress_array(writer, endian, &edges, args)
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 #[br(import(args: ODOLArgs))]
+#[bw(import(args: ODOLArgs))]
 pub struct LodFace {
+    #[bw(map = |_: &u8| vertex_indices.len() as u8)]
     pub face_type: u8,
 
     #[br(args(args, face_type as usize))]
     #[br(parse_with = read_vertex_index_array)]
+    #[bw(args(args))]
+    #[bw(write_with = write_vertex_index_array)]
     pub vertex_indices: Vec<u32>,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+impl LodFace {
+    /// Bytes taken by the face in the engine, the vertex count is stored with the index size.
+    pub(crate) const fn memory_size(&self, index_size: u32) -> u32 {
+        (self.vertex_indices.len() as u32 + 1) * index_size
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, BinRead, BinWrite)]
 #[br(import(args: ODOLArgs))]
+#[bw(import(args: ODOLArgs))]
 pub struct LodSection {
     #[br(calc = args.version < 69)]
+    #[bw(ignore)]
     pub short_indices: bool,
 
     pub face_lower_index: u32,
@@ -298,9 +751,12 @@ pub struct LodSection {
     pub material_index: i32,
 
     #[br(if(material_index == -1))]
+    #[bw(if(*material_index == -1))]
+    #[bw(map = |x: &Option<NullString>| x.clone().unwrap_or_default())]
     pub material: Option<NullString>,
 
     #[br(if(args.version >= 36, 1))]
+    #[bw(if(args.version >= 36))]
     pub stage_count: u32,
 
     #[br(count = stage_count)]
@@ -308,92 +764,175 @@ pub struct LodSection {
 
     #[br(if(args.version >= 67))]
     #[br(map = |x: i32| x >= 1)]
+    #[bw(if(args.version >= 67))]
+    #[bw(map = |x: &bool| i32::from(*x))]
     pub unk_matrix_exists: bool,
 
     #[br(if(args.version >= 67 && unk_matrix_exists))]
+    #[bw(if(args.version >= 67 && *unk_matrix_exists))]
     pub unk_matrix: TransformMatrix,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 #[br(import(args: ODOLArgs))]
+#[bw(import(args: ODOLArgs))]
 pub struct LodNameSelection {
     pub name: NullString,
 
-    #[br(args_raw(args))]
+    #[brw(args_raw(args))]
     pub selected_faces: CompressedVertexIndexArray,
 
     always_0: u32,
 
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     pub is_sectional: bool,
 
     #[br(args(4, args))]
     #[br(parse_with = read_compressed_array)]
+    #[bw(args(args))]
+    #[bw(write_with = write_compressed_array)]
     pub vertex_indices: Vec<i32>,
 
-    #[br(args_raw(args))]
+    #[brw(args_raw(args))]
     pub selected_vertices: CompressedVertexIndexArray,
 
     #[br(args(1, args))]
     #[br(parse_with = read_compressed)]
+    #[bw(args(args))]
+    #[bw(write_with = write_compressed)]
     pub selected_vertices_weights: Vec<u8>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 pub struct LodNamedProperty {
     pub property: NullString,
     pub value: NullString,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, BinRead, BinWrite)]
 pub struct LodFrame {
     pub frame_time: f32,
+
+    #[bw(map = |_: &u32| bone_positions.len() as u32)]
     pub bone_count: u32,
 
     #[br(count = bone_count)]
     pub bone_positions: Vec<XYZTriplet>,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[binrw]
+#[derive(Debug, Default, PartialEq, Clone)]
 #[br(import(args: ODOLArgs))]
+#[bw(import(args: ODOLArgs, vertex_count: u32))]
 pub struct UVSet {
     #[br(calc = args.version >= 45)]
+    #[bw(ignore)]
     pub is_discretized: bool,
 
     #[br(if(args.version >= 45))]
+    #[bw(if(args.version >= 45))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub min_u: Option<f32>,
 
     #[br(if(args.version >= 45))]
+    #[bw(if(args.version >= 45))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub min_v: Option<f32>,
 
     #[br(if(args.version >= 45))]
+    #[bw(if(args.version >= 45))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub max_u: Option<f32>,
 
     #[br(if(args.version >= 45))]
+    #[bw(if(args.version >= 45))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub max_v: Option<f32>,
 
+    #[br(temp)]
+    #[bw(calc = match uv_data {
+        Some(data) => (data.len() / if args.version >= 45 { 4 } else { 8 }) as u32,
+        None if *default_fill => vertex_count,
+        None => 0,
+    })]
     vertices_count: u32,
 
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     pub default_fill: bool,
 
     #[br(calc = if args.version >= 45 { 4 } else { 8 })]
+    #[bw(ignore)]
     value_size: u32,
 
     #[br(if(default_fill))]
     #[br(count = value_size)]
+    #[bw(if(*default_fill))]
+    #[bw(map = |x: &Option<Vec<u8>>| x.clone().unwrap_or_default())]
     pub default_value: Option<Vec<u8>>,
 
     #[br(args(!default_fill, value_size as usize, vertices_count as usize, args))]
     #[br(parse_with = read_compressed_size_cond)]
+    #[bw(args(args))]
+    #[bw(write_with = write_compressed_size_cond)]
     pub uv_data: Option<Vec<u8>>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+impl UVSet {
+    /// Dequantized UV coordinates, discretized values are scaled between the min and max UV.
+    ///
+    /// Default filled sets are expanded to `vertex_count` coordinates.
+    #[must_use]
+    pub fn coords(&self, vertex_count: usize) -> Vec<[f32; 2]> {
+        let decode = |bytes: &[u8]| -> [f32; 2] {
+            if self.is_discretized {
+                let (min_u, min_v) = (self.min_u.unwrap_or(0.0), self.min_v.unwrap_or(0.0));
+                let (max_u, max_v) = (self.max_u.unwrap_or(1.0), self.max_v.unwrap_or(1.0));
+                let u = f32::from(i16::from_le_bytes([bytes[0], bytes[1]]));
+                let v = f32::from(i16::from_le_bytes([bytes[2], bytes[3]]));
+                [
+                    ((u + 32767.0) / 65534.0).mul_add(max_u - min_u, min_u),
+                    ((v + 32767.0) / 65534.0).mul_add(max_v - min_v, min_v),
+                ]
+            } else {
+                [
+                    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                    f32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+                ]
+            }
+        };
+        let value_size = if self.is_discretized { 4 } else { 8 };
+
+        if self.default_fill {
+            self.default_value
+                .as_ref()
+                .filter(|v| v.len() >= value_size)
+                .map(|v| vec![decode(v); vertex_count])
+                .unwrap_or_default()
+        } else {
+            self.uv_data
+                .as_ref()
+                .map(|data| data.chunks_exact(value_size).map(decode).collect())
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 pub struct AnimationRTWeight {
+    #[bw(map = |_: &i32| animation_rt_pairs.len() as i32)]
     pub small_count: i32,
 
     #[br(count = 8)]
+    #[bw(map = |_: &Vec<u8>| {
+        let mut res = vec![0_u8; 8];
+        for (i, pair) in animation_rt_pairs.iter().take(4).enumerate() {
+            res[i * 2] = pair.selection_index;
+            res[i * 2 + 1] = pair.weight;
+        }
+        res
+    })]
     pub small_space: Vec<u8>,
 
     #[br(calc = {
@@ -404,16 +943,17 @@ pub struct AnimationRTWeight {
         }
         res
     })]
+    #[bw(ignore)]
     pub animation_rt_pairs: Vec<AnimationRTPair>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, BinRead, BinWrite)]
 pub struct AnimationRTPair {
     pub selection_index: u8,
     pub weight: u8,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 pub struct VertexNeighbour {
     pub pos_a: u16,
     unk_pos: u16,
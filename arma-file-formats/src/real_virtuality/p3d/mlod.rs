@@ -0,0 +1,773 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use binrw::{binrw, BinRead, BinResult, BinWrite, Endian, NullString};
+
+use crate::{errors::AffError, real_virtuality::types::XYZTriplet};
+
+use super::{LodNamedProperty, Resolution, ResolutionEnum};
+
+pub const MLOD_VERSION: u32 = 0x101;
+pub const P3DM_MAJOR_VERSION: u32 = 0x1C;
+pub const P3DM_MINOR_VERSION: u32 = 0x100;
+
+const TAGG_END_OF_FILE: &str = "#EndOfFile#";
+const TAGG_SHARP_EDGES: &str = "#SharpEdges#";
+const TAGG_PROPERTY: &str = "#Property#";
+const TAGG_MASS: &str = "#Mass#";
+const TAGG_UV_SET: &str = "#UVSet#";
+const TAGG_ANIMATION: &str = "#Animation#";
+
+const PROPERTY_FIELD_SIZE: usize = 64;
+const PROXY_PREFIX: &str = "proxy:";
+const COMPONENT_PREFIX: &str = "component";
+const MLOD_FACE_VERTEX_COUNT: u32 = 4;
+
+#[binrw]
+#[derive(Debug, Default, PartialEq, Clone)]
+#[brw(little, magic = b"MLOD")]
+pub struct MLOD {
+    #[br(assert(version == MLOD_VERSION, "MLOD Version {} Unsupported", version))]
+    pub version: u32,
+
+    #[br(temp)]
+    #[bw(calc = lods.len() as u32)]
+    lod_count: u32,
+
+    #[br(count = lod_count)]
+    pub lods: Vec<MlodLod>,
+}
+
+impl MLOD {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            version: MLOD_VERSION,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, AffError> {
+        let file = File::open(path)?;
+        let mut buf_reader = BufReader::new(file);
+        Self::from_stream(&mut buf_reader)
+    }
+
+    pub fn from_stream<R>(reader: &mut R) -> Result<Self, AffError>
+    where
+        R: Read + Seek,
+    {
+        Ok(Self::read(reader)?)
+    }
+
+    pub fn write<W>(&self, writer: &mut W) -> Result<(), AffError>
+    where
+        W: Write + Seek,
+    {
+        BinWrite::write(self, writer)?;
+        Ok(())
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<(), AffError> {
+        let mut file = File::create(path)?;
+        self.write(&mut file)
+    }
+
+    pub fn write_data(&self) -> Result<Vec<u8>, AffError> {
+        let mut buf = Vec::new();
+        self.write(&mut Cursor::new(&mut buf))?;
+        Ok(buf)
+    }
+
+    #[must_use]
+    pub fn lod(&self, resolution: ResolutionEnum) -> Option<&MlodLod> {
+        self.lods.iter().find(|l| l.resolution.res == resolution)
+    }
+
+    pub fn lod_mut(&mut self, resolution: ResolutionEnum) -> Option<&mut MlodLod> {
+        self.lods
+            .iter_mut()
+            .find(|l| l.resolution.res == resolution)
+    }
+
+    /// Replaces a texture path on all faces of all LODs, case insensitive. Returns the number of replaced faces.
+    pub fn replace_texture(&mut self, from: &str, to: &str) -> usize {
+        self.lods
+            .iter_mut()
+            .flat_map(|l| &mut l.faces)
+            .map(|f| replace_path(&mut f.texture, from, to))
+            .filter(|replaced| *replaced)
+            .count()
+    }
+
+    /// Replaces a material path on all faces of all LODs, case insensitive. Returns the number of replaced faces.
+    pub fn replace_material(&mut self, from: &str, to: &str) -> usize {
+        self.lods
+            .iter_mut()
+            .flat_map(|l| &mut l.faces)
+            .map(|f| replace_path(&mut f.material, from, to))
+            .filter(|replaced| *replaced)
+            .count()
+    }
+}
+
+#[binrw]
+#[derive(Debug, Default, PartialEq, Clone)]
+#[brw(little, magic = b"P3DM")]
+pub struct MlodLod {
+    #[br(assert(major_version == P3DM_MAJOR_VERSION, "P3DM Version {} Unsupported", major_version))]
+    pub major_version: u32,
+    pub minor_version: u32,
+
+    #[br(temp)]
+    #[bw(calc = points.len() as u32)]
+    point_count: u32,
+
+    #[br(temp)]
+    #[bw(calc = normals.len() as u32)]
+    normal_count: u32,
+
+    #[br(temp)]
+    #[bw(calc = faces.len() as u32)]
+    face_count: u32,
+
+    pub flags: u32,
+
+    #[br(count = point_count)]
+    pub points: Vec<MlodPoint>,
+
+    #[br(count = normal_count)]
+    pub normals: Vec<XYZTriplet>,
+
+    #[br(count = face_count)]
+    pub faces: Vec<MlodFace>,
+
+    #[br(args(point_count as usize, face_count as usize))]
+    pub taggs: MlodTaggs,
+
+    pub resolution: Resolution,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
+pub struct MlodPoint {
+    pub position: XYZTriplet,
+    pub flags: u32,
+}
+
+#[binrw]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct MlodFace {
+    #[br(temp)]
+    #[br(assert(is_face_vertex_count(vertex_count as usize), "Invalid MLOD face vertex count {}", vertex_count))]
+    #[bw(assert(is_face_vertex_count(vertices.len()), "Invalid MLOD face vertex count {}", vertices.len()))]
+    #[bw(calc = vertices.len() as u32)]
+    vertex_count: u32,
+
+    /// Always stored as 4 vertices, unused ones are dropped.
+    #[br(count = MLOD_FACE_VERTEX_COUNT)]
+    #[br(map = |v: Vec<MlodVertex>| v.into_iter().take(vertex_count as usize).collect())]
+    #[bw(map = |v: &Vec<MlodVertex>| {
+        let mut v = v.clone();
+        v.resize(MLOD_FACE_VERTEX_COUNT as usize, MlodVertex::default());
+        v
+    })]
+    pub vertices: Vec<MlodVertex>,
+
+    pub flags: u32,
+    pub texture: NullString,
+    pub material: NullString,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
+pub struct MlodVertex {
+    pub point_index: u32,
+    pub normal_index: u32,
+    pub u: f32,
+    pub v: f32,
+}
+
+/// Everything stored in the `TAGG` blocks of a LOD.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct MlodTaggs {
+    pub named_selections: Vec<MlodNamedSelection>,
+    pub named_properties: Vec<LodNamedProperty>,
+    pub sharp_edges: Vec<(u32, u32)>,
+
+    /// One value per point, only present in geometry LODs.
+    pub mass: Option<Vec<f32>>,
+    pub uv_sets: Vec<MlodUVSet>,
+    pub frames: Vec<MlodFrame>,
+
+    /// Blocks without a typed representation like `#Selected#`, `#Hide#` or `#Lock#`.
+    pub other: Vec<MlodTagg>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct MlodNamedSelection {
+    pub name: NullString,
+
+    /// One encoded weight per point, see [`MlodNamedSelection::point_weight`].
+    pub points: Vec<u8>,
+    pub faces: Vec<u8>,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct MlodUVSet {
+    pub stage: u32,
+
+    /// One pair per face vertex, in face order.
+    pub uvs: Vec<(f32, f32)>,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct MlodFrame {
+    pub time: f32,
+    pub points: Vec<XYZTriplet>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct MlodTagg {
+    pub active: bool,
+    pub name: NullString,
+    pub data: Vec<u8>,
+}
+
+impl MlodNamedSelection {
+    /// Decodes a selection byte, 0 is unselected, 1 fully selected and everything above a partial weight.
+    #[must_use]
+    pub fn decode_weight(value: u8) -> f32 {
+        match value {
+            0 => 0.0,
+            1 => 1.0,
+            _ => (256.0 - f32::from(value)) / 255.0,
+        }
+    }
+
+    #[must_use]
+    pub fn encode_weight(weight: f32) -> u8 {
+        if weight <= 0.0 {
+            0
+        } else if weight >= 1.0 {
+            1
+        } else {
+            (256.0 - (weight * 255.0).round()).clamp(2.0, 255.0) as u8
+        }
+    }
+
+    #[must_use]
+    pub fn point_weight(&self, index: usize) -> f32 {
+        self.points
+            .get(index)
+            .copied()
+            .map_or(0.0, Self::decode_weight)
+    }
+
+    #[must_use]
+    pub fn contains_point(&self, index: usize) -> bool {
+        self.points.get(index).is_some_and(|w| *w != 0)
+    }
+
+    #[must_use]
+    pub fn contains_face(&self, index: usize) -> bool {
+        self.faces.get(index).is_some_and(|w| *w != 0)
+    }
+}
+
+impl MlodLod {
+    #[must_use]
+    pub fn new(resolution: f32) -> Self {
+        Self {
+            major_version: P3DM_MAJOR_VERSION,
+            minor_version: P3DM_MINOR_VERSION,
+            resolution: Resolution::from_value(resolution),
+            ..Default::default()
+        }
+    }
+
+    /// Adds a point and grows all per point data with it. Returns the index of the new point.
+    pub fn add_point(&mut self, position: XYZTriplet) -> u32 {
+        let (point_count, face_count) = (self.points.len(), self.faces.len());
+
+        for selection in &mut self.taggs.named_selections {
+            selection.points.resize(point_count + 1, 0);
+        }
+        for tagg in &mut self.taggs.other {
+            if tagg.data.len() == point_count + face_count {
+                tagg.data.insert(point_count, 0);
+            }
+        }
+        if let Some(mass) = &mut self.taggs.mass {
+            mass.resize(point_count + 1, 0.0);
+        }
+        for frame in &mut self.taggs.frames {
+            frame.points.push(position);
+        }
+
+        self.points.push(MlodPoint { position, flags: 0 });
+        point_count as u32
+    }
+
+    /// Adds a flat shaded face from `(point_index, u, v)` triplets. Returns the index of the new face.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the face does not have 3 or 4 vertices.
+    pub fn add_face(&mut self, vertices: &[(u32, f32, f32)], texture: &str, material: &str) -> u32 {
+        assert!(
+            is_face_vertex_count(vertices.len()),
+            "Invalid MLOD face vertex count {}",
+            vertices.len()
+        );
+        let (point_count, face_count) = (self.points.len(), self.faces.len());
+
+        let normal_index = self.normals.len() as u32;
+        self.normals.push(self.face_normal(vertices));
+
+        for selection in &mut self.taggs.named_selections {
+            selection.faces.resize(face_count + 1, 0);
+        }
+        for tagg in &mut self.taggs.other {
+            if tagg.data.len() == point_count + face_count {
+                tagg.data.push(0);
+            }
+        }
+        for uv_set in &mut self.taggs.uv_sets {
+            uv_set.uvs.extend(vertices.iter().map(|(_, u, v)| (*u, *v)));
+        }
+
+        self.faces.push(MlodFace {
+            vertices: vertices
+                .iter()
+                .map(|&(point_index, u, v)| MlodVertex {
+                    point_index,
+                    normal_index,
+                    u,
+                    v,
+                })
+                .collect(),
+            flags: 0,
+            texture: texture.into(),
+            material: material.into(),
+        });
+        face_count as u32
+    }
+
+    /// Adds the points and faces to the selection, creating it if it doesn't exist yet.
+    pub fn add_named_selection(&mut self, name: &str, points: &[u32], faces: &[u32]) {
+        let (point_count, face_count) = (self.points.len(), self.faces.len());
+
+        let index = if let Some(index) = self
+            .taggs
+            .named_selections
+            .iter()
+            .position(|s| s.name.to_string().eq_ignore_ascii_case(name))
+        {
+            index
+        } else {
+            self.taggs.named_selections.push(MlodNamedSelection {
+                name: name.into(),
+                points: vec![0; point_count],
+                faces: vec![0; face_count],
+            });
+            self.taggs.named_selections.len() - 1
+        };
+
+        let selection = &mut self.taggs.named_selections[index];
+        for point in points.iter().filter(|p| (**p as usize) < point_count) {
+            selection.points[*point as usize] = 1;
+        }
+        for face in faces.iter().filter(|f| (**f as usize) < face_count) {
+            selection.faces[*face as usize] = 1;
+        }
+    }
+
+    pub fn rename_named_selection(&mut self, from: &str, to: &str) -> bool {
+        self.taggs
+            .named_selections
+            .iter_mut()
+            .find(|s| s.name.to_string().eq_ignore_ascii_case(from))
+            .map(|s| s.name = to.into())
+            .is_some()
+    }
+
+    pub fn set_named_property(&mut self, name: &str, value: &str) {
+        if let Some(property) = self
+            .taggs
+            .named_properties
+            .iter_mut()
+            .find(|p| p.property.to_string().eq_ignore_ascii_case(name))
+        {
+            property.value = value.into();
+        } else {
+            self.taggs.named_properties.push(LodNamedProperty {
+                property: name.into(),
+                value: value.into(),
+            });
+        }
+    }
+
+    /// Adds a proxy triangle for `model` at `position`, pointing along Z with Y up.
+    ///
+    /// Returns the name of the created `proxy:` selection.
+    pub fn add_proxy(&mut self, model: &str, position: XYZTriplet) -> String {
+        let index = self
+            .taggs
+            .named_selections
+            .iter()
+            .filter(|s| s.name.to_string().starts_with(PROXY_PREFIX))
+            .count()
+            + 1;
+
+        let a = self.add_point(position);
+        let b = self.add_point(XYZTriplet {
+            z: position.z + 2.0,
+            ..position
+        });
+        let c = self.add_point(XYZTriplet {
+            y: position.y + 1.0,
+            ..position
+        });
+        let face = self.add_face(&[(a, 0.0, 0.0), (b, 0.0, 0.0), (c, 0.0, 0.0)], "", "");
+
+        let name = format!(
+            "{PROXY_PREFIX}{}.{index:03}",
+            model.trim_end_matches(".p3d")
+        );
+        self.add_named_selection(&name, &[a, b, c], &[face]);
+        name
+    }
+
+    /// Adds an axis aligned box as a new `ComponentXX` selection, e.g. for geometry LODs.
+    ///
+    /// Returns the name of the created component selection.
+    pub fn add_box(&mut self, min: XYZTriplet, max: XYZTriplet) -> String {
+        let corner = |i: usize| XYZTriplet {
+            x: if i & 1 == 0 { min.x } else { max.x },
+            y: if i & 2 == 0 { min.y } else { max.y },
+            z: if i & 4 == 0 { min.z } else { max.z },
+        };
+        let center = XYZTriplet {
+            x: min.x.midpoint(max.x),
+            y: min.y.midpoint(max.y),
+            z: min.z.midpoint(max.z),
+        };
+
+        let points: Vec<u32> = (0..8).map(|i| self.add_point(corner(i))).collect();
+
+        let mut faces = Vec::with_capacity(6);
+        for quad in [
+            [0, 2, 6, 4],
+            [1, 5, 7, 3],
+            [0, 4, 5, 1],
+            [2, 3, 7, 6],
+            [0, 1, 3, 2],
+            [4, 6, 7, 5],
+        ] {
+            let mut vertices: Vec<(u32, f32, f32)> = quad
+                .iter()
+                .zip([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+                .map(|(i, (u, v))| (points[*i], u, v))
+                .collect();
+
+            // Keep the normals pointing outwards
+            let normal = self.face_normal(&vertices);
+            let outwards = corner(quad[0]);
+            if dot(&normal, &sub(&outwards, &center)) < 0.0 {
+                vertices.reverse();
+            }
+            faces.push(self.add_face(&vertices, "", ""));
+        }
+
+        let index = self
+            .taggs
+            .named_selections
+            .iter()
+            .filter(|s| {
+                s.name
+                    .to_string()
+                    .to_lowercase()
+                    .starts_with(COMPONENT_PREFIX)
+            })
+            .count()
+            + 1;
+        let name = format!("Component{index:02}");
+        self.add_named_selection(&name, &points, &faces);
+        name
+    }
+
+    fn face_normal(&self, vertices: &[(u32, f32, f32)]) -> XYZTriplet {
+        let position = |i: usize| {
+            vertices
+                .get(i)
+                .and_then(|(p, _, _)| self.points.get(*p as usize))
+                .map(|p| p.position)
+                .unwrap_or_default()
+        };
+        let (a, b, c) = (position(0), position(1), position(2));
+        let normal = cross(&sub(&b, &a), &sub(&c, &a));
+        let len = dot(&normal, &normal).sqrt();
+        if len > f32::EPSILON {
+            XYZTriplet {
+                x: normal.x / len,
+                y: normal.y / len,
+                z: normal.z / len,
+            }
+        } else {
+            normal
+        }
+    }
+
+    #[must_use]
+    pub fn named_selection(&self, name: &str) -> Option<&MlodNamedSelection> {
+        self.taggs
+            .named_selections
+            .iter()
+            .find(|s| s.name.to_string().eq_ignore_ascii_case(name))
+    }
+
+    #[must_use]
+    pub fn named_property(&self, name: &str) -> Option<String> {
+        self.taggs
+            .named_properties
+            .iter()
+            .find(|p| p.property.to_string().eq_ignore_ascii_case(name))
+            .map(|p| p.value.to_string())
+    }
+}
+
+impl BinRead for MlodTaggs {
+    type Args<'a> = (usize, usize);
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        (point_count, face_count): Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let pos = reader.stream_position()?;
+        let magic = <[u8; 4]>::read_options(reader, endian, ())?;
+        if &magic != b"TAGG" {
+            return Err(binrw::Error::BadMagic {
+                pos,
+                found: Box::new(magic),
+            });
+        }
+
+        let mut taggs = Self::default();
+
+        loop {
+            let active = u8::read_options(reader, endian, ())? != 0;
+            let name = NullString::read_options(reader, endian, ())?;
+            let size = u32::read_options(reader, endian, ())? as usize;
+            let data_pos = reader.stream_position()?;
+
+            match name.to_string().as_str() {
+                TAGG_END_OF_FILE => {
+                    reader.seek(SeekFrom::Current(size as i64))?;
+                    break;
+                }
+                TAGG_SHARP_EDGES => {
+                    taggs.sharp_edges = (0..size / 8)
+                        .map(|_| <(u32, u32)>::read_options(reader, endian, ()))
+                        .collect::<BinResult<_>>()?;
+                }
+                TAGG_PROPERTY => {
+                    let data = read_bytes(reader, size)?;
+                    let field = |bytes: &[u8]| {
+                        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+                        NullString(bytes[..end].to_vec())
+                    };
+                    let split = PROPERTY_FIELD_SIZE.min(data.len());
+                    taggs.named_properties.push(LodNamedProperty {
+                        property: field(&data[..split]),
+                        value: field(&data[split..]),
+                    });
+                }
+                TAGG_MASS => {
+                    taggs.mass = Some(
+                        (0..size / 4)
+                            .map(|_| f32::read_options(reader, endian, ()))
+                            .collect::<BinResult<_>>()?,
+                    );
+                }
+                TAGG_UV_SET => {
+                    let stage = u32::read_options(reader, endian, ())?;
+                    let uvs = (0..size.saturating_sub(4) / 8)
+                        .map(|_| <(f32, f32)>::read_options(reader, endian, ()))
+                        .collect::<BinResult<_>>()?;
+                    taggs.uv_sets.push(MlodUVSet { stage, uvs });
+                }
+                TAGG_ANIMATION => {
+                    let time = f32::read_options(reader, endian, ())?;
+                    let points = (0..size.saturating_sub(4) / 12)
+                        .map(|_| XYZTriplet::read_options(reader, endian, ()))
+                        .collect::<BinResult<_>>()?;
+                    taggs.frames.push(MlodFrame { time, points });
+                }
+                selection if !selection.starts_with('#') && size == point_count + face_count => {
+                    let data = read_bytes(reader, size)?;
+                    taggs.named_selections.push(MlodNamedSelection {
+                        name,
+                        points: data[..point_count].to_vec(),
+                        faces: data[point_count..].to_vec(),
+                    });
+                }
+                _ => {
+                    taggs.other.push(MlodTagg {
+                        active,
+                        name,
+                        data: read_bytes(reader, size)?,
+                    });
+                }
+            }
+
+            // Blocks may be larger than what was parsed
+            reader.seek(SeekFrom::Start(data_pos + size as u64))?;
+        }
+
+        Ok(taggs)
+    }
+}
+
+impl BinWrite for MlodTaggs {
+    type Args<'a> = ();
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: Endian,
+        (): Self::Args<'_>,
+    ) -> BinResult<()> {
+        b"TAGG".write_options(writer, endian, ())?;
+
+        for frame in &self.frames {
+            write_tagg_header(
+                writer,
+                endian,
+                true,
+                TAGG_ANIMATION,
+                4 + frame.points.len() * 12,
+            )?;
+            frame.time.write_options(writer, endian, ())?;
+            frame.points.write_options(writer, endian, ())?;
+        }
+
+        for uv_set in &self.uv_sets {
+            write_tagg_header(writer, endian, true, TAGG_UV_SET, 4 + uv_set.uvs.len() * 8)?;
+            uv_set.stage.write_options(writer, endian, ())?;
+            for (u, v) in &uv_set.uvs {
+                u.write_options(writer, endian, ())?;
+                v.write_options(writer, endian, ())?;
+            }
+        }
+
+        for tagg in &self.other {
+            write_tagg_header(
+                writer,
+                endian,
+                tagg.active,
+                &tagg.name.to_string(),
+                tagg.data.len(),
+            )?;
+            tagg.data.write_options(writer, endian, ())?;
+        }
+
+        for selection in &self.named_selections {
+            write_tagg_header(
+                writer,
+                endian,
+                true,
+                &selection.name.to_string(),
+                selection.points.len() + selection.faces.len(),
+            )?;
+            selection.points.write_options(writer, endian, ())?;
+            selection.faces.write_options(writer, endian, ())?;
+        }
+
+        for property in &self.named_properties {
+            write_tagg_header(writer, endian, true, TAGG_PROPERTY, PROPERTY_FIELD_SIZE * 2)?;
+            for field in [&property.property, &property.value] {
+                // Fixed size and always zero terminated
+                let mut data = field.0.clone();
+                data.resize(PROPERTY_FIELD_SIZE - 1, 0);
+                data.push(0);
+                data.write_options(writer, endian, ())?;
+            }
+        }
+
+        if let Some(mass) = &self.mass {
+            write_tagg_header(writer, endian, true, TAGG_MASS, mass.len() * 4)?;
+            mass.write_options(writer, endian, ())?;
+        }
+
+        if !self.sharp_edges.is_empty() {
+            write_tagg_header(
+                writer,
+                endian,
+                true,
+                TAGG_SHARP_EDGES,
+                self.sharp_edges.len() * 8,
+            )?;
+            for (a, b) in &self.sharp_edges {
+                a.write_options(writer, endian, ())?;
+                b.write_options(writer, endian, ())?;
+            }
+        }
+
+        write_tagg_header(writer, endian, true, TAGG_END_OF_FILE, 0)
+    }
+}
+
+fn write_tagg_header<W: Write + Seek>(
+    writer: &mut W,
+    endian: Endian,
+    active: bool,
+    name: &str,
+    size: usize,
+) -> BinResult<()> {
+    u8::from(active).write_options(writer, endian, ())?;
+    NullString::from(name).write_options(writer, endian, ())?;
+    (size as u32).write_options(writer, endian, ())
+}
+
+/// MLOD faces are triangles or quads.
+const fn is_face_vertex_count(count: usize) -> bool {
+    count == 3 || count == MLOD_FACE_VERTEX_COUNT as usize
+}
+
+fn replace_path(path: &mut NullString, from: &str, to: &str) -> bool {
+    if path.to_string().eq_ignore_ascii_case(from) {
+        *path = to.into();
+        true
+    } else {
+        false
+    }
+}
+
+fn sub(a: &XYZTriplet, b: &XYZTriplet) -> XYZTriplet {
+    XYZTriplet {
+        x: a.x - b.x,
+        y: a.y - b.y,
+        z: a.z - b.z,
+    }
+}
+
+fn cross(a: &XYZTriplet, b: &XYZTriplet) -> XYZTriplet {
+    XYZTriplet {
+        x: a.y.mul_add(b.z, -(a.z * b.y)),
+        y: a.z.mul_add(b.x, -(a.x * b.z)),
+        z: a.x.mul_add(b.y, -(a.y * b.x)),
+    }
+}
+
+fn dot(a: &XYZTriplet, b: &XYZTriplet) -> f32 {
+    a.x.mul_add(b.x, a.y.mul_add(b.y, a.z * b.z))
+}
+
+fn read_bytes<R: Read>(reader: &mut R, size: usize) -> BinResult<Vec<u8>> {
+    let mut data = vec![0_u8; size];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
@@ -1,11 +1,22 @@
 mod animations;
+mod dependencies;
 mod enums;
 mod face_data;
+mod geometry;
+mod gltf;
+mod legacy;
 mod lod;
+mod mlod;
 mod model_info;
+mod obj;
 mod odol;
+mod pose;
+mod report;
+mod resolution;
+mod rvmat;
 mod skeleton;
 
 pub use self::{
-    animations::*, enums::*, face_data::*, lod::*, model_info::*, odol::*, skeleton::*,
+    animations::*, dependencies::*, enums::*, face_data::*, gltf::*, legacy::*, lod::*, mlod::*,
+    model_info::*, obj::*, odol::*, pose::*, report::*, resolution::*, rvmat::*, skeleton::*,
 };
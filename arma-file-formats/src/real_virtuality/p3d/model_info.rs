@@ -1,13 +1,14 @@
 use crate::real_virtuality::types::{RGBAColor, XYZTriplet};
-use binrw::{BinRead, NullString};
+use binrw::{BinRead, BinWrite, NullString};
 
 use super::{skeleton::Skeleton, ODOLArgs};
-use crate::real_virtuality::binrw_utils::read_compressed_array;
+use crate::real_virtuality::binrw_utils::{read_compressed_array, write_compressed_array};
 use derivative::Derivative;
 
 #[allow(clippy::struct_excessive_bools)]
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, BinRead, BinWrite)]
 #[br(import(args: ODOLArgs, lod_count: u32))]
+#[bw(import(args: ODOLArgs, lod_count: u32))]
 pub struct ModelInfo {
     pub index: u32,
     pub mem_lod_sphere: f32,
@@ -22,13 +23,21 @@ pub struct ModelInfo {
     pub bbox_min_pos: XYZTriplet,
     pub bbox_max_pos: XYZTriplet,
     #[br(if(args.version >= 70))]
+    #[bw(if(args.version >= 70))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub lod_density_coef: Option<f32>,
 
     #[br(if(args.version >= 71))]
+    #[bw(if(args.version >= 71))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub draw_importance: Option<f32>,
     #[br(if(args.version >= 52))]
+    #[bw(if(args.version >= 52))]
+    #[bw(map = |x: &Option<XYZTriplet>| x.unwrap_or_default())]
     pub bbox_min_visual: Option<XYZTriplet>,
     #[br(if(args.version >= 52))]
+    #[bw(if(args.version >= 52))]
+    #[bw(map = |x: &Option<XYZTriplet>| x.unwrap_or_default())]
     pub bbox_max_visual: Option<XYZTriplet>,
 
     pub bounding_center: XYZTriplet,
@@ -36,56 +45,91 @@ pub struct ModelInfo {
     pub center_of_mass: XYZTriplet,
 
     #[br(count = 3)]
+    #[bw(map = |x: &Vec<XYZTriplet>| {
+        let mut inv_intertia = x.clone();
+        inv_intertia.resize(3, XYZTriplet::default());
+        inv_intertia
+    })]
     pub inv_intertia: Vec<XYZTriplet>,
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     pub auto_center: bool,
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     pub lock_auto_center: bool,
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     pub can_occlude: bool,
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     pub can_be_occlude: bool,
     #[br(if(args.version >= 73))]
     #[br(map = |x: u8| Some(x != 0))]
+    #[bw(if(args.version >= 73))]
+    #[bw(map = |x: &Option<bool>| u8::from(x.unwrap_or_default()))]
     pub ai_covers: Option<bool>,
 
     #[br(if(args.version >= 42))]
+    #[bw(if(args.version >= 42))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub ht_min: Option<f32>,
     #[br(if(args.version >= 42))]
+    #[bw(if(args.version >= 42))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub ht_max: Option<f32>,
     #[br(if(args.version >= 42))]
+    #[bw(if(args.version >= 42))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub af_max: Option<f32>,
     #[br(if(args.version >= 42))]
+    #[bw(if(args.version >= 42))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub mf_max: Option<f32>,
 
     #[br(if(args.version >= 43))]
+    #[bw(if(args.version >= 43))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub m_fact: Option<f32>,
     #[br(if(args.version >= 43))]
+    #[bw(if(args.version >= 43))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub t_body: Option<f32>,
 
     #[br(if(args.version >= 33))]
     #[br(map = |x: u8| Some(x != 0))]
+    #[bw(if(args.version >= 33))]
+    #[bw(map = |x: &Option<bool>| u8::from(x.unwrap_or_default()))]
     pub force_not_alpha: Option<bool>,
 
     #[br(if(args.version >= 37))]
+    #[bw(if(args.version >= 37))]
+    #[bw(map = |x: &Option<SBSource>| x.unwrap_or_default())]
     pub sb_source: Option<SBSource>,
     #[br(if(args.version >= 37))]
     #[br(map = |x: u8| Some(x != 0))]
+    #[bw(if(args.version >= 37))]
+    #[bw(map = |x: &Option<bool>| u8::from(x.unwrap_or_default()))]
     pub prefer_shadow_volume: Option<bool>,
 
     #[br(if(args.version >= 48))]
+    #[bw(if(args.version >= 48))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub shadow_offset: Option<f32>,
 
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     pub animated: bool,
 
     #[br(args(args.version))]
+    #[bw(args(args.version))]
     pub skeleton: Skeleton,
 
     pub map_type: u8,
 
     #[br(args(4, args))]
     #[br(parse_with = read_compressed_array)]
+    #[bw(args(args))]
+    #[bw(write_with = write_compressed_array)]
     pub mass_array: Vec<f32>,
 
     pub mass: f32,
@@ -94,12 +138,18 @@ pub struct ModelInfo {
     pub alt_mass_reciprocal: f32,
 
     #[br(if(args.version >= 72))]
+    #[bw(if(args.version >= 72))]
+    #[bw(map = |x: &Option<f32>| x.unwrap_or_default())]
     pub property_explosion_shielding: Option<f32>,
 
     #[br(if(args.version >= 53))]
+    #[bw(if(args.version >= 53))]
+    #[bw(map = |x: &Option<u8>| x.unwrap_or_default())]
     pub geometry_simple: Option<u8>,
 
     #[br(if(args.version >= 54))]
+    #[bw(if(args.version >= 54))]
+    #[bw(map = |x: &Option<u8>| x.unwrap_or_default())]
     pub geometry_phys: Option<u8>,
 
     pub memory: u8,
@@ -118,45 +168,116 @@ pub struct ModelInfo {
 
     #[br(if(args.version >= 38))]
     #[br(map = |x: u8| Some(x != 0))]
+    #[bw(if(args.version >= 38))]
+    #[bw(map = |x: &Option<bool>| u8::from(x.unwrap_or_default()))]
     pub can_blend: Option<bool>,
 
     pub property_class: NullString,
     pub property_damage: NullString,
 
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     pub property_frequent: bool,
 
     #[br(if(args.version >= 31))]
+    #[bw(if(args.version >= 31))]
     pub unknown_int: u32,
 
     #[br(if(args.version >= 57))]
     #[br(count = lod_count)]
+    #[bw(if(args.version >= 57))]
+    #[bw(map = |x: &Option<Vec<i32>>| lod_indices(x.as_deref(), lod_count))]
     pub prefferred_shadow_volumne_lod: Option<Vec<i32>>,
     #[br(if(args.version >= 57))]
     #[br(count = lod_count)]
+    #[bw(if(args.version >= 57))]
+    #[bw(map = |x: &Option<Vec<i32>>| lod_indices(x.as_deref(), lod_count))]
     pub prefferred_shadow_buffer_lod: Option<Vec<i32>>,
     #[br(if(args.version >= 57))]
     #[br(count = lod_count)]
+    #[bw(if(args.version >= 57))]
+    #[bw(map = |x: &Option<Vec<i32>>| lod_indices(x.as_deref(), lod_count))]
     pub prefferred_shadow_buffer_lod_vis: Option<Vec<i32>>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative)]
+/// One LOD index per LOD, missing entries are written as -1.
+fn lod_indices(lods: Option<&[i32]>, lod_count: u32) -> Vec<i32> {
+    let mut lods = lods.map(<[i32]>::to_vec).unwrap_or_default();
+    lods.resize(lod_count as usize, -1);
+    lods
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, BinWrite, Derivative)]
 #[derivative(Default)]
 pub enum SBSource {
     #[derivative(Default)]
-    #[br(magic = 0i32)]
+    #[brw(magic = 0i32)]
     Visual = 0,
-    #[br(magic = 1i32)]
+    #[brw(magic = 1i32)]
     ShadowVolume = 1,
-    #[br(magic = 2i32)]
+    #[brw(magic = 2i32)]
     Explicit = 2,
-    #[br(magic = 3i32)]
+    #[brw(magic = 3i32)]
     None = 3,
-    #[br(magic = 4i32)]
+    #[brw(magic = 4i32)]
     VisualEx = 4,
 }
 
-impl ModelInfo {}
+impl ModelInfo {
+    /// Updates the special LOD indices after a LOD was removed, references to it are cleared.
+    pub(crate) fn remove_lod(&mut self, index: usize) {
+        // Missing LODs are stored as -1
+        let shift_u8 = |lod: &mut u8| {
+            if *lod == u8::MAX {
+                return;
+            }
+            match usize::from(*lod) {
+                i if i == index => *lod = u8::MAX,
+                i if i > index => *lod -= 1,
+                _ => {}
+            }
+        };
+        for lod in [
+            &mut self.memory,
+            &mut self.geometry,
+            &mut self.geometry_fire,
+            &mut self.geometry_view,
+            &mut self.geometry_view_pilot,
+            &mut self.geometry_view_gunner,
+            &mut self.geometry_view_cargo,
+            &mut self.land_contact,
+            &mut self.roadway,
+            &mut self.paths,
+            &mut self.hitpoints,
+        ]
+        .into_iter()
+        .chain(self.geometry_simple.as_mut())
+        .chain(self.geometry_phys.as_mut())
+        {
+            shift_u8(lod);
+        }
+
+        for lods in [
+            &mut self.prefferred_shadow_volumne_lod,
+            &mut self.prefferred_shadow_buffer_lod,
+            &mut self.prefferred_shadow_buffer_lod_vis,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if index < lods.len() {
+                lods.remove(index);
+            }
+            for lod in lods.iter_mut() {
+                match usize::try_from(*lod) {
+                    Ok(i) if i == index => *lod = -1,
+                    Ok(i) if i > index => *lod -= 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative)]
 #[derivative(Default)]
@@ -0,0 +1,381 @@
+use std::{collections::HashMap, fmt::Write as _, fs, path::Path};
+
+use crate::{errors::AffError, real_virtuality::paa::TextureSuffix};
+
+use super::{geometry::texture_uri, Lod, MlodLod, Resolution, ResolutionEnum, Rvmat, MLOD, ODOL};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ObjGrouping {
+    /// One object per LOD
+    #[default]
+    Lod,
+    /// One object per LOD, faces grouped by the named selections they belong to
+    NamedSelection,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ObjOptions {
+    pub grouping: ObjGrouping,
+
+    /// Replaces the extension of texture paths in the MTL, e.g. `png`.
+    pub texture_extension: Option<String>,
+
+    /// Export geometry, memory and other non graphical LODs as well.
+    pub all_lods: bool,
+}
+
+/// Wavefront OBJ geometry with its MTL material library.
+///
+/// X is mirrored to convert from Arma's left handed coordinates, same as the glTF export.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Obj {
+    body: String,
+    pub mtl: String,
+}
+
+impl Obj {
+    #[must_use]
+    pub fn from_odol(odol: &ODOL, options: &ObjOptions) -> Self {
+        let mut builder = ObjBuilder::new(options);
+        for (i, lod) in odol.lods.iter().enumerate() {
+            let resolution = odol.resolutions.get(i).copied().unwrap_or_default();
+            if builder.include(resolution) {
+                builder.add_odol_lod(&resolution.to_string(), lod);
+            }
+        }
+        builder.finish()
+    }
+
+    #[must_use]
+    pub fn from_mlod(mlod: &MLOD, options: &ObjOptions) -> Self {
+        let mut builder = ObjBuilder::new(options);
+        for lod in &mlod.lods {
+            if builder.include(lod.resolution) {
+                builder.add_mlod_lod(&lod.resolution.to_string(), lod);
+            }
+        }
+        builder.finish()
+    }
+
+    /// OBJ document, referencing `mtl_file` as material library if given.
+    #[must_use]
+    pub fn obj(&self, mtl_file: Option<&str>) -> String {
+        mtl_file.map_or_else(
+            || self.body.clone(),
+            |mtl_file| format!("mtllib {mtl_file}\n{}", self.body),
+        )
+    }
+
+    /// Writes `<path>.obj` and `<path>.mtl` next to each other.
+    pub fn write_files<P: AsRef<Path>>(&self, path: P) -> Result<(), AffError> {
+        let obj_path = path.as_ref().with_extension("obj");
+        let mtl_path = path.as_ref().with_extension("mtl");
+        let mtl_file = mtl_path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        fs::write(obj_path, self.obj(Some(&mtl_file)))?;
+        fs::write(mtl_path, &self.mtl)?;
+        Ok(())
+    }
+}
+
+struct ObjBuilder<'a> {
+    options: &'a ObjOptions,
+    body: String,
+    mtl: String,
+    materials: HashMap<String, String>,
+    vertex_offset: usize,
+    uv_offset: usize,
+    normal_offset: usize,
+}
+
+/// Face in OBJ indices, zero based and relative to the current LOD.
+struct ObjFace<'r> {
+    vertices: Vec<(usize, Option<usize>, Option<usize>)>,
+    texture: String,
+    material: String,
+    /// Material properties, only known for ODOL models.
+    rvmat: Option<&'r Rvmat>,
+    groups: Vec<String>,
+}
+
+impl<'a> ObjBuilder<'a> {
+    fn new(options: &'a ObjOptions) -> Self {
+        Self {
+            options,
+            body: String::new(),
+            mtl: String::new(),
+            materials: HashMap::new(),
+            vertex_offset: 1,
+            uv_offset: 1,
+            normal_offset: 1,
+        }
+    }
+
+    fn include(&self, resolution: Resolution) -> bool {
+        self.options.all_lods || resolution.res == ResolutionEnum::GraphicalLod
+    }
+
+    fn add_odol_lod(&mut self, name: &str, lod: &Lod) {
+        let vertex_count = lod.vertices.len();
+        let uvs = lod.default_uv_set.coords(vertex_count);
+        let has_uvs = uvs.len() == vertex_count;
+        let has_normals = lod.normals.len() == vertex_count;
+
+        let rvmats: Vec<Rvmat> = lod.materials.iter().map(Rvmat::from).collect();
+        let mut face_sections = vec![None; lod.faces.len()];
+        for (section, faces) in lod.section_faces() {
+            for face in faces {
+                face_sections[face] = section;
+            }
+        }
+        let mut face_groups = vec![Vec::new(); lod.faces.len()];
+        if self.options.grouping == ObjGrouping::NamedSelection {
+            for selection in &lod.named_selection {
+                let name = sanitize(&selection.name.to_string());
+                for face in &selection.selected_faces.edges {
+                    if let Some(groups) = face_groups.get_mut(*face as usize) {
+                        if groups.last() != Some(&name) {
+                            groups.push(name.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        let faces = lod
+            .faces
+            .iter()
+            .zip(face_groups)
+            .enumerate()
+            .map(|(i, (face, groups))| {
+                let section = face_sections[i];
+                let texture = section
+                    .and_then(|s| usize::try_from(s.common_texture_index).ok())
+                    .and_then(|t| lod.textures.get(t))
+                    .map(ToString::to_string)
+                    .unwrap_or_default();
+                let material_index = section
+                    .and_then(|s| usize::try_from(s.material_index).ok())
+                    .filter(|m| *m < lod.materials.len());
+                let material = material_index
+                    .map(|m| &lod.materials[m].material_name)
+                    .or_else(|| section.and_then(|s| s.material.as_ref()))
+                    .map(ToString::to_string)
+                    .unwrap_or_default();
+
+                ObjFace {
+                    vertices: face
+                        .vertex_indices
+                        .iter()
+                        .map(|v| {
+                            let v = *v as usize;
+                            (v, has_uvs.then_some(v), has_normals.then_some(v))
+                        })
+                        .collect(),
+                    texture,
+                    material,
+                    rvmat: material_index.map(|m| &rvmats[m]),
+                    groups,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let _ = writeln!(self.body, "o {}", sanitize(name));
+        for v in &lod.vertices {
+            let _ = writeln!(self.body, "v {} {} {}", -v.x, v.y, v.z);
+        }
+        if has_uvs {
+            for [u, v] in &uvs {
+                let _ = writeln!(self.body, "vt {u} {}", 1.0 - v);
+            }
+        }
+        if has_normals {
+            for n in &lod.normals {
+                let _ = writeln!(self.body, "vn {} {} {}", -n.x, n.y, n.z);
+            }
+        }
+
+        self.write_faces(&faces);
+        self.vertex_offset += vertex_count;
+        self.uv_offset += if has_uvs { vertex_count } else { 0 };
+        self.normal_offset += if has_normals { vertex_count } else { 0 };
+    }
+
+    fn add_mlod_lod(&mut self, name: &str, lod: &MlodLod) {
+        let faces: Vec<ObjFace> = lod
+            .faces
+            .iter()
+            .enumerate()
+            .scan(0, |uv_index, (i, face)| {
+                let vertices = face
+                    .vertices
+                    .iter()
+                    .map(|v| {
+                        *uv_index += 1;
+                        (
+                            v.point_index as usize,
+                            Some(*uv_index - 1),
+                            Some(v.normal_index as usize),
+                        )
+                    })
+                    .collect();
+
+                Some(ObjFace {
+                    vertices,
+                    texture: face.texture.to_string(),
+                    material: face.material.to_string(),
+                    rvmat: None,
+                    groups: self.groups(
+                        lod.taggs
+                            .named_selections
+                            .iter()
+                            .filter(|s| s.contains_face(i))
+                            .map(|s| s.name.to_string()),
+                    ),
+                })
+            })
+            .collect();
+
+        let _ = writeln!(self.body, "o {}", sanitize(name));
+        for p in &lod.points {
+            let p = p.position;
+            let _ = writeln!(self.body, "v {} {} {}", -p.x, p.y, p.z);
+        }
+        let mut uv_count = 0;
+        for v in lod.faces.iter().flat_map(|f| &f.vertices) {
+            let _ = writeln!(self.body, "vt {} {}", v.u, 1.0 - v.v);
+            uv_count += 1;
+        }
+        for n in &lod.normals {
+            let _ = writeln!(self.body, "vn {} {} {}", -n.x, n.y, n.z);
+        }
+
+        self.write_faces(&faces);
+        self.vertex_offset += lod.points.len();
+        self.uv_offset += uv_count;
+        self.normal_offset += lod.normals.len();
+    }
+
+    fn groups(&self, selections: impl Iterator<Item = String>) -> Vec<String> {
+        match self.options.grouping {
+            ObjGrouping::Lod => Vec::new(),
+            ObjGrouping::NamedSelection => selections.map(|s| sanitize(&s)).collect(),
+        }
+    }
+
+    fn write_faces(&mut self, faces: &[ObjFace]) {
+        let mut current_material = None;
+        let mut current_groups = None;
+
+        for face in faces {
+            if self.options.grouping == ObjGrouping::NamedSelection
+                && current_groups.as_ref() != Some(&face.groups)
+            {
+                if face.groups.is_empty() {
+                    self.body.push_str("g default\n");
+                } else {
+                    let _ = writeln!(self.body, "g {}", face.groups.join(" "));
+                }
+                current_groups = Some(face.groups.clone());
+            }
+
+            let material = self.material(&face.texture, &face.material, face.rvmat);
+            if current_material.as_ref() != Some(&material) {
+                let _ = writeln!(self.body, "usemtl {material}");
+                current_material = Some(material);
+            }
+
+            self.body.push('f');
+            for (v, uv, n) in &face.vertices {
+                let v = v + self.vertex_offset;
+                let _ = match (uv, n) {
+                    (Some(uv), Some(n)) => {
+                        write!(
+                            self.body,
+                            " {v}/{}/{}",
+                            uv + self.uv_offset,
+                            n + self.normal_offset
+                        )
+                    }
+                    (Some(uv), None) => write!(self.body, " {v}/{}", uv + self.uv_offset),
+                    (None, Some(n)) => write!(self.body, " {v}//{}", n + self.normal_offset),
+                    (None, None) => write!(self.body, " {v}"),
+                };
+            }
+            self.body.push('\n');
+        }
+    }
+
+    /// Name of the MTL material for a texture and rvmat pair, adding it on first use.
+    fn material(&mut self, texture: &str, material: &str, rvmat: Option<&Rvmat>) -> String {
+        let key = format!("{texture}|{material}");
+        if let Some(name) = self.materials.get(&key) {
+            return name.clone();
+        }
+
+        let stem = |path: &str| {
+            path.rsplit(['\\', '/'])
+                .next()
+                .and_then(|f| f.split('.').next())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let label = if texture.is_empty() || texture.starts_with('#') {
+            stem(material)
+        } else {
+            stem(texture)
+        };
+        let name = if label.is_empty() {
+            format!("mat{}", self.materials.len())
+        } else {
+            sanitize(&format!("mat{}_{label}", self.materials.len()))
+        };
+
+        let _ = writeln!(self.mtl, "newmtl {name}");
+        if let Some(rvmat) = rvmat {
+            let (d, s, e) = (rvmat.diffuse, rvmat.specular, rvmat.emissive);
+            let _ = writeln!(self.mtl, "Kd {} {} {}", d.r, d.g, d.b);
+            let _ = writeln!(self.mtl, "Ks {} {} {}", s.r, s.g, s.b);
+            let _ = writeln!(self.mtl, "Ke {} {} {}", e.r, e.g, e.b);
+            let _ = writeln!(self.mtl, "Ns {}", rvmat.specular_power);
+        } else {
+            self.mtl.push_str("Kd 1 1 1\n");
+        }
+        if !material.is_empty() {
+            let _ = writeln!(self.mtl, "# rvmat {material}");
+        }
+        let extension = self.options.texture_extension.as_deref();
+        // Procedural textures like `#(argb,8,8,3)color(1,1,1,1)` can't be referenced
+        if !texture.is_empty() && !texture.starts_with('#') {
+            let _ = writeln!(self.mtl, "map_Kd {}", texture_uri(texture, extension));
+        }
+        if let Some(rvmat) = rvmat {
+            if let Some(normal) = rvmat.texture_of(TextureSuffix::Nohq) {
+                let _ = writeln!(self.mtl, "map_Bump {}", texture_uri(normal, extension));
+            }
+            if let Some(specular) = rvmat.texture_of(TextureSuffix::Smdi) {
+                let _ = writeln!(self.mtl, "map_Ks {}", texture_uri(specular, extension));
+            }
+        }
+        self.mtl.push('\n');
+
+        self.materials.insert(key, name.clone());
+        name
+    }
+
+    fn finish(self) -> Obj {
+        Obj {
+            body: self.body,
+            mtl: self.mtl,
+        }
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect()
+}
@@ -1,19 +1,25 @@
-use binrw::{BinRead, BinResult, NullString};
+use binrw::{BinRead, BinResult, BinWrite, Endian, NullString};
 use byteorder::ReadBytesExt;
 use std::{
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap},
     fs::File,
-    io::{BufReader, Cursor, Read, Seek, SeekFrom},
-    marker::PhantomData,
+    io::{BufReader, Cursor, Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
 use crate::{
-    core::decompress_lzss_unk_size, errors::AffError, real_virtuality::p3d::model_info::ModelInfo,
+    core::decompress_lzss_unk_size,
+    errors::{AffError, OdolError},
+    real_virtuality::p3d::model_info::ModelInfo,
 };
-use derivative::Derivative;
 
-use super::{animations::Animations, face_data::FaceData, lod::Lod};
+use super::{
+    animations::Animations,
+    face_data::FaceData,
+    legacy::{LegacyLod, LegacyModelInfo},
+    lod::Lod,
+    Resolution,
+};
 
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 pub struct ODOLArgs {
@@ -23,16 +29,41 @@ pub struct ODOLArgs {
     pub skip_lods: bool,
 }
 
+/// Oldest ODOL version that can be read, used by the models of OFP.
+///
+/// Versions 7 and 20 to 27 use the legacy layout, other versions before
+/// [`ODOL_MIN_MODERN_VERSION`] are rejected with [`OdolError::UnsupportedVersion`].
+pub const ODOL_MIN_VERSION: u32 = 7;
+/// Oldest ODOL version storing the model info before the LODs.
+///
+/// Older models store the LODs first and are read with [`LegacyLod`] and [`LegacyModelInfo`].
+pub const ODOL_MIN_MODERN_VERSION: u32 = 28;
+/// Newest known ODOL version.
+pub const ODOL_MAX_VERSION: u32 = 73;
+/// Oldest ODOL version that can be written, older versions need LZSS compression.
+pub const ODOL_MIN_WRITE_VERSION: u32 = 44;
+
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 pub struct ODOLOptions {
     pub skip_lods: bool,
+
+    /// Read versions newer than [`ODOL_MAX_VERSION`] with the newest known layout instead of failing.
+    pub allow_newer_versions: bool,
 }
 
 #[derive(Debug, Default, PartialEq, Clone, BinRead)]
 #[br(magic = b"ODOL")]
 #[br(import(options: ODOLOptions))]
 pub struct ODOL {
-    #[br(assert((28..=73).contains(&version), "ODOL Version {} Unsupported", version))]
+    /// Position of the model in the stream, LOD addresses are relative to it.
+    #[br(parse_with = magic_position)]
+    stream_offset: u64,
+
+    #[br(assert(
+        version >= ODOL_MIN_MODERN_VERSION && Self::is_supported_version(version, options),
+        "ODOL Version {} Unsupported",
+        version
+    ))]
     pub version: u32,
 
     #[br(calc = version >= 44)]
@@ -89,15 +120,96 @@ pub struct ODOL {
     })]
     pub face_defaults: Vec<Option<FaceData>>,
 
-    #[br(args(lod_count as usize, &start_address_of_lods, args,))]
+    #[br(args(lod_count as usize, &start_address_of_lods, stream_offset, args,))]
     #[br(parse_with = read_lods)]
     pub lods: Vec<Lod>,
 }
 
+/// Counts and LOD addresses are taken from the written data.
+impl BinWrite for ODOL {
+    type Args<'a> = ();
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: Endian,
+        _args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        let version = self.version;
+        let args = ODOLArgs {
+            version,
+            use_lzo: version >= 44,
+            use_compression_flag: version >= 64,
+            skip_lods: false,
+        };
+        let lod_count = self.lods.len().min(self.resolutions.len());
+        let base = writer.stream_position()?;
+
+        b"ODOL".write_options(writer, endian, ())?;
+        version.write_options(writer, endian, ())?;
+        if version >= 59 {
+            self.app_id.write_options(writer, endian, ())?;
+        }
+        if version >= 58 {
+            self.p3d_prefix
+                .clone()
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+        }
+
+        (lod_count as u32).write_options(writer, endian, ())?;
+        self.resolutions[..lod_count].write_options(writer, endian, ())?;
+        self.model_info
+            .write_options(writer, endian, (args, lod_count as u32))?;
+
+        if version >= 30 {
+            u8::from(self.animations.is_some()).write_options(writer, endian, ())?;
+            if let Some(animations) = &self.animations {
+                animations.write_options(writer, endian, (args,))?;
+            }
+        }
+
+        let addresses_pos = writer.stream_position()?;
+        vec![0_u32; lod_count * 2].write_options(writer, endian, ())?;
+
+        let face_defaults: Vec<Option<&FaceData>> = (0..lod_count)
+            .map(|i| self.face_defaults.get(i).and_then(Option::as_ref))
+            .collect();
+        for face_data in &face_defaults {
+            u8::from(face_data.is_some()).write_options(writer, endian, ())?;
+        }
+        for face_data in face_defaults.into_iter().flatten() {
+            face_data.write_options(writer, endian, (args,))?;
+        }
+
+        let mut start_addresses = Vec::with_capacity(lod_count);
+        let mut end_addresses = Vec::with_capacity(lod_count);
+        for lod in &self.lods[..lod_count] {
+            start_addresses.push((writer.stream_position()? - base) as u32);
+            lod.write_options(writer, endian, (args,))?;
+            end_addresses.push((writer.stream_position()? - base) as u32);
+        }
+
+        let end_pos = writer.stream_position()?;
+        writer.seek(SeekFrom::Start(addresses_pos))?;
+        start_addresses.write_options(writer, endian, ())?;
+        end_addresses.write_options(writer, endian, ())?;
+        writer.seek(SeekFrom::Start(end_pos))?;
+
+        Ok(())
+    }
+}
+
+#[binrw::parser(reader)]
+fn magic_position() -> BinResult<u64> {
+    Ok(reader.stream_position()? - 4)
+}
+
 #[binrw::parser(reader, endian)]
 pub fn read_lods(
     count: usize,
     start_address_of_lods: &[u32],
+    stream_offset: u64,
     args: ODOLArgs,
 ) -> BinResult<Vec<Lod>> {
     if args.skip_lods {
@@ -106,99 +218,14 @@ pub fn read_lods(
 
     let mut lods = Vec::with_capacity(count);
 
-    #[allow(clippy::needless_range_loop)]
-    for i in 0..count {
-        println!("Lod Index: {i}");
-        reader.seek(SeekFrom::Start(start_address_of_lods[i].into()))?;
+    for address in start_address_of_lods.iter().take(count) {
+        reader.seek(SeekFrom::Start(stream_offset + u64::from(*address)))?;
         lods.push(Lod::read_options(reader, endian, (args,))?);
     }
 
     Ok(lods)
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
-pub struct Resolution {
-    pub value: f32,
-
-    #[br(args { value })]
-    pub res: ResolutionEnum,
-}
-
-#[derive(Debug, PartialEq, Eq, Clone, Copy, BinRead, Derivative)]
-#[derivative(Default)]
-#[br(import { value: f32 })]
-pub enum ResolutionEnum {
-    #[br(pre_assert(value < 1E3f32))]
-    GraphicalLod,
-    #[br(pre_assert(value == 1E3f32))]
-    ViewGunner,
-    #[br(pre_assert(value == 1.1E3f32))]
-    ViewPilot,
-    #[br(pre_assert(value == 1.2E3f32))]
-    ViewCargo,
-    #[br(pre_assert(value == 1.202E3f32))]
-    ViewUnknown,
-    #[br(pre_assert(value == 1E4f32))]
-    ShadowVolume,
-    #[br(pre_assert(value == 1.001E4f32))]
-    ShadowVolume2,
-    #[br(pre_assert(value == 1.1E4f32))]
-    StencilShadow,
-    #[br(pre_assert(value == 1.101E4f32))]
-    StencilShadow2,
-    #[br(pre_assert(value == 1.102E4f32))]
-    StencilShadowUnknown,
-    #[br(pre_assert(value == 1E13f32))]
-    Geometry,
-    #[br(pre_assert(value == 4E13f32))]
-    Unknown4E13,
-    #[br(pre_assert(value == 1E15f32))]
-    Memory,
-    #[br(pre_assert(value == 2E15f32))]
-    LandContact,
-    #[br(pre_assert(value == 3E15f32))]
-    Roadway,
-    #[br(pre_assert(value == 4E15f32))]
-    Paths,
-    #[br(pre_assert(value == 5E15f32))]
-    HitPoints,
-    #[br(pre_assert(value == 6E15f32))]
-    ViewGeometry,
-    #[br(pre_assert(value == 7E15f32))]
-    FireGeometry,
-    #[br(pre_assert(value == 8E15f32))]
-    ViewCargoGeometry,
-    #[br(pre_assert(value == 9E15f32))]
-    ViewCargoFireGeometry,
-    #[br(pre_assert(value == 1E16f32))]
-    ViewCommander,
-    #[br(pre_assert(value == 1.1E16f32))]
-    ViewCommanderGeometry,
-    #[br(pre_assert(value == 1.2E16f32))]
-    ViewCommanderFireGeometry,
-    #[br(pre_assert(value == 1.3E16f32))]
-    ViewPilotGeometry,
-    #[br(pre_assert(value == 1.4E16f32))]
-    ViewPilotFireGeometry,
-    #[br(pre_assert(value == 1.5E16f32))]
-    ViewGunnerGeometry,
-    #[br(pre_assert(value == 1.6E16f32))]
-    ViewGunnerFireGeometry,
-    #[br(pre_assert(value == 1.7E16f32))]
-    SubParts,
-    #[br(pre_assert(value == 1.8E16f32))]
-    ShadowVolumeViewCargo,
-    #[br(pre_assert(value == 1.9E16f32))]
-    ShadowVolumeViewPilot,
-    #[br(pre_assert(value == 2E16f32))]
-    ShadowVolumeViewGunner,
-    #[br(pre_assert(value == 2.1E16f32))]
-    Wreck,
-    #[br(pre_assert(true))]
-    #[derivative(Default)]
-    Unknown(PhantomData<f32>),
-}
-
 impl ODOL {
     #[must_use]
     pub fn new() -> Self {
@@ -215,54 +242,251 @@ impl ODOL {
     where
         R: Read + Seek,
     {
-        Self::read(reader, true)
+        Self::from_stream_with_options(
+            reader,
+            ODOLOptions {
+                skip_lods: true,
+                ..Default::default()
+            },
+        )
     }
 
     pub fn from_stream<R>(reader: &mut R) -> Result<Self, AffError>
     where
         R: Read + Seek,
     {
-        Self::read(reader, false)
+        Self::from_stream_with_options(reader, ODOLOptions::default())
+    }
+
+    pub fn from_stream_with_options<R>(
+        reader: &mut R,
+        options: ODOLOptions,
+    ) -> Result<Self, AffError>
+    where
+        R: Read + Seek,
+    {
+        if let Some(data) = Self::decompress_if_lzss(reader)? {
+            return Self::read_checked(&mut Cursor::new(data), options);
+        }
+        Self::read_checked(reader, options)
+    }
+
+    fn read_checked<R>(reader: &mut R, options: ODOLOptions) -> Result<Self, AffError>
+    where
+        R: Read + Seek,
+    {
+        match Self::check_version(reader, options)? {
+            Some(version) if version < ODOL_MIN_MODERN_VERSION => {
+                Self::read_legacy(reader, version, options)
+            }
+            _ => Ok(Self::read_le_args(reader, (options,))?),
+        }
     }
 
-    fn read<R>(reader: &mut R, skip_lods: bool) -> Result<Self, AffError>
+    /// Reads the layout of versions before [`ODOL_MIN_MODERN_VERSION`], the LODs are followed by
+    /// the resolutions and the model info.
+    ///
+    /// LOD addresses are recorded while reading, so single LODs can be read later on.
+    fn read_legacy<R>(reader: &mut R, version: u32, options: ODOLOptions) -> Result<Self, AffError>
     where
         R: Read + Seek,
     {
-        let opt = ODOLOptions { skip_lods };
+        let stream_offset = reader.stream_position()?;
+        reader.seek(SeekFrom::Current(8))?;
+        let lod_count = u32::read_le(reader)?;
+        let args = ODOLArgs {
+            version,
+            use_lzo: false,
+            use_compression_flag: false,
+            skip_lods: options.skip_lods,
+        };
+
+        let mut lods = Vec::new();
+        let mut start_address_of_lods = Vec::with_capacity(lod_count as usize);
+        let mut end_address_of_lods = Vec::with_capacity(lod_count as usize);
+        for _ in 0..lod_count {
+            start_address_of_lods.push((reader.stream_position()? - stream_offset) as u32);
+            let lod = LegacyLod::read_le_args(reader, (args,))?;
+            end_address_of_lods.push((reader.stream_position()? - stream_offset) as u32);
+            if !options.skip_lods {
+                lods.push(lod.into());
+            }
+        }
 
+        let resolutions = (0..lod_count)
+            .map(|_| Resolution::read_le(reader))
+            .collect::<BinResult<_>>()?;
+        let model_info = LegacyModelInfo::read_le(reader)?.into();
+
+        Ok(Self {
+            stream_offset,
+            version,
+            args,
+            lod_count,
+            resolutions,
+            model_info,
+            start_address_of_lods,
+            end_address_of_lods,
+            lods,
+            ..Default::default()
+        })
+    }
+
+    /// Writes the model uncompressed, all LODs have to be loaded.
+    pub fn write<W>(&self, writer: &mut W) -> Result<(), AffError>
+    where
+        W: Write + Seek,
+    {
+        if self.version < ODOL_MIN_WRITE_VERSION {
+            return Err(OdolError::UnsupportedVersion(self.version).into());
+        }
+        if self.version > ODOL_MAX_VERSION {
+            return Err(OdolError::UnknownVersion(self.version).into());
+        }
+        if self.lods.len() < self.resolutions.len() {
+            return Err(AffError::LodNotFound(self.lods.len()));
+        }
+
+        self.write_le(writer)?;
+        Ok(())
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<(), AffError> {
+        let mut file = File::create(path)?;
+        self.write(&mut file)
+    }
+
+    pub fn write_data(&self) -> Result<Vec<u8>, AffError> {
+        let mut buf = Vec::new();
+        self.write(&mut Cursor::new(&mut buf))?;
+        Ok(buf)
+    }
+
+    /// Removes a LOD, per LOD data and LOD indices of the model info are updated accordingly.
+    pub fn remove_lod(&mut self, index: usize) -> Result<Lod, AffError> {
+        if index >= self.lods.len() {
+            return Err(AffError::LodNotFound(index));
+        }
+
+        remove_at(&mut self.resolutions, index);
+        remove_at(&mut self.start_address_of_lods, index);
+        remove_at(&mut self.end_address_of_lods, index);
+        remove_at(&mut self.use_defaults, index);
+        remove_at(&mut self.face_defaults, index);
+        if let Some(animations) = &mut self.animations {
+            remove_at(&mut animations.bones_2_anims, index);
+            remove_at(&mut animations.anims_2_bones, index);
+        }
+        self.model_info.remove_lod(index);
+
+        self.lod_count = self.lod_count.saturating_sub(1);
+        Ok(self.lods.remove(index))
+    }
+
+    /// Whether models of this version can be read.
+    #[must_use]
+    pub const fn is_supported_version(version: u32, options: ODOLOptions) -> bool {
+        if version < ODOL_MIN_MODERN_VERSION {
+            return version == ODOL_MIN_VERSION || version >= 20;
+        }
+        options.allow_newer_versions || version <= ODOL_MAX_VERSION
+    }
+
+    /// Rejects unsupported and unknown versions with a typed error before parsing, the stream position is kept.
+    ///
+    /// Returns the version if the stream starts with an ODOL header.
+    fn check_version<R>(reader: &mut R, options: ODOLOptions) -> Result<Option<u32>, AffError>
+    where
+        R: Read + Seek,
+    {
+        let start = reader.stream_position()?;
+        let mut header = [0_u8; 8];
+        let read = reader.read_exact(&mut header);
+        reader.seek(SeekFrom::Start(start))?;
+
+        // Anything else is reported by the parser
+        if read.is_err() || &header[..4] != b"ODOL" {
+            return Ok(None);
+        }
+
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if version < ODOL_MIN_MODERN_VERSION && !Self::is_supported_version(version, options) {
+            return Err(OdolError::UnsupportedVersion(version).into());
+        }
+        if !Self::is_supported_version(version, options) {
+            return Err(OdolError::UnknownVersion(version).into());
+        }
+        Ok(Some(version))
+    }
+
+    /// Decompressed data if the stream is a LZSS compressed ODOL, returns to the start position otherwise.
+    fn decompress_if_lzss<R>(reader: &mut R) -> Result<Option<Vec<u8>>, AffError>
+    where
+        R: Read + Seek,
+    {
+        let start = reader.stream_position()?;
         let mut magic_buf = vec![0_u8; 4];
         reader.read_exact(&mut magic_buf)?;
-        reader.rewind()?;
+        reader.seek(SeekFrom::Start(start))?;
         if magic_buf != b"ODOL" {
             reader.read_u8()?;
             reader.read_exact(&mut magic_buf)?;
             if magic_buf == b"ODOL" {
-                let data = decompress_lzss_unk_size(reader)?;
-                let mut cursor = Cursor::new(data);
-
-                return Ok(Self::read_le_args(&mut cursor, (opt,))?);
+                return Ok(Some(decompress_lzss_unk_size(reader)?));
             }
+            reader.seek(SeekFrom::Start(start))?;
         }
-        Ok(Self::read_le_args(reader, (opt,))?)
+        Ok(None)
     }
 
-    pub fn read_lod<RS>(&self, reader: &mut RS, resolution: ResolutionEnum) -> Result<Lod, AffError>
+    /// Index of the LOD with exactly this resolution.
+    #[must_use]
+    pub fn lod_index(&self, resolution: Resolution) -> Option<usize> {
+        self.resolutions.iter().position(|r| *r == resolution)
+    }
+
+    fn lod_start_address(&self, lod_index: usize) -> Result<u64, AffError> {
+        self.start_address_of_lods
+            .get(lod_index)
+            .map(|address| self.stream_offset + u64::from(*address))
+            .ok_or(AffError::LodNotFound(lod_index))
+    }
+
+    fn read_lod_at<RS>(&self, reader: &mut RS, start_address: u64) -> Result<Lod, AffError>
     where
         RS: Read + Seek,
     {
-        if let Some(lod_index) = self.resolutions.iter().position(|r| r.res == resolution) {
-            if let Some(start_address) = self.start_address_of_lods.get(lod_index) {
-                reader.seek(SeekFrom::Start((*start_address).into()))?;
-                let lod = Lod::read_le_args(reader, (self.args,))?;
-                //self.lods.insert(resolution, lod);
-                // doesnt handle lzss btw
-
-                return Ok(lod);
-            }
+        reader.seek(SeekFrom::Start(start_address))?;
+        if self.version < ODOL_MIN_MODERN_VERSION {
+            return Ok(LegacyLod::read_le_args(reader, (self.args,))?.into());
         }
+        Ok(Lod::read_le_args(reader, (self.args,))?)
+    }
 
-        todo!()
+    /// Reads a single LOD from the stream the model was read from, positioned at the start of the model.
+    ///
+    /// LZSS compressed streams are decompressed as a whole on every call,
+    /// use [`OdolLazyReader`] to read several LODs.
+    pub fn read_lod<RS>(&self, reader: &mut RS, resolution: Resolution) -> Result<Lod, AffError>
+    where
+        RS: Read + Seek,
+    {
+        let lod_index = self
+            .lod_index(resolution)
+            .ok_or(OdolError::ResolutionNotFound(resolution))?;
+        let start_address = self.lod_start_address(lod_index)?;
+
+        // LOD addresses refer to the decompressed data
+        if let Some(data) = Self::decompress_if_lzss(reader)? {
+            return self.read_lod_at(&mut Cursor::new(data), start_address);
+        }
+        self.read_lod_at(reader, start_address)
+    }
+}
+
+fn remove_at<T>(v: &mut Vec<T>, index: usize) {
+    if index < v.len() {
+        v.remove(index);
     }
 }
 
@@ -272,8 +496,14 @@ where
     R: Read + Seek,
 {
     reader: R,
+
+    /// Whole model if the stream is LZSS compressed, as LOD addresses refer to the decompressed data.
+    decompressed: Option<Cursor<Vec<u8>>>,
+
     pub odol: ODOL,
-    pub lods: HashMap<ResolutionEnum, Lod>,
+
+    /// Loaded LODs by their index in the model.
+    pub lods: HashMap<usize, Lod>,
 }
 
 impl<R> OdolLazyReader<R>
@@ -281,30 +511,37 @@ where
     R: Read + Seek,
 {
     pub fn from_reader(mut reader: R) -> Result<Self, AffError> {
-        let odol = ODOL::from_stream_lazy(&mut reader)?;
+        let mut decompressed = ODOL::decompress_if_lzss(&mut reader)?.map(Cursor::new);
+        let odol = match decompressed.as_mut() {
+            Some(cursor) => ODOL::from_stream_lazy(cursor)?,
+            None => ODOL::from_stream_lazy(&mut reader)?,
+        };
+
         Ok(Self {
             lods: HashMap::new(),
             reader,
+            decompressed,
             odol,
         })
     }
 
-    pub fn read_lod(&mut self, resolution: ResolutionEnum) -> Result<Lod, AffError> {
-        if let Some(lod_index) = self
+    /// Reads the LOD on first access, later calls return the cached LOD.
+    pub fn read_lod(&mut self, resolution: Resolution) -> Result<&Lod, AffError> {
+        let lod_index = self
             .odol
-            .resolutions
-            .iter()
-            .position(|r| r.res == resolution)
-        {
-            if let Some(start_address) = self.odol.start_address_of_lods.get(lod_index) {
-                self.reader.seek(SeekFrom::Start((*start_address).into()))?;
-                let lod = Lod::read_le_args(&mut self.reader, (self.odol.args,))?;
-                //self.lods.insert(resolution, lod);
-
-                return Ok(lod);
+            .lod_index(resolution)
+            .ok_or(OdolError::ResolutionNotFound(resolution))?;
+        let lod = match self.lods.entry(lod_index) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let start_address = self.odol.lod_start_address(lod_index)?;
+                let lod = match self.decompressed.as_mut() {
+                    Some(cursor) => self.odol.read_lod_at(cursor, start_address)?,
+                    None => self.odol.read_lod_at(&mut self.reader, start_address)?,
+                };
+                entry.insert(lod)
             }
-        }
-
-        todo!()
+        };
+        Ok(lod)
     }
 }
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::AffError,
+    real_virtuality::types::{TransformMatrix, XYZTriplet},
+};
+
+use super::{AnimAddress, AnimBones, AnimType, AnimationClass, Lod, ODOL};
+
+/// Model space transform of a skeleton bone, including the transforms of its parents.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BonePose {
+    pub transform: TransformMatrix,
+
+    /// Hidden bones are collapsed by a zero transform.
+    pub hidden: bool,
+}
+
+impl Default for BonePose {
+    fn default() -> Self {
+        Self {
+            transform: TransformMatrix::IDENTITY,
+            hidden: false,
+        }
+    }
+}
+
+impl AnimationClass {
+    /// Animation phase between 0 and 1 for a source value, wrapped according to the source address.
+    #[must_use]
+    pub fn phase(&self, value: f32) -> f32 {
+        let range = self.max_value - self.min_value;
+        if range == 0.0 {
+            return if value >= self.max_value { 1.0 } else { 0.0 };
+        }
+
+        let phase = (value - self.min_value) / range;
+        match self.source_address {
+            AnimAddress::AnimClamp | AnimAddress::NAnimAddress => phase.clamp(0.0, 1.0),
+            AnimAddress::AnimLoop => phase.rem_euclid(1.0),
+            AnimAddress::AnimMirror => {
+                let phase = phase.rem_euclid(2.0);
+                if phase > 1.0 {
+                    2.0 - phase
+                } else {
+                    phase
+                }
+            }
+        }
+    }
+
+    /// Transform of the animated bone at the given phase, `None` if the bone is hidden.
+    ///
+    /// Hide animations hide the bone from the hide value until the unhide value.
+    #[must_use]
+    pub fn transform(&self, phase: f32, bone: &AnimBones) -> Option<TransformMatrix> {
+        let lerp = |a: Option<f32>, b: Option<f32>| {
+            let (a, b) = (a.unwrap_or_default(), b.unwrap_or_default());
+            (b - a).mul_add(phase, a)
+        };
+        let axis_pos = bone.axis_pos.unwrap_or_default();
+        let axis_dir = bone.axis_dir.unwrap_or_default();
+
+        let transform = match self.anim_transform_type {
+            AnimType::Rotation => {
+                TransformMatrix::rotation(axis_pos, axis_dir, lerp(self.angle_0, self.angle_1))
+            }
+            AnimType::RotationX => TransformMatrix::rotation(
+                axis_pos,
+                XYZTriplet::new(1.0, 0.0, 0.0),
+                lerp(self.angle_0, self.angle_1),
+            ),
+            AnimType::RotationY => TransformMatrix::rotation(
+                axis_pos,
+                XYZTriplet::new(0.0, 1.0, 0.0),
+                lerp(self.angle_0, self.angle_1),
+            ),
+            AnimType::RotationZ => TransformMatrix::rotation(
+                axis_pos,
+                XYZTriplet::new(0.0, 0.0, 1.0),
+                lerp(self.angle_0, self.angle_1),
+            ),
+            AnimType::Translation => {
+                TransformMatrix::translation(axis_dir.scaled(lerp(self.offset_0, self.offset_1)))
+            }
+            AnimType::TranslationX => TransformMatrix::translation(XYZTriplet::new(
+                lerp(self.offset_0, self.offset_1),
+                0.0,
+                0.0,
+            )),
+            AnimType::TranslationY => TransformMatrix::translation(XYZTriplet::new(
+                0.0,
+                lerp(self.offset_0, self.offset_1),
+                0.0,
+            )),
+            AnimType::TranslationZ => TransformMatrix::translation(XYZTriplet::new(
+                0.0,
+                0.0,
+                lerp(self.offset_0, self.offset_1),
+            )),
+            AnimType::Direct => {
+                let axis_dir = self.axis_dir.unwrap_or_default();
+                let offset = axis_dir
+                    .normalized()
+                    .scaled(self.axis_offset.unwrap_or_default() * phase);
+                TransformMatrix::translation(offset).multiply(&TransformMatrix::rotation(
+                    self.axis_pos.unwrap_or_default(),
+                    axis_dir,
+                    self.axis_angle.unwrap_or_default() * phase,
+                ))
+            }
+            AnimType::Hide => {
+                let hide_value = self.hide_value.unwrap_or(f32::MAX);
+                // Unhide values not above the hide value never show the bone again
+                let unhide_value = self
+                    .unhide_value
+                    .filter(|v| *v > hide_value)
+                    .unwrap_or(f32::MAX);
+                if (hide_value..unhide_value).contains(&phase) {
+                    return None;
+                }
+                TransformMatrix::IDENTITY
+            }
+        };
+        Some(transform)
+    }
+}
+
+impl ODOL {
+    /// Evaluates the model animations for a LOD, indexed by skeleton bone.
+    ///
+    /// Sources are matched case insensitive, missing sources default to `0`.
+    pub fn bone_poses(
+        &self,
+        lod_index: usize,
+        sources: &HashMap<String, f32>,
+    ) -> Result<Vec<BonePose>, AffError> {
+        if lod_index >= self.lods.len().max(self.resolutions.len()) {
+            return Err(AffError::LodNotFound(lod_index));
+        }
+
+        let tree = self.model_info.skeleton.tree();
+        let mut local = vec![BonePose::default(); tree.bones.len()];
+
+        if let Some(animations) = &self.animations {
+            let lod_bones = animations
+                .anims_2_bones
+                .get(lod_index)
+                .map_or(&[][..], |a| a.animation_class_indices.as_slice());
+
+            for (class, bone) in animations.animation_classes.iter().zip(lod_bones) {
+                let Some(pose) = usize::try_from(bone.skeleton_bone_name_index)
+                    .ok()
+                    .and_then(|b| local.get_mut(b))
+                else {
+                    continue;
+                };
+
+                let value = sources
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(&class.anim_source.to_string()))
+                    .map_or(0.0, |(_, v)| *v);
+
+                match class.transform(class.phase(value), bone) {
+                    Some(transform) => pose.transform = transform.multiply(&pose.transform),
+                    None => pose.hidden = true,
+                }
+            }
+        }
+
+        let mut poses = local.clone();
+        for index in tree.depth_first() {
+            let mut pose = local[index];
+            if let Some(parent) = tree.bones[index].parent.map(|p| poses[p]) {
+                pose.transform = parent.transform.multiply(&pose.transform);
+                pose.hidden |= parent.hidden;
+            }
+            if pose.hidden {
+                pose.transform = TransformMatrix::default();
+            }
+            poses[index] = pose;
+        }
+
+        Ok(poses)
+    }
+
+    /// Copy of a LOD with the animations for `sources` applied to its vertices and normals.
+    pub fn animated_lod(
+        &self,
+        lod_index: usize,
+        sources: &HashMap<String, f32>,
+    ) -> Result<Lod, AffError> {
+        let mut lod = self
+            .lods
+            .get(lod_index)
+            .cloned()
+            .ok_or(AffError::LodNotFound(lod_index))?;
+        let poses = self.bone_poses(lod_index, sources)?;
+        lod.apply_bone_poses(&poses);
+        Ok(lod)
+    }
+}
+
+impl Lod {
+    /// Deforms vertices and normals by their weighted skeleton bone poses.
+    pub fn apply_bone_poses(&mut self, poses: &[BonePose]) {
+        if self.vertex_bone_ref.len() != self.vertices.len() {
+            return;
+        }
+        let has_normals = self.normals.len() == self.vertices.len();
+
+        for (i, weights) in self.vertex_bone_ref.iter().enumerate() {
+            let transforms: Vec<(TransformMatrix, f32)> = weights
+                .animation_rt_pairs
+                .iter()
+                .filter_map(|pair| {
+                    let pose = self
+                        .skeleton_bone(pair.selection_index.into())
+                        .and_then(|b| poses.get(b))?;
+                    Some((pose.transform, f32::from(pair.weight)))
+                })
+                .collect();
+
+            let total: f32 = transforms.iter().map(|(_, w)| w).sum();
+            if total <= 0.0 {
+                continue;
+            }
+
+            let blend = |f: &dyn Fn(&TransformMatrix) -> XYZTriplet| {
+                transforms
+                    .iter()
+                    .fold(XYZTriplet::default(), |acc, (m, w)| {
+                        acc.add(&f(m).scaled(w / total))
+                    })
+            };
+
+            let vertex = self.vertices[i];
+            self.vertices[i] = blend(&|m| m.transform_point(&vertex));
+            if has_normals {
+                let normal = self.normals[i];
+                self.normals[i] = blend(&|m| m.transform_vector(&normal)).normalized();
+            }
+        }
+    }
+}
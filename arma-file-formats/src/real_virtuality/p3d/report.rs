@@ -0,0 +1,281 @@
+use std::{collections::HashMap, fmt};
+
+use crate::real_virtuality::types::XYZTriplet;
+
+use super::{Lod, Resolution, ResolutionEnum, ODOL};
+
+/// LODs checked for holes, collisions are computed on closed convex components.
+const CLOSED_LODS: [ResolutionEnum; 3] = [
+    ResolutionEnum::Geometry,
+    ResolutionEnum::FireGeometry,
+    ResolutionEnum::ViewGeometry,
+];
+
+/// Tolerance for vertices outside the model bounding box.
+const BBOX_TOLERANCE: f32 = 0.01;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ModelReportOptions {
+    /// Sections per LOD before [`ModelIssue::TooManySections`] is reported, every section is a draw call.
+    pub max_sections: usize,
+}
+
+impl Default for ModelReportOptions {
+    fn default() -> Self {
+        Self { max_sections: 50 }
+    }
+}
+
+/// Statistics and common problems of a binarized model.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ModelReport {
+    pub lods: Vec<LodStats>,
+
+    /// Bounding box from the model info.
+    pub bbox_min: XYZTriplet,
+    pub bbox_max: XYZTriplet,
+
+    pub mass: MassStats,
+    pub issues: Vec<ModelIssue>,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct LodStats {
+    pub resolution: Resolution,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub triangle_count: usize,
+    pub section_count: usize,
+    pub material_count: usize,
+    pub texture_count: usize,
+    pub named_selection_count: usize,
+    pub proxy_count: usize,
+
+    /// Bounds of the LOD vertices, `None` for LODs without vertices.
+    pub bounds: Option<(XYZTriplet, XYZTriplet)>,
+}
+
+/// Mass distribution over the points of the geometry LOD.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct MassStats {
+    pub total: f32,
+    pub point_count: usize,
+    pub min: f32,
+    pub max: f32,
+
+    /// Mass weighted center of the geometry LOD, if its vertices match the mass array.
+    pub center: Option<XYZTriplet>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ModelIssue {
+    MissingLod(ResolutionEnum),
+    /// The model has a geometry LOD without mass.
+    ZeroMass,
+    TooManySections {
+        lod: usize,
+        count: usize,
+    },
+    /// Edges of a collision LOD not shared by exactly two faces.
+    OpenGeometry {
+        lod: usize,
+        open_edges: usize,
+    },
+    /// LOD vertices outside the bounding box of the model info.
+    OutsideBoundingBox {
+        lod: usize,
+    },
+}
+
+impl fmt::Display for ModelIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingLod(res) => write!(f, "missing {res:?} LOD"),
+            Self::ZeroMass => write!(f, "geometry LOD without mass"),
+            Self::TooManySections { lod, count } => {
+                write!(f, "LOD {lod} has {count} sections")
+            }
+            Self::OpenGeometry { lod, open_edges } => {
+                write!(f, "LOD {lod} is not closed, {open_edges} open edges")
+            }
+            Self::OutsideBoundingBox { lod } => {
+                write!(f, "LOD {lod} exceeds the model bounding box")
+            }
+        }
+    }
+}
+
+impl ModelReport {
+    #[must_use]
+    pub fn from_odol(odol: &ODOL) -> Self {
+        Self::from_odol_with_options(odol, ModelReportOptions::default())
+    }
+
+    #[must_use]
+    pub fn from_odol_with_options(odol: &ODOL, options: ModelReportOptions) -> Self {
+        let info = &odol.model_info;
+        let lods: Vec<LodStats> = odol
+            .lods
+            .iter()
+            .enumerate()
+            .map(|(i, lod)| {
+                LodStats::from_lod(lod, odol.resolutions.get(i).copied().unwrap_or_default())
+            })
+            .collect();
+
+        let geometry = lod_index(odol, ResolutionEnum::Geometry);
+        let mass = MassStats::new(&info.mass_array, geometry.and_then(|i| odol.lods.get(i)));
+
+        let mut issues = Vec::new();
+        for res in [ResolutionEnum::Geometry, ResolutionEnum::FireGeometry] {
+            if lod_index(odol, res).is_none() {
+                issues.push(ModelIssue::MissingLod(res));
+            }
+        }
+        if geometry.is_some() && info.mass <= 0.0 && mass.total <= 0.0 {
+            issues.push(ModelIssue::ZeroMass);
+        }
+
+        for (i, (lod, stats)) in odol.lods.iter().zip(&lods).enumerate() {
+            if stats.section_count > options.max_sections {
+                issues.push(ModelIssue::TooManySections {
+                    lod: i,
+                    count: stats.section_count,
+                });
+            }
+
+            if CLOSED_LODS.contains(&stats.resolution.res) {
+                let open_edges = open_edges(lod);
+                if open_edges > 0 {
+                    issues.push(ModelIssue::OpenGeometry { lod: i, open_edges });
+                }
+            }
+
+            if let Some((min, max)) = stats.bounds {
+                let outside = |v: f32, min: f32, max: f32| {
+                    v < min - BBOX_TOLERANCE || v > max + BBOX_TOLERANCE
+                };
+                let (bmin, bmax) = (info.bbox_min_pos, info.bbox_max_pos);
+                if outside(min.x, bmin.x, bmax.x)
+                    || outside(min.y, bmin.y, bmax.y)
+                    || outside(min.z, bmin.z, bmax.z)
+                    || outside(max.x, bmin.x, bmax.x)
+                    || outside(max.y, bmin.y, bmax.y)
+                    || outside(max.z, bmin.z, bmax.z)
+                {
+                    issues.push(ModelIssue::OutsideBoundingBox { lod: i });
+                }
+            }
+        }
+
+        Self {
+            lods,
+            bbox_min: info.bbox_min_pos,
+            bbox_max: info.bbox_max_pos,
+            mass,
+            issues,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl LodStats {
+    #[must_use]
+    pub fn from_lod(lod: &Lod, resolution: Resolution) -> Self {
+        let bounds = lod.vertices.split_first().map(|(first, rest)| {
+            rest.iter().fold((*first, *first), |(min, max), v| {
+                (
+                    XYZTriplet::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z)),
+                    XYZTriplet::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z)),
+                )
+            })
+        });
+
+        Self {
+            resolution,
+            vertex_count: lod.vertices.len(),
+            face_count: lod.faces.len(),
+            triangle_count: lod
+                .faces
+                .iter()
+                .map(|f| f.vertex_indices.len().saturating_sub(2))
+                .sum(),
+            section_count: lod.sections.len(),
+            material_count: lod.materials.len(),
+            texture_count: lod.textures.len(),
+            named_selection_count: lod.named_selection.len(),
+            proxy_count: lod.proxies.len(),
+            bounds,
+        }
+    }
+}
+
+impl MassStats {
+    fn new(mass_array: &[f32], geometry: Option<&Lod>) -> Self {
+        let total: f32 = mass_array.iter().sum();
+        let center = geometry
+            .filter(|lod| lod.vertices.len() == mass_array.len() && total > 0.0)
+            .map(|lod| {
+                lod.vertices
+                    .iter()
+                    .zip(mass_array)
+                    .fold(XYZTriplet::default(), |acc, (v, m)| {
+                        acc.add(&v.scaled(m / total))
+                    })
+            });
+
+        Self {
+            total,
+            point_count: mass_array.len(),
+            min: mass_array
+                .iter()
+                .copied()
+                .reduce(f32::min)
+                .unwrap_or_default(),
+            max: mass_array
+                .iter()
+                .copied()
+                .reduce(f32::max)
+                .unwrap_or_default(),
+            center,
+        }
+    }
+}
+
+/// Index of a LOD in the model, it isn't loaded if the model was read with `skip_lods`.
+fn lod_index(odol: &ODOL, resolution: ResolutionEnum) -> Option<usize> {
+    odol.resolutions.iter().position(|r| r.res == resolution)
+}
+
+/// Edges not shared by exactly two faces, vertices are matched by position as they are split by UVs and normals.
+fn open_edges(lod: &Lod) -> usize {
+    let mut positions: HashMap<[u32; 3], usize> = HashMap::new();
+    let welded: Vec<usize> = lod
+        .vertices
+        .iter()
+        .map(|v| {
+            let key = [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()];
+            let next = positions.len();
+            *positions.entry(key).or_insert(next)
+        })
+        .collect();
+
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+    for face in &lod.faces {
+        let indices: Vec<usize> = face
+            .vertex_indices
+            .iter()
+            .filter_map(|i| welded.get(*i as usize).copied())
+            .collect();
+        for (i, a) in indices.iter().enumerate() {
+            let b = indices[(i + 1) % indices.len()];
+            *edges.entry((*a.min(&b), *a.max(&b))).or_default() += 1;
+        }
+    }
+
+    edges.values().filter(|count| **count != 2).count()
+}
@@ -0,0 +1,269 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    io::Cursor,
+    mem,
+};
+
+use binrw::{BinRead, BinWrite, Endian};
+use derivative::Derivative;
+
+const SHADOW_VOLUME: f32 = 1E4;
+const EDIT: f32 = 2E4;
+
+/// Object Builder names of the LODs with a fixed resolution.
+const NAMED_RESOLUTIONS: [(f32, &str); 28] = [
+    (1E3, "View Gunner"),
+    (1.1E3, "View Pilot"),
+    (1.2E3, "View Cargo"),
+    (1E13, "Geometry"),
+    (2E13, "Geometry Buoyancy"),
+    (3E13, "Geometry PhysX Old"),
+    (4E13, "Geometry PhysX"),
+    (1E15, "Memory"),
+    (2E15, "LandContact"),
+    (3E15, "Roadway"),
+    (4E15, "Paths"),
+    (5E15, "Hit-points"),
+    (6E15, "View Geometry"),
+    (7E15, "Fire Geometry"),
+    (8E15, "View Cargo Geometry"),
+    (9E15, "View Cargo Fire Geometry"),
+    (1E16, "View Commander"),
+    (1.1E16, "View Commander Geometry"),
+    (1.2E16, "View Commander Fire Geometry"),
+    (1.3E16, "View Pilot Geometry"),
+    (1.4E16, "View Pilot Fire Geometry"),
+    (1.5E16, "View Gunner Geometry"),
+    (1.6E16, "View Gunner Fire Geometry"),
+    (1.7E16, "Sub Parts"),
+    (1.8E16, "ShadowVolume - View Cargo"),
+    (1.9E16, "ShadowVolume - View Pilot"),
+    (2E16, "ShadowVolume - View Gunner"),
+    (2.1E16, "Wreck"),
+];
+
+/// LOD resolution, ordered and compared by its raw value.
+#[derive(Debug, Default, Clone, Copy, BinRead, BinWrite)]
+pub struct Resolution {
+    pub value: f32,
+
+    #[br(args { value })]
+    #[bw(ignore)]
+    pub res: ResolutionEnum,
+}
+
+impl Resolution {
+    #[must_use]
+    pub fn from_value(value: f32) -> Self {
+        let res = ResolutionEnum::read_options(
+            &mut Cursor::new([]),
+            Endian::Little,
+            ResolutionEnumBinReadArgs { value },
+        )
+        .unwrap_or_default();
+
+        Self { value, res }
+    }
+
+    /// Parses Object Builder names like `1.000`, `View Cargo` or `ShadowVolume 1000`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if let Some((value, _)) = NAMED_RESOLUTIONS
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        {
+            return Some(Self::from_value(*value));
+        }
+
+        let offset = |prefix: &str| {
+            name.get(..prefix.len())
+                .filter(|p| p.eq_ignore_ascii_case(prefix))
+                .and_then(|_| name[prefix.len()..].trim().parse::<f32>().ok())
+        };
+        let value = if let Some(offset) = offset("ShadowVolume") {
+            SHADOW_VOLUME + offset
+        } else if let Some(offset) = offset("Edit") {
+            EDIT + offset
+        } else {
+            name.parse().ok()?
+        };
+        Some(Self::from_value(value))
+    }
+
+    /// Graphical LODs and the view LODs rendered from inside a vehicle.
+    #[must_use]
+    pub fn is_visual(&self) -> bool {
+        self.value < SHADOW_VOLUME || self.res == ResolutionEnum::ViewCommander
+    }
+
+    #[must_use]
+    pub fn is_shadow(&self) -> bool {
+        (SHADOW_VOLUME..EDIT).contains(&self.value)
+            || matches!(
+                self.res,
+                ResolutionEnum::ShadowVolumeViewCargo
+                    | ResolutionEnum::ShadowVolumeViewPilot
+                    | ResolutionEnum::ShadowVolumeViewGunner
+            )
+    }
+
+    /// Collision LODs, including the physics and view specific geometries.
+    #[must_use]
+    pub const fn is_geometry(&self) -> bool {
+        matches!(
+            self.res,
+            ResolutionEnum::Geometry
+                | ResolutionEnum::GeometryBuoyancy
+                | ResolutionEnum::GeometryPhysxOld
+                | ResolutionEnum::GeometryPhysx
+                | ResolutionEnum::ViewGeometry
+                | ResolutionEnum::FireGeometry
+                | ResolutionEnum::ViewCargoGeometry
+                | ResolutionEnum::ViewCargoFireGeometry
+                | ResolutionEnum::ViewCommanderGeometry
+                | ResolutionEnum::ViewCommanderFireGeometry
+                | ResolutionEnum::ViewPilotGeometry
+                | ResolutionEnum::ViewPilotFireGeometry
+                | ResolutionEnum::ViewGunnerGeometry
+                | ResolutionEnum::ViewGunnerFireGeometry
+        )
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((_, name)) = NAMED_RESOLUTIONS.iter().find(|(v, _)| *v == self.value) {
+            return f.write_str(name);
+        }
+        if (SHADOW_VOLUME..EDIT).contains(&self.value) {
+            write!(f, "ShadowVolume {}", self.value - SHADOW_VOLUME)
+        } else if (EDIT..3E4).contains(&self.value) {
+            write!(f, "Edit {}", self.value - EDIT)
+        } else {
+            write!(f, "{:.3}", self.value)
+        }
+    }
+}
+
+impl PartialEq for Resolution {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Resolution {}
+
+impl PartialOrd for Resolution {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Resolution {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.total_cmp(&other.value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, BinRead, Derivative)]
+#[derivative(Default)]
+#[br(import { value: f32 })]
+pub enum ResolutionEnum {
+    #[br(pre_assert(value < 1E3f32))]
+    GraphicalLod,
+    #[br(pre_assert(value == 1E3f32))]
+    ViewGunner,
+    #[br(pre_assert(value == 1.1E3f32))]
+    ViewPilot,
+    #[br(pre_assert(value == 1.2E3f32))]
+    ViewCargo,
+    #[br(pre_assert(value == 1.202E3f32))]
+    ViewUnknown,
+    #[br(pre_assert(value == 1E4f32))]
+    ShadowVolume,
+    #[br(pre_assert(value == 1.001E4f32))]
+    ShadowVolume2,
+    #[br(pre_assert(value == 1.1E4f32))]
+    StencilShadow,
+    #[br(pre_assert(value == 1.101E4f32))]
+    StencilShadow2,
+    #[br(pre_assert(value == 1.102E4f32))]
+    StencilShadowUnknown,
+    #[br(pre_assert(value == 1E13f32))]
+    Geometry,
+    #[br(pre_assert(value == 2E13f32))]
+    GeometryBuoyancy,
+    #[br(pre_assert(value == 3E13f32))]
+    GeometryPhysxOld,
+    #[br(pre_assert(value == 4E13f32))]
+    GeometryPhysx,
+    #[br(pre_assert(value == 1E15f32))]
+    Memory,
+    #[br(pre_assert(value == 2E15f32))]
+    LandContact,
+    #[br(pre_assert(value == 3E15f32))]
+    Roadway,
+    #[br(pre_assert(value == 4E15f32))]
+    Paths,
+    #[br(pre_assert(value == 5E15f32))]
+    HitPoints,
+    #[br(pre_assert(value == 6E15f32))]
+    ViewGeometry,
+    #[br(pre_assert(value == 7E15f32))]
+    FireGeometry,
+    #[br(pre_assert(value == 8E15f32))]
+    ViewCargoGeometry,
+    #[br(pre_assert(value == 9E15f32))]
+    ViewCargoFireGeometry,
+    #[br(pre_assert(value == 1E16f32))]
+    ViewCommander,
+    #[br(pre_assert(value == 1.1E16f32))]
+    ViewCommanderGeometry,
+    #[br(pre_assert(value == 1.2E16f32))]
+    ViewCommanderFireGeometry,
+    #[br(pre_assert(value == 1.3E16f32))]
+    ViewPilotGeometry,
+    #[br(pre_assert(value == 1.4E16f32))]
+    ViewPilotFireGeometry,
+    #[br(pre_assert(value == 1.5E16f32))]
+    ViewGunnerGeometry,
+    #[br(pre_assert(value == 1.6E16f32))]
+    ViewGunnerFireGeometry,
+    #[br(pre_assert(value == 1.7E16f32))]
+    SubParts,
+    #[br(pre_assert(value == 1.8E16f32))]
+    ShadowVolumeViewCargo,
+    #[br(pre_assert(value == 1.9E16f32))]
+    ShadowVolumeViewPilot,
+    #[br(pre_assert(value == 2E16f32))]
+    ShadowVolumeViewGunner,
+    #[br(pre_assert(value == 2.1E16f32))]
+    Wreck,
+    #[br(pre_assert(true))]
+    #[derivative(Default)]
+    Unknown(#[br(calc = value)] f32),
+}
+
+// The raw value of unknown resolutions is compared bitwise to keep them usable as map keys
+impl PartialEq for ResolutionEnum {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Unknown(a), Self::Unknown(b)) => a.to_bits() == b.to_bits(),
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for ResolutionEnum {}
+
+impl Hash for ResolutionEnum {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        if let Self::Unknown(value) = self {
+            value.to_bits().hash(state);
+        }
+    }
+}
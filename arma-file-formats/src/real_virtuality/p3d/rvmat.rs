@@ -0,0 +1,303 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::Path,
+};
+
+use crate::{
+    errors::AffError,
+    real_virtuality::{
+        paa::TextureSuffix,
+        rap::{Cfg, CfgClass, CfgEntry, CfgValue},
+        types::{D3DColorValue, TransformMatrix, XYZTriplet},
+    },
+};
+
+use super::{LodMaterial, PixelShaderID, TextureFilterType, UVSource, VertexShaderID};
+
+/// Material as described by a `.rvmat` config.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rvmat {
+    pub ambient: D3DColorValue,
+    pub diffuse: D3DColorValue,
+    pub forced_diffuse: D3DColorValue,
+    pub emissive: D3DColorValue,
+    pub specular: D3DColorValue,
+    pub specular_power: f32,
+
+    pub pixel_shader: PixelShaderID,
+    pub vertex_shader: VertexShaderID,
+
+    pub surface_info: Option<String>,
+    pub render_flags: Vec<String>,
+
+    pub stages: Vec<RvmatStage>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RvmatStage {
+    /// Number of the `StageN` class, stage 0 is the texture of the face itself.
+    pub index: u32,
+    pub texture: String,
+    pub uv_source: UVSource,
+    pub uv_transform: TransformMatrix,
+    pub filter: Option<TextureFilterType>,
+}
+
+impl Default for Rvmat {
+    fn default() -> Self {
+        let white = D3DColorValue {
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+            a: 1.0,
+        };
+        Self {
+            ambient: white,
+            diffuse: white,
+            forced_diffuse: D3DColorValue::default(),
+            emissive: D3DColorValue::default(),
+            specular: D3DColorValue::default(),
+            specular_power: 0.0,
+            pixel_shader: PixelShaderID::PSNormal,
+            vertex_shader: VertexShaderID::default(),
+            surface_info: None,
+            render_flags: Vec::new(),
+            stages: Vec::new(),
+        }
+    }
+}
+
+impl Default for RvmatStage {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            texture: String::new(),
+            uv_source: UVSource::UVTex,
+            uv_transform: TransformMatrix::IDENTITY,
+            filter: None,
+        }
+    }
+}
+
+impl Rvmat {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, AffError> {
+        let file = File::open(path)?;
+        let mut buf_reader = BufReader::new(file);
+        Self::from_stream(&mut buf_reader)
+    }
+
+    /// Reads text, binarized and LZSS compressed rvmats.
+    pub fn from_stream<R>(reader: &mut R) -> Result<Self, AffError>
+    where
+        R: Read + Seek,
+    {
+        Ok(Self::from_cfg(&Cfg::read(reader)?))
+    }
+
+    /// Missing entries keep their engine defaults, names are matched case insensitive.
+    #[must_use]
+    pub fn from_cfg(cfg: &Cfg) -> Self {
+        let entries = &cfg.entries;
+        let defaults = Self::default();
+        let color = |names: &[&str], default: D3DColorValue| {
+            names
+                .iter()
+                .find_map(|n| property(entries, n))
+                .and_then(to_color)
+                .unwrap_or(default)
+        };
+
+        let mut stages: Vec<RvmatStage> = entries
+            .iter()
+            .filter_map(|e| match e {
+                CfgEntry::Class(class) => class_index(&class.name, "stage")
+                    .map(|index| RvmatStage::from_class(index, class)),
+                _ => None,
+            })
+            .collect();
+        stages.sort_by_key(|s| s.index);
+
+        Self {
+            ambient: color(&["ambient"], defaults.ambient),
+            diffuse: color(&["diffuse"], defaults.diffuse),
+            forced_diffuse: color(&["forcedDiffuse"], defaults.forced_diffuse),
+            // `emmisive` is the spelling used by the engine
+            emissive: color(&["emmisive", "emissive"], defaults.emissive),
+            specular: color(&["specular"], defaults.specular),
+            specular_power: property(entries, "specularPower")
+                .and_then(to_float)
+                .unwrap_or_default(),
+            pixel_shader: property(entries, "PixelShaderID")
+                .and_then(CfgValue::as_string)
+                .and_then(|n| PixelShaderID::from_name(&n))
+                .unwrap_or(defaults.pixel_shader),
+            vertex_shader: property(entries, "VertexShaderID")
+                .and_then(CfgValue::as_string)
+                .and_then(|n| VertexShaderID::from_name(&n))
+                .unwrap_or(defaults.vertex_shader),
+            surface_info: property(entries, "surfaceInfo").and_then(CfgValue::as_string),
+            render_flags: property(entries, "renderFlags")
+                .and_then(CfgValue::as_array)
+                .map(|flags| flags.iter().filter_map(CfgValue::as_string).collect())
+                .unwrap_or_default(),
+            stages,
+        }
+    }
+
+    #[must_use]
+    pub fn stage(&self, index: u32) -> Option<&RvmatStage> {
+        self.stages.iter().find(|s| s.index == index)
+    }
+
+    /// Texture files of all stages, procedural textures like `#(argb,8,8,3)color(1,1,1,1)` are skipped.
+    pub fn textures(&self) -> impl Iterator<Item = &str> {
+        self.stages
+            .iter()
+            .map(|s| s.texture.as_str())
+            .filter(|t| !t.is_empty() && !t.starts_with('#'))
+    }
+
+    /// First stage texture of a kind, e.g. the normal map for [`TextureSuffix::Nohq`].
+    #[must_use]
+    pub fn texture_of(&self, suffix: TextureSuffix) -> Option<&str> {
+        self.textures()
+            .find(|t| TextureSuffix::from_path(t.replace('\\', "/")) == suffix)
+    }
+}
+
+impl RvmatStage {
+    fn from_class(index: u32, class: &CfgClass) -> Self {
+        let entries = &class.entries;
+        let uv_transform = entries.iter().find_map(|e| match e {
+            CfgEntry::Class(c) if c.name.eq_ignore_ascii_case("uvTransform") => {
+                let axis = |name: &str| {
+                    property(&c.entries, name)
+                        .and_then(to_xyz)
+                        .unwrap_or_default()
+                };
+                Some(TransformMatrix(
+                    axis("aside"),
+                    axis("up"),
+                    axis("dir"),
+                    axis("pos"),
+                ))
+            }
+            _ => None,
+        });
+
+        Self {
+            index,
+            texture: property(entries, "texture")
+                .and_then(CfgValue::as_string)
+                .unwrap_or_default(),
+            uv_source: property(entries, "uvSource")
+                .and_then(CfgValue::as_string)
+                .and_then(|n| UVSource::from_name(&n))
+                .unwrap_or(UVSource::UVTex),
+            uv_transform: uv_transform.unwrap_or(TransformMatrix::IDENTITY),
+            filter: property(entries, "filter")
+                .and_then(CfgValue::as_string)
+                .and_then(|n| TextureFilterType::from_name(&n)),
+        }
+    }
+}
+
+impl From<&LodMaterial> for Rvmat {
+    fn from(material: &LodMaterial) -> Self {
+        let stages = material
+            .stage_textures
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| {
+                let transform = stage
+                    .stage_id
+                    .and_then(|id| material.stage_transforms.get(id as usize))
+                    .or_else(|| material.stage_transforms.get(i));
+
+                RvmatStage {
+                    index: i as u32,
+                    texture: stage.texture.to_string(),
+                    uv_source: transform
+                        .and_then(|t| UVSource::from_value(t.uv_source))
+                        .unwrap_or(UVSource::UVTex),
+                    uv_transform: transform.map_or(TransformMatrix::IDENTITY, |t| t.transformation),
+                    filter: None,
+                }
+            })
+            .collect();
+
+        Self {
+            ambient: material.ambient,
+            diffuse: material.diffuse,
+            forced_diffuse: material.forced_diffuse,
+            emissive: material.emissive,
+            specular: material.specular,
+            specular_power: material.specular_power,
+            pixel_shader: PixelShaderID::from_value(material.pixel_shader),
+            vertex_shader: VertexShaderID::from_value(material.vertex_shader),
+            surface_info: material.surface_file.as_ref().map(ToString::to_string),
+            render_flags: Vec::new(),
+            stages,
+        }
+    }
+}
+
+fn property<'a>(entries: &'a [CfgEntry], name: &str) -> Option<&'a CfgValue> {
+    entries.iter().find_map(|e| match e {
+        CfgEntry::Property(p) if p.name.eq_ignore_ascii_case(name) => Some(&p.value),
+        _ => None,
+    })
+}
+
+/// Index of classes like `Stage1`.
+fn class_index(name: &str, prefix: &str) -> Option<u32> {
+    name.get(..prefix.len())
+        .filter(|p| p.eq_ignore_ascii_case(prefix))
+        .and_then(|_| name[prefix.len()..].parse().ok())
+}
+
+fn to_float(value: &CfgValue) -> Option<f32> {
+    match value {
+        CfgValue::Float(v) => Some(*v),
+        CfgValue::Long(v) => Some(*v as f32),
+        CfgValue::String(v) => v.trim().parse().ok(),
+        CfgValue::Array(_) => None,
+    }
+}
+
+fn to_floats(value: &CfgValue) -> Option<Vec<f32>> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(to_float).collect())
+}
+
+fn to_color(value: &CfgValue) -> Option<D3DColorValue> {
+    match to_floats(value)?.as_slice() {
+        [r, g, b, a, ..] => Some(D3DColorValue {
+            r: *r,
+            g: *g,
+            b: *b,
+            a: *a,
+        }),
+        [r, g, b] => Some(D3DColorValue {
+            r: *r,
+            g: *g,
+            b: *b,
+            a: 1.0,
+        }),
+        _ => None,
+    }
+}
+
+fn to_xyz(value: &CfgValue) -> Option<XYZTriplet> {
+    match to_floats(value)?.as_slice() {
+        [x, y, z, ..] => Some(XYZTriplet::new(*x, *y, *z)),
+        _ => None,
+    }
+}
@@ -1,25 +1,148 @@
-use binrw::{BinRead, NullString};
+use binrw::{BinRead, BinWrite, NullString};
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 #[br(import(version: u32))]
+#[bw(import(version: u32))]
 pub struct Skeleton {
     pub name: NullString,
 
     #[br(if(!(*name).is_empty() && version >= 23))]
     #[br(map = |x: u8| Some(x != 0))]
+    #[bw(if(!(*name).is_empty() && version >= 23))]
+    #[bw(map = |x: &Option<bool>| u8::from(x.unwrap_or_default()))]
     pub is_discrete: Option<bool>,
 
     #[br(if(!(*name).is_empty(), 0))]
+    #[bw(if(!(*name).is_empty()))]
+    #[bw(map = |_: &u32| skeleton_bones.len() as u32)]
     pub bone_names_count: u32,
     #[br(count = bone_names_count)]
+    #[bw(if(!(*name).is_empty()))]
     pub skeleton_bones: Vec<Bone>,
 
     #[br(if(!(*name).is_empty() && version > 40))]
+    #[bw(if(!(*name).is_empty() && version > 40))]
+    #[bw(map = |x: &Option<NullString>| x.clone().unwrap_or_default())]
     pub pivots_name_obsolete: Option<NullString>,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 pub struct Bone {
     pub bone_name: NullString,
     pub bone_parent: NullString,
 }
+
+impl Skeleton {
+    /// Index of a bone, bone names are case insensitive.
+    #[must_use]
+    pub fn bone_index(&self, name: &str) -> Option<usize> {
+        self.skeleton_bones
+            .iter()
+            .position(|b| b.bone_name.to_string().eq_ignore_ascii_case(name))
+    }
+
+    #[must_use]
+    pub fn tree(&self) -> SkeletonTree {
+        SkeletonTree::from_bones(&self.skeleton_bones)
+    }
+}
+
+/// Skeleton with resolved parent and child links, indices match [`Skeleton::skeleton_bones`].
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SkeletonTree {
+    pub bones: Vec<SkeletonBone>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SkeletonBone {
+    pub index: usize,
+    pub name: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+impl SkeletonTree {
+    /// Bones with an empty, unknown or cyclic parent become roots.
+    #[must_use]
+    pub fn from_bones(bones: &[Bone]) -> Self {
+        let names: Vec<String> = bones.iter().map(|b| b.bone_name.to_string()).collect();
+        let mut parents: Vec<Option<usize>> = bones
+            .iter()
+            .map(|b| {
+                let parent = b.bone_parent.to_string();
+                names.iter().position(|n| n.eq_ignore_ascii_case(&parent))
+            })
+            .collect();
+
+        // Only bones on a cycle lose their parent, chains merely leading into a cycle stay
+        // intact as the cycle is broken when its own bones are visited
+        for i in 0..parents.len() {
+            let mut current = parents[i];
+            let mut steps = 0;
+            while let Some(p) = current {
+                if p == i {
+                    parents[i] = None;
+                    break;
+                }
+                if steps > parents.len() {
+                    break;
+                }
+                current = parents[p];
+                steps += 1;
+            }
+        }
+
+        let bones = names
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| SkeletonBone {
+                index,
+                name,
+                parent: parents[index],
+                children: (0..parents.len())
+                    .filter(|c| parents[*c] == Some(index))
+                    .collect(),
+            })
+            .collect();
+
+        Self { bones }
+    }
+
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&SkeletonBone> {
+        self.bones.get(index)
+    }
+
+    /// Bone by case insensitive name.
+    #[must_use]
+    pub fn bone(&self, name: &str) -> Option<&SkeletonBone> {
+        self.bones
+            .iter()
+            .find(|b| b.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = &SkeletonBone> {
+        self.bones.iter().filter(|b| b.parent.is_none())
+    }
+
+    /// Parent chain of a bone, starting with its direct parent.
+    pub fn ancestors(&self, index: usize) -> impl Iterator<Item = &SkeletonBone> {
+        std::iter::successors(self.get(index).and_then(|b| b.parent), |i| {
+            self.get(*i).and_then(|b| b.parent)
+        })
+        .filter_map(|i| self.get(i))
+    }
+
+    /// Bone indices ordered so that parents come before their children.
+    #[must_use]
+    pub fn depth_first(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.bones.len());
+        let mut stack: Vec<usize> = self.roots().map(|b| b.index).collect();
+        stack.reverse();
+        while let Some(i) = stack.pop() {
+            order.push(i);
+            stack.extend(self.bones[i].children.iter().rev());
+        }
+        order
+    }
+}
@@ -0,0 +1,45 @@
+use image::DynamicImage;
+
+use crate::{
+    core::dynamic_image::to_dynamic_image,
+    errors::{AffError, PaaError},
+};
+
+use super::Paa;
+
+impl Paa {
+    /// Builds a paa from any image, the pixels are converted to RGBA.
+    pub fn from_dynamic_image(img: &DynamicImage) -> Result<Self, AffError> {
+        let (Ok(width), Ok(height)) = (u16::try_from(img.width()), u16::try_from(img.height()))
+        else {
+            return Err(PaaError::InvalidDimensions(img.width(), img.height()).into());
+        };
+
+        Ok(Self::from_image(width, height, img.to_rgba8().into_raw()))
+    }
+
+    /// Converts a decoded mipmap, `0` being the largest one.
+    pub fn mipmap_to_image(&self, index: usize) -> Result<DynamicImage, AffError> {
+        let mipmap = self
+            .mipmaps
+            .get(index)
+            .filter(|m| !m.data.is_empty())
+            .ok_or(AffError::MipmapNotFound(index))?;
+
+        to_dynamic_image(
+            &self.pixel_type,
+            mipmap.width.into(),
+            mipmap.height.into(),
+            mipmap.data.clone(),
+        )
+        .ok_or_else(|| AffError::UnsupportedPixelType(self.pixel_type.clone()))
+    }
+}
+
+impl TryFrom<&Paa> for DynamicImage {
+    type Error = AffError;
+
+    fn try_from(paa: &Paa) -> Result<Self, Self::Error> {
+        paa.mipmap_to_image(0)
+    }
+}
@@ -0,0 +1,369 @@
+use std::f32::consts::PI;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSliceMut};
+
+use super::Mipmap;
+
+const CHANNELS: usize = 4;
+const KAISER_ALPHA: f32 = 4.0;
+
+/// Smallest dimension a generated mipmap level may have.
+pub const MIN_MIPMAP_DIM: usize = 4;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum MipmapFilter {
+    /// 2x2 average
+    #[default]
+    Box,
+    /// Tent filter, slightly softer than box
+    Triangle,
+    /// Kaiser windowed sinc
+    Kaiser,
+    /// Lanczos3 windowed sinc
+    Lanczos,
+}
+
+impl MipmapFilter {
+    /// Kernel radius in destination pixels.
+    const fn support(self) -> f32 {
+        match self {
+            Self::Box => 0.5,
+            Self::Triangle => 1.0,
+            Self::Kaiser | Self::Lanczos => 3.0,
+        }
+    }
+
+    fn weight(self, t: f32) -> f32 {
+        let t = t.abs();
+        match self {
+            Self::Box => {
+                if t < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Triangle => (1.0 - t).max(0.0),
+            Self::Kaiser => {
+                let support = self.support();
+                if t >= support {
+                    return 0.0;
+                }
+                let ratio = t / support;
+                sinc(t) * bessel_i0(KAISER_ALPHA * ratio.mul_add(-ratio, 1.0).sqrt())
+                    / bessel_i0(KAISER_ALPHA)
+            }
+            Self::Lanczos => {
+                let support = self.support();
+                if t >= support {
+                    0.0
+                } else {
+                    sinc(t) * sinc(t / support)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct MipmapOptions {
+    pub filter: MipmapFilter,
+
+    /// Treat RGB as sRGB and average in linear light, alpha is always linear.
+    pub linear_light: bool,
+
+    /// Alpha test reference, scales the alpha of every level to keep the coverage of the top level.
+    pub alpha_coverage: Option<u8>,
+}
+
+impl MipmapOptions {
+    #[must_use]
+    pub const fn color() -> Self {
+        Self {
+            filter: MipmapFilter::Box,
+            linear_light: true,
+            alpha_coverage: None,
+        }
+    }
+}
+
+/// Number of levels for a texture, halving until one side would drop below [`MIN_MIPMAP_DIM`].
+#[must_use]
+pub const fn mipmap_level_count(width: usize, height: usize) -> usize {
+    let (mut width, mut height) = (width, height);
+    let mut count = 1;
+    while width / 2 >= MIN_MIPMAP_DIM && height / 2 >= MIN_MIPMAP_DIM {
+        width /= 2;
+        height /= 2;
+        count += 1;
+    }
+    count
+}
+
+/// Generates all levels below `top`, which must contain tightly packed RGBA data.
+pub fn generate_mipmaps(top: &Mipmap, options: MipmapOptions) -> Vec<Mipmap> {
+    let mut width = top.width as usize;
+    let mut height = top.height as usize;
+
+    let to_linear = srgb_to_linear_table();
+    let mut current: Vec<f32> = top
+        .data
+        .chunks_exact(CHANNELS)
+        .flat_map(|p| {
+            if options.linear_light {
+                [
+                    to_linear[p[0] as usize],
+                    to_linear[p[1] as usize],
+                    to_linear[p[2] as usize],
+                    f32::from(p[3]) / 255.0,
+                ]
+            } else {
+                [
+                    f32::from(p[0]) / 255.0,
+                    f32::from(p[1]) / 255.0,
+                    f32::from(p[2]) / 255.0,
+                    f32::from(p[3]) / 255.0,
+                ]
+            }
+        })
+        .collect();
+
+    let target_coverage = options
+        .alpha_coverage
+        .map(|reference| alpha_coverage(&top.data, reference, 1.0));
+
+    let level_count = mipmap_level_count(width, height);
+    let mut mipmaps = Vec::with_capacity(level_count.saturating_sub(1));
+
+    for _ in 1..level_count {
+        let new_width = (width / 2).max(1);
+        let new_height = (height / 2).max(1);
+
+        let horizontal = resample_x(&current, width, height, new_width, options.filter);
+        current = resample_y(&horizontal, new_width, height, new_height, options.filter);
+        width = new_width;
+        height = new_height;
+
+        let mut data: Vec<u8> = current
+            .chunks_exact(CHANNELS)
+            .flat_map(|p| {
+                if options.linear_light {
+                    [
+                        linear_to_srgb(p[0]),
+                        linear_to_srgb(p[1]),
+                        linear_to_srgb(p[2]),
+                        quantize(p[3]),
+                    ]
+                } else {
+                    [
+                        quantize(p[0]),
+                        quantize(p[1]),
+                        quantize(p[2]),
+                        quantize(p[3]),
+                    ]
+                }
+            })
+            .collect();
+
+        if let (Some(reference), Some(target)) = (options.alpha_coverage, target_coverage) {
+            preserve_alpha_coverage(&mut data, reference, target);
+        }
+
+        let mut mipmap = Mipmap::new();
+        mipmap.width = width as u16;
+        mipmap.height = height as u16;
+        mipmap.data_size = data.len() as i64;
+        mipmap.data = data;
+        mipmaps.push(mipmap);
+    }
+
+    mipmaps
+}
+
+/// Source taps and normalized weights for every destination index along one axis.
+fn contributions(src_len: usize, dst_len: usize, filter: MipmapFilter) -> Vec<Vec<(usize, f32)>> {
+    let scale = src_len as f32 / dst_len as f32;
+    let radius = filter.support() * scale;
+
+    (0..dst_len)
+        .map(|dst| {
+            let center = (dst as f32 + 0.5).mul_add(scale, -0.5);
+            let first = (center - radius).floor() as isize;
+            let last = (center + radius).ceil() as isize;
+
+            let mut taps: Vec<(usize, f32)> = (first..=last)
+                .filter_map(|i| {
+                    let w = filter.weight((i as f32 - center) / scale);
+                    (w != 0.0).then(|| (i.clamp(0, src_len as isize - 1) as usize, w))
+                })
+                .collect();
+
+            let sum: f32 = taps.iter().map(|(_, w)| w).sum();
+            if sum.abs() > f32::EPSILON {
+                for (_, w) in &mut taps {
+                    *w /= sum;
+                }
+            } else {
+                taps = vec![(
+                    center.round().clamp(0.0, src_len as f32 - 1.0) as usize,
+                    1.0,
+                )];
+            }
+            taps
+        })
+        .collect()
+}
+
+fn resample_x(
+    src: &[f32],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    filter: MipmapFilter,
+) -> Vec<f32> {
+    if width == new_width {
+        return src.to_vec();
+    }
+
+    let taps = contributions(width, new_width, filter);
+    let mut out = vec![0_f32; new_width * height * CHANNELS];
+
+    #[cfg(feature = "parallel")]
+    let rows = out.par_chunks_mut(new_width * CHANNELS);
+    #[cfg(not(feature = "parallel"))]
+    let rows = out.chunks_mut(new_width * CHANNELS);
+
+    rows.enumerate().for_each(|(y, row)| {
+        let src_row = &src[y * width * CHANNELS..(y + 1) * width * CHANNELS];
+        for (x, pixel) in row.chunks_exact_mut(CHANNELS).enumerate() {
+            for &(i, w) in &taps[x] {
+                for c in 0..CHANNELS {
+                    pixel[c] += src_row[i * CHANNELS + c] * w;
+                }
+            }
+        }
+    });
+
+    out
+}
+
+fn resample_y(
+    src: &[f32],
+    width: usize,
+    height: usize,
+    new_height: usize,
+    filter: MipmapFilter,
+) -> Vec<f32> {
+    if height == new_height {
+        return src.to_vec();
+    }
+
+    let taps = contributions(height, new_height, filter);
+    let row_len = width * CHANNELS;
+    let mut out = vec![0_f32; row_len * new_height];
+
+    #[cfg(feature = "parallel")]
+    let rows = out.par_chunks_mut(row_len);
+    #[cfg(not(feature = "parallel"))]
+    let rows = out.chunks_mut(row_len);
+
+    rows.enumerate().for_each(|(y, row)| {
+        for &(i, w) in &taps[y] {
+            let src_row = &src[i * row_len..(i + 1) * row_len];
+            for (d, s) in row.iter_mut().zip(src_row) {
+                *d += s * w;
+            }
+        }
+    });
+
+    out
+}
+
+fn alpha_coverage(data: &[u8], reference: u8, scale: f32) -> f32 {
+    let pixel_count = data.len() / CHANNELS;
+    if pixel_count == 0 {
+        return 0.0;
+    }
+
+    let covered = data
+        .chunks_exact(CHANNELS)
+        .filter(|p| scale_alpha(p[3], scale) > reference)
+        .count();
+
+    covered as f32 / pixel_count as f32
+}
+
+/// Scales the alpha up until the coverage reaches the target, the scale closest to `1.0` is used.
+fn preserve_alpha_coverage(data: &mut [u8], reference: u8, target: f32) {
+    if alpha_coverage(data, reference, 1.0) >= target {
+        return;
+    }
+
+    let (mut low, mut high) = (1.0_f32, 8.0_f32);
+    for _ in 0..16 {
+        let mid = low.midpoint(high);
+        if alpha_coverage(data, reference, mid) < target {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    for pixel in data.chunks_exact_mut(CHANNELS) {
+        pixel[3] = scale_alpha(pixel[3], high);
+    }
+}
+
+fn scale_alpha(alpha: u8, scale: f32) -> u8 {
+    (f32::from(alpha) * scale).round().min(255.0) as u8
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_sq = x * x / 4.0;
+    for k in 1..32 {
+        term *= half_sq / (k * k) as f32;
+        sum += term;
+        if term < sum * 1e-8 {
+            break;
+        }
+    }
+    sum
+}
+
+fn quantize(val: f32) -> u8 {
+    (val.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn srgb_to_linear_table() -> [f32; 256] {
+    let mut table = [0_f32; 256];
+    for (i, val) in table.iter_mut().enumerate() {
+        let c = i as f32 / 255.0;
+        *val = if c <= 0.040_45 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        };
+    }
+    table
+}
+
+fn linear_to_srgb(val: f32) -> u8 {
+    let val = val.clamp(0.0, 1.0);
+    let c = if val <= 0.003_130_8 {
+        val * 12.92
+    } else {
+        1.055f32.mul_add(val.powf(1.0 / 2.4), -0.055)
+    };
+    quantize(c)
+}
@@ -1,6 +1,18 @@
+#[cfg(feature = "image")]
+mod dynamic_image;
+mod filter;
 mod mipmap;
 #[allow(clippy::module_inception)]
 mod paa;
+mod paa_reader;
+mod profile;
 mod tagg;
 
-pub use self::{mipmap::Mipmap, paa::Paa, tagg::Tagg};
+pub use self::{
+    filter::{MipmapFilter, MipmapOptions, MIN_MIPMAP_DIM},
+    mipmap::Mipmap,
+    paa::Paa,
+    paa_reader::PaaReader,
+    profile::{ChannelSwizzle, ConversionProfile, SwizzleSource, TextureSuffix},
+    tagg::Tagg,
+};
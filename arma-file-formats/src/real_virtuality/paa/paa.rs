@@ -1,21 +1,21 @@
 use std::{
-    cmp,
     collections::HashMap,
     io::{Read, Seek, SeekFrom, Write},
 };
 
 use lzokay_native::Dict;
 
-#[cfg(feature = "parallel")]
-use rayon::prelude::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
-
 use crate::{
     core::{read::ReadExtTrait, types::PixelType, write::WriteExtTrait},
     errors::PaaError,
     real_virtuality::types::PaaType,
 };
 
-use super::{Mipmap, Tagg};
+use super::{
+    filter::{generate_mipmaps, mipmap_level_count, MipmapOptions},
+    profile::{ChannelSwizzle, SwizzleSource, SWIZ_TAGG_SIGNATURE},
+    Mipmap, Tagg,
+};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Paa {
@@ -32,6 +32,12 @@ impl Paa {
         (width.max(height) as f64).log2().floor() as usize
     }
 
+    /// Number of levels written by [`Paa::write`], down to 4 pixels on the smaller side.
+    #[must_use]
+    pub const fn mipmap_level_count(width: usize, height: usize) -> usize {
+        mipmap_level_count(width, height)
+    }
+
     #[must_use]
     pub fn dim_at_level(dim: usize, i: u32) -> usize {
         (dim / 2_usize.pow(i)).max(1)
@@ -62,6 +68,25 @@ impl Paa {
         paa
     }
 
+    #[must_use]
+    pub fn get_tagg(&self, signature: &str) -> Option<&Tagg> {
+        self.taggs.get(signature)
+    }
+
+    pub fn set_tagg(&mut self, tagg: Tagg) {
+        self.taggs.insert(tagg.signature.clone(), tagg);
+    }
+
+    pub fn remove_tagg(&mut self, signature: &str) -> Option<Tagg> {
+        self.taggs.remove(signature)
+    }
+
+    #[must_use]
+    pub fn swizzle(&self) -> Option<ChannelSwizzle> {
+        self.get_tagg(SWIZ_TAGG_SIGNATURE)
+            .and_then(ChannelSwizzle::from_tagg)
+    }
+
     pub fn from_reader<R>(
         reader: &mut R,
         indicies_to_load: Option<&[u32]>,
@@ -70,11 +95,27 @@ impl Paa {
         R: Read + Seek,
     {
         let mut paa = Self::new();
+        paa.read_headers(reader)?;
 
+        let indicies_to_load = indicies_to_load.unwrap_or_default();
+        for (index, mipmap) in paa.mipmaps.iter_mut().enumerate() {
+            if indicies_to_load.is_empty() || indicies_to_load.contains(&(index as u32)) {
+                mipmap.read(reader, paa.magic_number)?;
+            }
+        }
+
+        Ok(paa)
+    }
+
+    /// Reads everything except the mipmap data, which can be loaded with [`Mipmap::read`] later on.
+    pub(crate) fn read_headers<R>(&mut self, reader: &mut R) -> Result<(), PaaError>
+    where
+        R: Read + Seek,
+    {
         reader.rewind()?;
-        paa.magic_number = PaaType::try_from(reader.read_u16()?).unwrap_or(PaaType::UNKNOWN);
+        self.magic_number = PaaType::try_from(reader.read_u16()?).unwrap_or(PaaType::UNKNOWN);
 
-        paa.pixel_type = match paa.magic_number {
+        self.pixel_type = match self.magic_number {
             PaaType::DXT1 | PaaType::DXT5 | PaaType::RGBA4444 => PixelType::Rgba,
             PaaType::GRAYwAlpha => PixelType::GrayAlpha,
             _ => PixelType::Unknown,
@@ -83,37 +124,36 @@ impl Paa {
         while reader.peek_string_lossy(4)?.starts_with("GGAT") {
             let mut tagg = Tagg::new();
             tagg.read(reader)?;
-            paa.taggs.insert(tagg.signature.clone(), tagg);
+            self.taggs.insert(tagg.signature.clone(), tagg);
         }
 
         let palette_length = reader.read_u16()? as usize;
         if palette_length > 0 {
-            paa.palette = reader.read_bytes(palette_length)?;
+            self.palette = reader.read_bytes(palette_length)?;
         }
 
-        let mut index_counter: u32 = 0;
         while reader.peek_u16()? != 0 {
             let mut mipmap = Mipmap::new();
-
             mipmap.read_header(reader)?;
-
-            // TODO: refactor
-            if indicies_to_load.is_none()
-                || indicies_to_load.unwrap().is_empty()
-                || indicies_to_load.unwrap().contains(&index_counter)
-            {
-                mipmap.read(reader, paa.magic_number)?;
-            }
-
-            paa.mipmaps.push(mipmap);
-            index_counter += 1;
+            self.mipmaps.push(mipmap);
         }
 
-        Ok(paa)
+        Ok(())
     }
 
-    #[allow(clippy::too_many_lines)]
     pub fn write<W>(&mut self, writer: &mut W, paa_type: Option<PaaType>) -> Result<(), PaaError>
+    where
+        W: Write + Seek,
+    {
+        self.write_with_options(writer, paa_type, &MipmapOptions::default())
+    }
+
+    pub fn write_with_options<W>(
+        &mut self,
+        writer: &mut W,
+        paa_type: Option<PaaType>,
+        options: &MipmapOptions,
+    ) -> Result<(), PaaError>
     where
         W: Write + Seek,
     {
@@ -123,71 +163,21 @@ impl Paa {
             return Err(PaaError::NoMipmapError);
         }
 
-        for i in 1..self.mipmaps.len() {
-            let mipmap = &self.mipmaps[i];
-            if !mipmap.width.is_power_of_two()
-                || !mipmap.height.is_power_of_two()
-                || mipmap.width as usize * mipmap.height as usize * 4 != mipmap.data.len()
-            {
-                return Err(PaaError::InvalidMipmapError(i));
-            }
-        }
-
-        let initial_width = self.mipmaps[0].width as usize;
-        let initial_height = self.mipmaps[0].height as usize;
-
-        let level_count =
-            (f32::log2(cmp::min(initial_width, initial_height) as f32) - 1.0) as usize;
-        self.mipmaps.resize(level_count, Mipmap::default());
-
-        let mut prev_data = self.mipmaps[0].data.clone();
-
-        let mut mipmaps: Vec<Mipmap> = (1..level_count)
-            .map(|level| -> Mipmap {
-                let desired_width = initial_width >> level;
-                let desired_height = initial_height >> level;
-
-                let previous_width = initial_width >> (level - 1);
-
-                let mut data = vec![0u8; desired_width * desired_height * 4];
-
-                #[cfg(feature = "parallel")]
-                let iter = data.par_iter_mut();
-                #[cfg(not(feature = "parallel"))]
-                let iter = data.iter_mut();
-
-                iter.enumerate().for_each(|(i, d)| {
-                    let x = (i / 4) % desired_width;
-                    let y = i / (desired_width * 4);
-                    let i = i % 4;
-
-                    let y0 = y << 1;
-                    let y1 = cmp::min(y0 + 1, cmp::max(1, initial_height >> (level - 1)) - 1);
-
-                    let x0 = x << 1;
-                    let x1 = cmp::min(x0 + 1, cmp::max(1, initial_width >> (level - 1)) - 1);
-
-                    *d = (0.25
-                        * (prev_data[x0 * 4 + y0 * 4 * previous_width + i] as usize
-                            + prev_data[x1 * 4 + y0 * 4 * previous_width + i] as usize
-                            + prev_data[x0 * 4 + y1 * 4 * previous_width + i] as usize
-                            + prev_data[x1 * 4 + y1 * 4 * previous_width + i] as usize)
-                            as f64) as u8;
-                });
-
-                let mut mipmap = Mipmap::new();
-                mipmap.width = desired_width as u16;
-                mipmap.height = desired_height as u16;
-                mipmap.data_size = data.len() as i64;
-                mipmap.data = data.clone();
-
-                prev_data = data;
+        // Lower levels are always regenerated from the top level
+        self.mipmaps.truncate(1);
 
-                mipmap
-            })
-            .collect();
+        let top = &self.mipmaps[0];
+        if !top.width.is_power_of_two() || !top.height.is_power_of_two() {
+            return Err(PaaError::InvalidDimensions(
+                top.width.into(),
+                top.height.into(),
+            ));
+        }
+        if top.width as usize * top.height as usize * 4 != top.data.len() {
+            return Err(PaaError::InvalidMipmapError(0));
+        }
 
-        self.mipmaps.truncate(1);
+        let mut mipmaps = generate_mipmaps(top, *options);
         self.mipmaps.append(&mut mipmaps);
 
         // Calc average color for "AVGCTAGG"
@@ -229,9 +219,16 @@ impl Paa {
             );
         }
 
-        // "FLAGTAGG"
+        // "SWIZTAGG"
+        let swizzle = self.swizzle();
+        if let Some(swizzle) = swizzle {
+            taggs.insert(SWIZ_TAGG_SIGNATURE.to_string(), swizzle.to_tagg());
+        }
+
+        // "FLAGTAGG", only if the alpha channel actually holds alpha
+        let alpha_is_alpha = swizzle.is_none_or(|s| s.a == SwizzleSource::Alpha);
         taggs.remove("GGATGALF");
-        if avg_a != 0xff {
+        if avg_a != 0xff && alpha_is_alpha {
             taggs.insert(
                 "GGATGALF".to_string(),
                 Tagg {
@@ -0,0 +1,70 @@
+use std::io::{Read, Seek};
+
+use crate::errors::PaaError;
+
+use super::{Mipmap, Paa};
+
+/// Lazy paa reader, only the headers are parsed up front and mipmaps are decoded on request.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PaaReader<R>
+where
+    R: Read + Seek,
+{
+    reader: R,
+    pub paa: Paa,
+}
+
+impl<R> PaaReader<R>
+where
+    R: Read + Seek,
+{
+    pub fn from_stream(reader: R) -> Result<Self, PaaError> {
+        let mut paa_reader = Self {
+            reader,
+            paa: Paa::new(),
+        };
+        paa_reader.paa.read_headers(&mut paa_reader.reader)?;
+        Ok(paa_reader)
+    }
+
+    #[must_use]
+    pub const fn mipmap_count(&self) -> usize {
+        self.paa.mipmaps.len()
+    }
+
+    /// Width and height of every mipmap, largest first.
+    #[must_use]
+    pub fn mipmap_dimensions(&self) -> Vec<(u16, u16)> {
+        self.paa
+            .mipmaps
+            .iter()
+            .map(|m| (m.width, m.height))
+            .collect()
+    }
+
+    pub fn get_mipmap(&mut self, index: usize) -> Result<Option<&Mipmap>, PaaError> {
+        let magic_number = self.paa.magic_number;
+
+        let Some(mipmap) = self.paa.mipmaps.get_mut(index) else {
+            return Ok(None);
+        };
+
+        if mipmap.data.is_empty() {
+            mipmap.read(&mut self.reader, magic_number)?;
+        }
+
+        Ok(Some(mipmap))
+    }
+
+    /// Decodes the smallest mipmap which is at least `min_width` wide, or the largest one if none is.
+    pub fn get_mipmap_for_width(&mut self, min_width: u16) -> Result<Option<&Mipmap>, PaaError> {
+        let index = self
+            .paa
+            .mipmaps
+            .iter()
+            .rposition(|m| m.width >= min_width)
+            .unwrap_or_default();
+
+        self.get_mipmap(index)
+    }
+}
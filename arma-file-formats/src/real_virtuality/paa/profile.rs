@@ -0,0 +1,360 @@
+use std::{
+    io::{Seek, Write},
+    path::Path,
+};
+
+use crate::{errors::PaaError, real_virtuality::types::PaaType};
+
+use super::{MipmapFilter, MipmapOptions, Paa, Tagg};
+
+pub const SWIZ_TAGG_SIGNATURE: &str = "GGATZIWS";
+
+/// Texture kind as encoded in the file name suffix (`foo_co.paa`, `foo_nohq.png`, ...).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TextureSuffix {
+    /// `_co`, color
+    Co,
+    /// `_ca`, color with alpha
+    Ca,
+    /// `_nohq`, normal map
+    Nohq,
+    /// `_smdi`, specular / gloss map
+    Smdi,
+    /// `_as`, ambient shadow
+    As,
+    /// `_mc`, macro map
+    Mc,
+    Unknown,
+}
+
+impl TextureSuffix {
+    #[must_use]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let stem = path
+            .as_ref()
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        stem.rsplit_once('_')
+            .map_or(Self::Unknown, |(_, suffix)| Self::from_suffix(suffix))
+    }
+
+    #[must_use]
+    pub fn from_suffix(suffix: &str) -> Self {
+        match suffix.trim_start_matches('_').to_lowercase().as_str() {
+            "co" => Self::Co,
+            "ca" => Self::Ca,
+            "nohq" => Self::Nohq,
+            "smdi" => Self::Smdi,
+            "as" => Self::As,
+            "mc" => Self::Mc,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Source of a single channel in a [`ChannelSwizzle`], values as stored in the `SWIZTAGG`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum SwizzleSource {
+    Alpha = 0,
+    Red = 1,
+    Green = 2,
+    Blue = 3,
+    InvAlpha = 4,
+    InvRed = 5,
+    InvGreen = 6,
+    InvBlue = 7,
+    One = 8,
+    Zero = 9,
+}
+
+impl SwizzleSource {
+    #[must_use]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::Alpha,
+            1 => Self::Red,
+            2 => Self::Green,
+            3 => Self::Blue,
+            4 => Self::InvAlpha,
+            5 => Self::InvRed,
+            6 => Self::InvGreen,
+            7 => Self::InvBlue,
+            8 => Self::One,
+            9 => Self::Zero,
+            _ => return None,
+        })
+    }
+
+    /// Index into an RGBA pixel and whether the value is inverted, `None` for constants.
+    const fn rgba_index(self) -> Option<(usize, bool)> {
+        match self {
+            Self::Alpha => Some((3, false)),
+            Self::Red => Some((0, false)),
+            Self::Green => Some((1, false)),
+            Self::Blue => Some((2, false)),
+            Self::InvAlpha => Some((3, true)),
+            Self::InvRed => Some((0, true)),
+            Self::InvGreen => Some((1, true)),
+            Self::InvBlue => Some((2, true)),
+            Self::One | Self::Zero => None,
+        }
+    }
+
+    fn sample(self, pixel: &[u8]) -> u8 {
+        match self {
+            Self::One => 0xFF,
+            Self::Zero => 0,
+            _ => self.rgba_index().map_or(
+                0,
+                |(i, inverted)| {
+                    if inverted {
+                        0xFF - pixel[i]
+                    } else {
+                        pixel[i]
+                    }
+                },
+            ),
+        }
+    }
+}
+
+/// Per channel swizzle, stored in ARGB order in the `SWIZTAGG`.
+///
+/// Every destination channel names the source channel it was taken from on encode.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ChannelSwizzle {
+    pub a: SwizzleSource,
+    pub r: SwizzleSource,
+    pub g: SwizzleSource,
+    pub b: SwizzleSource,
+}
+
+impl Default for ChannelSwizzle {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl ChannelSwizzle {
+    pub const IDENTITY: Self = Self {
+        a: SwizzleSource::Alpha,
+        r: SwizzleSource::Red,
+        g: SwizzleSource::Green,
+        b: SwizzleSource::Blue,
+    };
+
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    #[must_use]
+    pub fn from_tagg(tagg: &Tagg) -> Option<Self> {
+        if tagg.signature != SWIZ_TAGG_SIGNATURE || tagg.data.len() < 4 {
+            return None;
+        }
+
+        Some(Self {
+            a: SwizzleSource::from_u8(tagg.data[0])?,
+            r: SwizzleSource::from_u8(tagg.data[1])?,
+            g: SwizzleSource::from_u8(tagg.data[2])?,
+            b: SwizzleSource::from_u8(tagg.data[3])?,
+        })
+    }
+
+    #[must_use]
+    pub fn to_tagg(&self) -> Tagg {
+        Tagg {
+            signature: SWIZ_TAGG_SIGNATURE.to_string(),
+            data: vec![self.a as u8, self.r as u8, self.g as u8, self.b as u8],
+        }
+    }
+
+    /// Applies the swizzle to tightly packed RGBA data.
+    pub fn apply(&self, data: &mut [u8]) {
+        for pixel in data.chunks_exact_mut(4) {
+            let src = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            pixel[0] = self.r.sample(&src);
+            pixel[1] = self.g.sample(&src);
+            pixel[2] = self.b.sample(&src);
+            pixel[3] = self.a.sample(&src);
+        }
+    }
+
+    /// Reverts the swizzle on tightly packed RGBA data.
+    ///
+    /// Channels which were replaced by a constant can't be recovered and are set to `0xFF`.
+    pub fn revert(&self, data: &mut [u8]) {
+        let mapping = [(0, self.r), (1, self.g), (2, self.b), (3, self.a)];
+        for pixel in data.chunks_exact_mut(4) {
+            let src = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            pixel.fill(0xFF);
+            for (dst_index, source) in mapping {
+                if let Some((i, inverted)) = source.rgba_index() {
+                    pixel[i] = if inverted {
+                        0xFF - src[dst_index]
+                    } else {
+                        src[dst_index]
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Conversion settings for a texture kind, modelled after the official `TexConvert.cfg`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ConversionProfile {
+    pub suffix: TextureSuffix,
+
+    /// `None` picks DXT1 or DXT5 depending on the alpha channel.
+    pub paa_type: Option<PaaType>,
+    pub swizzle: ChannelSwizzle,
+    pub normal_map: bool,
+    pub mipmap_options: MipmapOptions,
+}
+
+impl Default for ConversionProfile {
+    fn default() -> Self {
+        Self::from_suffix(TextureSuffix::Unknown)
+    }
+}
+
+impl ConversionProfile {
+    #[must_use]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        Self::from_suffix(TextureSuffix::from_path(path))
+    }
+
+    #[must_use]
+    pub const fn from_suffix(suffix: TextureSuffix) -> Self {
+        let (paa_type, swizzle, normal_map) = match suffix {
+            TextureSuffix::Co | TextureSuffix::Unknown => (None, ChannelSwizzle::IDENTITY, false),
+            TextureSuffix::Ca | TextureSuffix::Mc => {
+                (Some(PaaType::DXT5), ChannelSwizzle::IDENTITY, false)
+            }
+            // X is moved into alpha for better precision, Z is reconstructed on decode
+            TextureSuffix::Nohq => (
+                Some(PaaType::DXT5),
+                ChannelSwizzle {
+                    a: SwizzleSource::Red,
+                    r: SwizzleSource::One,
+                    g: SwizzleSource::Green,
+                    b: SwizzleSource::One,
+                },
+                true,
+            ),
+            TextureSuffix::Smdi => (
+                Some(PaaType::DXT1),
+                ChannelSwizzle {
+                    a: SwizzleSource::One,
+                    r: SwizzleSource::One,
+                    g: SwizzleSource::Green,
+                    b: SwizzleSource::Blue,
+                },
+                false,
+            ),
+            TextureSuffix::As => (
+                Some(PaaType::DXT1),
+                ChannelSwizzle {
+                    a: SwizzleSource::One,
+                    r: SwizzleSource::One,
+                    g: SwizzleSource::Green,
+                    b: SwizzleSource::One,
+                },
+                false,
+            ),
+        };
+
+        // Only color textures are stored in sRGB
+        let mipmap_options = match suffix {
+            TextureSuffix::Co | TextureSuffix::Ca | TextureSuffix::Mc | TextureSuffix::Unknown => {
+                MipmapOptions::color()
+            }
+            TextureSuffix::Nohq | TextureSuffix::Smdi | TextureSuffix::As => MipmapOptions {
+                filter: MipmapFilter::Box,
+                linear_light: false,
+                alpha_coverage: None,
+            },
+        };
+
+        Self {
+            suffix,
+            paa_type,
+            swizzle,
+            normal_map,
+            mipmap_options,
+        }
+    }
+
+    /// Prepares a copy of the top level mipmap, stores the swizzle and writes the paa.
+    pub fn encode<W>(&self, paa: &Paa, writer: &mut W) -> Result<(), PaaError>
+    where
+        W: Write + Seek,
+    {
+        let mut paa = paa.clone();
+        let mipmap = paa.mipmaps.first_mut().ok_or(PaaError::NoMipmapError)?;
+
+        if self.normal_map {
+            normalize_normals(&mut mipmap.data);
+        }
+
+        if self.swizzle.is_identity() {
+            paa.remove_tagg(SWIZ_TAGG_SIGNATURE);
+        } else {
+            self.swizzle.apply(&mut mipmap.data);
+            paa.set_tagg(self.swizzle.to_tagg());
+        }
+
+        paa.write_with_options(writer, self.paa_type, &self.mipmap_options)
+    }
+
+    /// Reverts the stored swizzle on all loaded mipmaps and reconstructs normal map Z.
+    ///
+    /// Textures without a swizzle tag are left as they are, regardless of the profile, as their Z
+    /// channel wasn't dropped.
+    pub fn decode(&self, paa: &mut Paa) {
+        let Some(swizzle) = paa.swizzle().filter(|s| !s.is_identity()) else {
+            return;
+        };
+
+        for mipmap in paa.mipmaps.iter_mut().filter(|m| !m.data.is_empty()) {
+            swizzle.revert(&mut mipmap.data);
+            if self.normal_map {
+                reconstruct_normal_z(&mut mipmap.data);
+            }
+        }
+
+        paa.remove_tagg(SWIZ_TAGG_SIGNATURE);
+    }
+}
+
+fn to_unit(val: u8) -> f32 {
+    f32::from(val) / 127.5 - 1.0
+}
+
+fn from_unit(val: f32) -> u8 {
+    ((val.clamp(-1.0, 1.0) + 1.0) * 127.5).round() as u8
+}
+
+fn normalize_normals(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        let (x, y, z) = (to_unit(pixel[0]), to_unit(pixel[1]), to_unit(pixel[2]));
+        let len = x.mul_add(x, y.mul_add(y, z * z)).sqrt();
+        if len > f32::EPSILON {
+            pixel[0] = from_unit(x / len);
+            pixel[1] = from_unit(y / len);
+            pixel[2] = from_unit(z / len);
+        }
+    }
+}
+
+fn reconstruct_normal_z(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        let (x, y) = (to_unit(pixel[0]), to_unit(pixel[1]));
+        pixel[2] = from_unit((1.0 - x.mul_add(x, y * y)).max(0.0).sqrt());
+    }
+}
@@ -0,0 +1,8 @@
+#[allow(clippy::module_inception)]
+mod tex_headers;
+mod texture_header;
+
+pub use self::{
+    tex_headers::{TexHeaders, TEX_HEADERS_FILENAME},
+    texture_header::{MipmapHeader, TextureHeader, TexturePixelFormat},
+};
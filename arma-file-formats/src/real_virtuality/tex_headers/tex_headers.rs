@@ -0,0 +1,118 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek, Write},
+    path::Path,
+};
+
+use binrw::{binrw, BinRead, BinWrite, Endian};
+
+use crate::{errors::AffError, real_virtuality::paa::Paa};
+
+use super::TextureHeader;
+
+pub const TEX_HEADERS_FILENAME: &str = "texHeaders.bin";
+const TEX_HEADERS_VERSION: u32 = 1;
+
+#[binrw]
+#[derive(Debug, PartialEq, Clone)]
+#[brw(little, magic = b"0DHT")]
+pub struct TexHeaders {
+    pub version: u32,
+
+    #[br(temp)]
+    #[bw(calc = textures.len() as u32)]
+    texture_count: u32,
+
+    #[br(count = texture_count)]
+    pub textures: Vec<TextureHeader>,
+}
+
+impl TexHeaders {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            version: TEX_HEADERS_VERSION,
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, AffError> {
+        let file = File::open(path)?;
+        let mut buf_reader = BufReader::new(file);
+        Self::from_stream(&mut buf_reader)
+    }
+
+    pub fn from_stream<R>(reader: &mut R) -> Result<Self, AffError>
+    where
+        R: Read + Seek,
+    {
+        Ok(Self::read_options(reader, Endian::Little, ())?)
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<(), AffError> {
+        let mut file = File::create(path)?;
+        self.write_stream(&mut file)
+    }
+
+    pub fn write_stream<W>(&self, writer: &mut W) -> Result<(), AffError>
+    where
+        W: Write + Seek,
+    {
+        self.write_options(writer, Endian::Little, ())?;
+        Ok(())
+    }
+
+    pub fn write_data(&self) -> Result<Vec<u8>, AffError> {
+        let mut buf = Vec::new();
+        self.write_stream(&mut Cursor::new(&mut buf))?;
+        Ok(buf)
+    }
+
+    /// Looks up a texture by its path, case insensitive and independent of the path separator.
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<&TextureHeader> {
+        let path = normalize_path(path);
+        self.textures
+            .iter()
+            .find(|t| normalize_path(&t.path.to_string()) == path)
+    }
+
+    /// Adds or replaces the header of the paa at `path`, relative to the pbo prefix.
+    pub fn add_paa(&mut self, path: &str, paa: &Paa, file_size: u32) {
+        self.insert(TextureHeader::from_paa(path, paa, file_size));
+    }
+
+    /// Adds or replaces the header of a paa read from `reader`, only the headers are read.
+    pub fn add_paa_stream<R>(&mut self, path: &str, reader: &mut R) -> Result<(), AffError>
+    where
+        R: Read + Seek,
+    {
+        self.insert(TextureHeader::from_paa_stream(path, reader)?);
+        Ok(())
+    }
+
+    pub fn insert(&mut self, header: TextureHeader) {
+        let path = normalize_path(&header.path.to_string());
+        if let Some(existing) = self
+            .textures
+            .iter_mut()
+            .find(|t| normalize_path(&t.path.to_string()) == path)
+        {
+            *existing = header;
+        } else {
+            self.textures.push(header);
+        }
+    }
+}
+
+impl Default for TexHeaders {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    path.trim_start_matches(['\\', '/'])
+        .replace('/', "\\")
+        .to_lowercase()
+}
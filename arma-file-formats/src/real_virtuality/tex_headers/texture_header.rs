@@ -0,0 +1,243 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use binrw::{binrw, NullString};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::{
+    errors::AffError,
+    real_virtuality::{paa::Paa, types::PaaType},
+};
+
+/// Size of a mipmap header inside a paa, width, height and the 24 bit data size.
+const PAA_MIPMAP_HEADER_SIZE: u64 = 7;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum TexturePixelFormat {
+    #[default]
+    P8 = 0,
+    Ai88 = 1,
+    Rgb565 = 2,
+    Argb1555 = 3,
+    Argb4444 = 4,
+    Argb8888 = 5,
+    Dxt1 = 6,
+    Dxt2 = 7,
+    Dxt3 = 8,
+    Dxt4 = 9,
+    Dxt5 = 10,
+}
+
+impl From<PaaType> for TexturePixelFormat {
+    fn from(paa_type: PaaType) -> Self {
+        match paa_type {
+            PaaType::UNKNOWN => Self::P8,
+            PaaType::DXT1 => Self::Dxt1,
+            PaaType::DXT2 => Self::Dxt2,
+            PaaType::DXT3 => Self::Dxt3,
+            PaaType::DXT4 => Self::Dxt4,
+            PaaType::DXT5 => Self::Dxt5,
+            PaaType::RGBA4444 => Self::Argb4444,
+            PaaType::RGBA5551 => Self::Argb1555,
+            PaaType::RGBA8888 => Self::Argb8888,
+            PaaType::GRAYwAlpha => Self::Ai88,
+        }
+    }
+}
+
+#[binrw]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[brw(little)]
+pub struct MipmapHeader {
+    pub width: u16,
+    pub height: u16,
+
+    #[br(temp, assert(zero == 0))]
+    #[bw(calc = 0)]
+    zero: u8,
+
+    #[br(try_map = |v: u8| TexturePixelFormat::try_from(v))]
+    #[bw(map = |f: &TexturePixelFormat| u8::from(*f))]
+    pub pixel_format: TexturePixelFormat,
+
+    pub unk: u8,
+
+    /// Offset of the mipmap inside the paa, pointing at its width.
+    pub data_offset: u32,
+}
+
+#[binrw]
+#[derive(Debug, PartialEq, Clone)]
+#[brw(little)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct TextureHeader {
+    pub color_palette_count: u32,
+    pub palette_pointer: u32,
+
+    /// Average color as RGBA floats.
+    pub average_color_f: [f32; 4],
+
+    /// Average color as ARGB8888, same layout as the `AVGCTAGG`.
+    pub average_color: [u8; 4],
+
+    /// Max color as ARGB8888, same layout as the `MAXCTAGG`.
+    pub max_color: [u8; 4],
+
+    pub clamp_flags: u32,
+    pub transparent_color: u32,
+
+    #[br(map = |v: u8| v != 0)]
+    #[bw(map = |v: &bool| u8::from(*v))]
+    pub has_max_ctagg: bool,
+
+    /// Interpolated alpha, bit 0 of the `FLAGTAGG`.
+    #[br(map = |v: u8| v != 0)]
+    #[bw(map = |v: &bool| u8::from(*v))]
+    pub is_alpha: bool,
+
+    /// Alpha tested transparency, bit 1 of the `FLAGTAGG`.
+    #[br(map = |v: u8| v != 0)]
+    #[bw(map = |v: &bool| u8::from(*v))]
+    pub is_transparent: bool,
+
+    /// Heuristic when built with [`TextureHeader::from_paa`]: set for interpolated alpha textures
+    /// with an average alpha below 50%. The rule of the official generator is not known, read
+    /// headers keep the stored value.
+    #[br(map = |v: u8| v != 0)]
+    #[bw(map = |v: &bool| u8::from(*v))]
+    pub is_alpha_non_opaque: bool,
+
+    #[br(temp)]
+    #[bw(calc = mipmaps.len() as u32)]
+    mipmap_count: u32,
+
+    #[br(try_map = |v: u32| TexturePixelFormat::try_from(v as u8))]
+    #[bw(map = |f: &TexturePixelFormat| u32::from(u8::from(*f)))]
+    pub pixel_format: TexturePixelFormat,
+
+    #[br(map = |v: u8| v != 0)]
+    #[bw(map = |v: &bool| u8::from(*v))]
+    pub little_endian: bool,
+
+    #[br(map = |v: u8| v != 0)]
+    #[bw(map = |v: &bool| u8::from(*v))]
+    pub is_paa: bool,
+
+    /// Path of the texture relative to the pbo prefix.
+    pub path: NullString,
+
+    pub texture_type: u32,
+
+    #[br(temp)]
+    #[bw(calc = mipmaps.len() as u32)]
+    mipmap_count_2: u32,
+
+    #[br(count = mipmap_count_2)]
+    pub mipmaps: Vec<MipmapHeader>,
+
+    pub file_size: u32,
+}
+
+impl Default for TextureHeader {
+    fn default() -> Self {
+        Self {
+            color_palette_count: 1,
+            palette_pointer: 0,
+            average_color_f: [0.0; 4],
+            average_color: [0; 4],
+            max_color: [0xFF; 4],
+            clamp_flags: 0,
+            transparent_color: 0xFFFF_FFFF,
+            has_max_ctagg: false,
+            is_alpha: false,
+            is_transparent: false,
+            is_alpha_non_opaque: false,
+            pixel_format: TexturePixelFormat::default(),
+            little_endian: true,
+            is_paa: true,
+            path: NullString::default(),
+            texture_type: 0,
+            mipmaps: Vec::new(),
+            file_size: 0,
+        }
+    }
+}
+
+impl TextureHeader {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the header from a paa, only the taggs and mipmap headers are used.
+    ///
+    /// Mipmap offsets are taken from the `OFFSTAGG`, or from the read positions if the paa was read from a file.
+    #[must_use]
+    pub fn from_paa(path: &str, paa: &Paa, file_size: u32) -> Self {
+        let mut header = Self::new();
+        header.path = path.into();
+        header.file_size = file_size;
+        header.pixel_format = paa.magic_number.into();
+
+        if let Some(avg) = paa.get_tagg("GGATCGVA").filter(|t| t.data.len() >= 4) {
+            header.average_color = [avg.data[0], avg.data[1], avg.data[2], avg.data[3]];
+        }
+        let [b, g, r, a] = header.average_color;
+        header.average_color_f = [r, g, b, a].map(|c| f32::from(c) / 255.0);
+
+        if let Some(max) = paa.get_tagg("GGATCXAM").filter(|t| t.data.len() >= 4) {
+            header.has_max_ctagg = true;
+            header.max_color = [max.data[0], max.data[1], max.data[2], max.data[3]];
+        }
+
+        let flags = paa
+            .get_tagg("GGATGALF")
+            .and_then(|t| t.data.first().copied())
+            .unwrap_or_default();
+        header.is_alpha = flags & 0x01 != 0;
+        header.is_transparent = flags & 0x02 != 0;
+        header.is_alpha_non_opaque = header.is_alpha && a < 0x80;
+
+        let offsets: Vec<u32> = paa
+            .get_tagg("GGATSFFO")
+            .map(|t| {
+                t.data
+                    .chunks_exact(4)
+                    .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                    .take_while(|&offset| offset != 0)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        header.mipmaps = paa
+            .mipmaps
+            .iter()
+            .enumerate()
+            .map(|(i, mipmap)| MipmapHeader {
+                width: mipmap.width,
+                height: mipmap.height,
+                pixel_format: header.pixel_format,
+                unk: 3,
+                data_offset: offsets.get(i).copied().unwrap_or_else(|| {
+                    mipmap
+                        .data_pos
+                        .map_or(0, |pos| pos.saturating_sub(PAA_MIPMAP_HEADER_SIZE) as u32)
+                }),
+            })
+            .collect();
+
+        header
+    }
+
+    /// Reads the headers of the paa in `reader` and builds the texture header from it.
+    pub fn from_paa_stream<R>(path: &str, reader: &mut R) -> Result<Self, AffError>
+    where
+        R: Read + Seek,
+    {
+        let mut paa = Paa::new();
+        paa.read_headers(reader)?;
+        let file_size = reader.seek(SeekFrom::End(0))?;
+
+        Ok(Self::from_paa(path, &paa, file_size as u32))
+    }
+}
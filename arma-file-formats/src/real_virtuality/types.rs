@@ -1,4 +1,4 @@
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
@@ -16,7 +16,7 @@ pub enum PaaType {
     GRAYwAlpha = 0x8080,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 pub struct BoundingBox {
     pub a: XY,
     pub b: XY,
@@ -25,7 +25,7 @@ pub struct BoundingBox {
 }
 
 // ToDo: refactor this to be generic
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 pub struct XY {
     pub x: f32,
     pub y: f32,
@@ -37,20 +37,61 @@ pub struct XYPair {
     pub y: u32,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 pub struct XYZTriplet {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+impl XYZTriplet {
+    #[must_use]
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.z
+            .mul_add(self.z, self.x.mul_add(self.x, self.y * self.y))
+            .sqrt()
+    }
+
+    /// Unit vector in the same direction, zero vectors stay zero.
+    #[must_use]
+    pub fn normalized(&self) -> Self {
+        let length = self.length();
+        if length > 0.0 {
+            self.scaled(1.0 / length)
+        } else {
+            *self
+        }
+    }
+
+    #[must_use]
+    pub fn scaled(&self, factor: f32) -> Self {
+        Self::new(self.x * factor, self.y * factor, self.z * factor)
+    }
+
+    #[must_use]
+    pub fn add(&self, other: &Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+
+    #[must_use]
+    pub fn sub(&self, other: &Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 pub struct STPair {
     pub s: XYZTriplet,
     pub t: XYZTriplet,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+/// Affine transform as the three axes followed by the translation.
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 pub struct TransformMatrix(
     pub XYZTriplet,
     pub XYZTriplet,
@@ -58,7 +99,76 @@ pub struct TransformMatrix(
     pub XYZTriplet,
 );
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+impl TransformMatrix {
+    pub const IDENTITY: Self = Self(
+        XYZTriplet::new(1.0, 0.0, 0.0),
+        XYZTriplet::new(0.0, 1.0, 0.0),
+        XYZTriplet::new(0.0, 0.0, 1.0),
+        XYZTriplet::new(0.0, 0.0, 0.0),
+    );
+
+    #[must_use]
+    pub const fn translation(offset: XYZTriplet) -> Self {
+        Self(Self::IDENTITY.0, Self::IDENTITY.1, Self::IDENTITY.2, offset)
+    }
+
+    /// Rotation by `angle` radians around the axis through `axis_pos` along `axis_dir`.
+    #[must_use]
+    pub fn rotation(axis_pos: XYZTriplet, axis_dir: XYZTriplet, angle: f32) -> Self {
+        let XYZTriplet { x, y, z } = axis_dir.normalized();
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+
+        let rotation = Self(
+            XYZTriplet::new(
+                (t * x).mul_add(x, cos),
+                (t * x).mul_add(y, z * sin),
+                (t * x).mul_add(z, -y * sin),
+            ),
+            XYZTriplet::new(
+                (t * x).mul_add(y, -z * sin),
+                (t * y).mul_add(y, cos),
+                (t * y).mul_add(z, x * sin),
+            ),
+            XYZTriplet::new(
+                (t * x).mul_add(z, y * sin),
+                (t * y).mul_add(z, -x * sin),
+                (t * z).mul_add(z, cos),
+            ),
+            XYZTriplet::default(),
+        );
+
+        Self::translation(axis_pos)
+            .multiply(&rotation)
+            .multiply(&Self::translation(axis_pos.scaled(-1.0)))
+    }
+
+    #[must_use]
+    pub fn transform_vector(&self, v: &XYZTriplet) -> XYZTriplet {
+        self.0
+            .scaled(v.x)
+            .add(&self.1.scaled(v.y))
+            .add(&self.2.scaled(v.z))
+    }
+
+    #[must_use]
+    pub fn transform_point(&self, p: &XYZTriplet) -> XYZTriplet {
+        self.transform_vector(p).add(&self.3)
+    }
+
+    /// Combined transform applying `other` first.
+    #[must_use]
+    pub fn multiply(&self, other: &Self) -> Self {
+        Self(
+            self.transform_vector(&other.0),
+            self.transform_vector(&other.1),
+            self.transform_vector(&other.2),
+            self.transform_point(&other.3),
+        )
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 pub struct D3DColorValue {
     pub r: f32,
     pub g: f32,
@@ -66,7 +176,7 @@ pub struct D3DColorValue {
     pub a: f32,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, BinRead, BinWrite)]
 pub struct RGBAColor {
     pub a: u8,
     pub b: u8,
@@ -0,0 +1,500 @@
+use std::io::{BufRead, Write};
+
+#[cfg(feature = "image")]
+use std::io::Seek;
+
+#[cfg(feature = "image")]
+use image::{codecs::png::PngEncoder, DynamicImage, ImageBuffer, ImageFormat, Luma};
+
+use crate::errors::{AffError, WrpError};
+
+use super::{oprw::check_length, OPRW, WVR8};
+
+/// Elevation grid of a terrain.
+///
+/// Elevations are row major starting at the south west corner, like the terrain grid of the
+/// world files. Exports are oriented north up.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Heightmap {
+    pub width: u32,
+    pub height: u32,
+
+    /// Distance between two grid points in meters.
+    pub cell_size: f32,
+
+    /// World position of the south west corner, Terrain Builder projects use an easting of 200000.
+    pub origin_x: f64,
+    pub origin_y: f64,
+
+    pub elevations: Vec<f32>,
+}
+
+impl Heightmap {
+    #[must_use]
+    pub fn new(width: u32, height: u32, cell_size: f32, elevations: Vec<f32>) -> Self {
+        Self {
+            width,
+            height,
+            cell_size,
+            elevations,
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width {
+            return None;
+        }
+        self.elevations
+            .get(y as usize * self.width as usize + x as usize)
+            .copied()
+    }
+
+    /// Elevation at a world position, interpolated between the surrounding grid points.
+    #[must_use]
+    pub fn elevation_at(&self, x: f64, y: f64) -> Option<f32> {
+        interpolate(
+            &self.elevations,
+            (self.width, self.height),
+            self.cell_size,
+            x - self.origin_x,
+            y - self.origin_y,
+        )
+    }
+
+    /// Lowest and highest elevation.
+    #[must_use]
+    pub fn range(&self) -> (f32, f32) {
+        self.elevations
+            .iter()
+            .fold(None, |range: Option<(f32, f32)>, e| {
+                Some(range.map_or((*e, *e), |(min, max)| (min.min(*e), max.max(*e))))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Writes an ESRI ASCII grid.
+    pub fn write_asc(&self, writer: &mut impl Write) -> Result<(), AffError> {
+        writeln!(writer, "ncols {}", self.width)?;
+        writeln!(writer, "nrows {}", self.height)?;
+        writeln!(writer, "xllcorner {}", self.origin_x)?;
+        writeln!(writer, "yllcorner {}", self.origin_y)?;
+        writeln!(writer, "cellsize {}", self.cell_size)?;
+        writeln!(writer, "NODATA_value -9999")?;
+
+        for row in self.rows_north_up() {
+            let line = row
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(writer, "{line}")?;
+        }
+        Ok(())
+    }
+
+    /// Reads an ESRI ASCII grid, cells with the `NODATA_value` are imported at sea level as the
+    /// terrain grid can't have holes.
+    pub fn from_asc(reader: impl BufRead) -> Result<Self, AffError> {
+        let mut heightmap = Self::default();
+        let mut no_data = None;
+        let mut rows = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let mut tokens = line.split_whitespace().peekable();
+            let Some(first) = tokens.peek() else {
+                continue;
+            };
+
+            if first.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                let key = first.to_ascii_lowercase();
+                let value = tokens
+                    .nth(1)
+                    .ok_or_else(|| AffError::ParseError(format!("Missing value of `{key}`")))?;
+                match key.as_str() {
+                    "ncols" => heightmap.width = parse(value)?,
+                    "nrows" => heightmap.height = parse(value)?,
+                    "xllcorner" | "xllcenter" => heightmap.origin_x = parse(value)?,
+                    "yllcorner" | "yllcenter" => heightmap.origin_y = parse(value)?,
+                    "cellsize" => heightmap.cell_size = parse(value)?,
+                    "nodata_value" => no_data = Some(parse::<f32>(value)?),
+                    _ => {}
+                }
+            } else {
+                for token in tokens {
+                    let elevation = parse::<f32>(token)?;
+                    rows.push(if Some(elevation) == no_data {
+                        0.0
+                    } else {
+                        elevation
+                    });
+                }
+            }
+        }
+
+        let width = heightmap.width as usize;
+        check_length(
+            "elevations",
+            grid_size(heightmap.width, heightmap.height),
+            rows.len(),
+        )?;
+        heightmap.elevations = rows.chunks(width.max(1)).rev().flatten().copied().collect();
+        Ok(heightmap)
+    }
+
+    /// Writes `x y z` lines of all grid points.
+    pub fn write_xyz(&self, writer: &mut impl Write) -> Result<(), AffError> {
+        for (i, elevation) in self.elevations.iter().enumerate() {
+            let (x, y) = self.position(i);
+            writeln!(writer, "{x} {y} {elevation}")?;
+        }
+        Ok(())
+    }
+
+    /// Reads `x y z` points of a regular grid in any order, separated by whitespace or commas.
+    pub fn from_xyz(reader: impl BufRead) -> Result<Self, AffError> {
+        let mut points = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let values = line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|t| !t.is_empty())
+                .map(parse::<f64>)
+                .collect::<Result<Vec<_>, _>>()?;
+            match values.as_slice() {
+                [] => {}
+                [x, y, z] => points.push((*x, *y, *z)),
+                _ => return Err(AffError::ParseError(format!("Invalid point `{line}`"))),
+            }
+        }
+
+        let axis = |coord: fn(&(f64, f64, f64)) -> f64| {
+            let mut values: Vec<f64> = points.iter().map(coord).collect();
+            values.sort_by(f64::total_cmp);
+            values.dedup();
+            values
+        };
+        let xs = axis(|p| p.0);
+        let ys = axis(|p| p.1);
+        let (Some(origin_x), Some(origin_y)) = (xs.first(), ys.first()) else {
+            return Ok(Self::default());
+        };
+        let cell_size = xs
+            .windows(2)
+            .chain(ys.windows(2))
+            .map(|w| w[1] - w[0])
+            .reduce(f64::min)
+            .unwrap_or(1.0);
+
+        let width = u32::try_from(xs.len()).unwrap_or(u32::MAX);
+        let height = u32::try_from(ys.len()).unwrap_or(u32::MAX);
+        check_length("elevations", grid_size(width, height), points.len())?;
+
+        let mut elevations = vec![0.0; points.len()];
+        for (x, y, z) in &points {
+            let column = ((x - origin_x) / cell_size).round() as usize;
+            let row = ((y - origin_y) / cell_size).round() as usize;
+            if let Some(elevation) = elevations.get_mut(row * xs.len() + column) {
+                *elevation = *z as f32;
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            cell_size: cell_size as f32,
+            origin_x: *origin_x,
+            origin_y: *origin_y,
+            elevations,
+        })
+    }
+
+    /// Writes a 16 bit grayscale PNG, the elevation range and grid are stored as text chunks.
+    #[cfg(feature = "image")]
+    pub fn write_png(&self, mut writer: impl Write) -> Result<(), AffError> {
+        let (min, max) = self.range();
+        let scale = if max > min {
+            f32::from(u16::MAX) / (max - min)
+        } else {
+            0.0
+        };
+
+        check_length(
+            "elevations",
+            grid_size(self.width, self.height),
+            self.elevations.len(),
+        )?;
+        let pixels = self
+            .rows_north_up()
+            .flatten()
+            .map(|e| ((e - min) * scale).round() as u16)
+            .collect();
+        let image = ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(self.width, self.height, pixels)
+            .ok_or(AffError::InvalidFileError)?;
+        let mut png = Vec::new();
+        image.write_with_encoder(PngEncoder::new(&mut png))?;
+
+        // The encoder doesn't write text chunks, they are placed right after the header chunk
+        let (header, rest) = png.split_at(PNG_HEADER_LEN);
+        writer.write_all(header)?;
+        for (keyword, text) in [
+            (PNG_MIN, min.to_string()),
+            (PNG_MAX, max.to_string()),
+            (PNG_CELL_SIZE, self.cell_size.to_string()),
+            (PNG_ORIGIN_X, self.origin_x.to_string()),
+            (PNG_ORIGIN_Y, self.origin_y.to_string()),
+        ] {
+            let data = [keyword.as_bytes(), &[0], text.as_bytes()].concat();
+            let chunk = [&b"tEXt"[..], &data].concat();
+            writer.write_all(&(data.len() as u32).to_be_bytes())?;
+            writer.write_all(&chunk)?;
+            writer.write_all(&crc32(&chunk).to_be_bytes())?;
+        }
+        writer.write_all(rest)?;
+        Ok(())
+    }
+
+    /// Reads 8 or 16 bit grayscale PNGs, without range metadata the raw pixel values are used.
+    #[cfg(feature = "image")]
+    pub fn from_png(mut reader: impl BufRead + Seek) -> Result<Self, AffError> {
+        let mut png = Vec::new();
+        reader.read_to_end(&mut png)?;
+        let texts = png_text_chunks(&png);
+        let text = |keyword: &str| {
+            texts
+                .iter()
+                .find(|(k, _)| *k == keyword)
+                .map(|(_, text)| *text)
+        };
+
+        let image = image::load_from_memory_with_format(&png, ImageFormat::Png)?;
+        let (values, levels): (Vec<f32>, f32) = match &image {
+            DynamicImage::ImageLuma16(image) => (
+                image.as_raw().iter().map(|v| f32::from(*v)).collect(),
+                f32::from(u16::MAX),
+            ),
+            DynamicImage::ImageLuma8(image) => (
+                image.as_raw().iter().map(|v| f32::from(*v)).collect(),
+                f32::from(u8::MAX),
+            ),
+            image => {
+                return Err(AffError::ParseError(format!(
+                    "Unsupported PNG color type `{:?}`",
+                    image.color()
+                )))
+            }
+        };
+        let (min, scale) = match (text(PNG_MIN), text(PNG_MAX)) {
+            (Some(min), Some(max)) => {
+                let (min, max): (f32, f32) = (parse(min)?, parse(max)?);
+                (min, (max - min) / levels)
+            }
+            _ => (0.0, 1.0),
+        };
+
+        let width = image.width() as usize;
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            cell_size: text(PNG_CELL_SIZE).map(parse).transpose()?.unwrap_or(1.0),
+            origin_x: text(PNG_ORIGIN_X)
+                .map(parse)
+                .transpose()?
+                .unwrap_or_default(),
+            origin_y: text(PNG_ORIGIN_Y)
+                .map(parse)
+                .transpose()?
+                .unwrap_or_default(),
+            elevations: values
+                .chunks(width.max(1))
+                .rev()
+                .flatten()
+                .map(|v| v.mul_add(scale, min))
+                .collect(),
+        })
+    }
+
+    fn rows_north_up(&self) -> impl Iterator<Item = &[f32]> {
+        self.elevations.chunks(self.width.max(1) as usize).rev()
+    }
+
+    fn position(&self, index: usize) -> (f64, f64) {
+        let width = self.width.max(1) as usize;
+        let cell_size = f64::from(self.cell_size);
+        (
+            ((index % width) as f64).mul_add(cell_size, self.origin_x),
+            ((index / width) as f64).mul_add(cell_size, self.origin_y),
+        )
+    }
+}
+
+#[cfg(feature = "image")]
+const PNG_MIN: &str = "min_elevation";
+#[cfg(feature = "image")]
+const PNG_MAX: &str = "max_elevation";
+#[cfg(feature = "image")]
+const PNG_CELL_SIZE: &str = "cell_size";
+#[cfg(feature = "image")]
+const PNG_ORIGIN_X: &str = "origin_x";
+#[cfg(feature = "image")]
+const PNG_ORIGIN_Y: &str = "origin_y";
+
+/// Signature and `IHDR` chunk.
+#[cfg(feature = "image")]
+const PNG_HEADER_LEN: usize = 8 + 25;
+
+/// Keywords and texts of the `tEXt` chunks, texts that aren't valid UTF-8 are skipped.
+#[cfg(feature = "image")]
+fn png_text_chunks(png: &[u8]) -> Vec<(&str, &str)> {
+    let mut chunks = Vec::new();
+    let mut pos = 8;
+    while let Some(header) = png.get(pos..pos + 8) {
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let Some(data) = png.get(pos + 8..pos + 8 + len) else {
+            break;
+        };
+        if &header[4..] == b"tEXt" {
+            let text = data.iter().position(|b| *b == 0).and_then(|i| {
+                Some((
+                    std::str::from_utf8(&data[..i]).ok()?,
+                    std::str::from_utf8(&data[i + 1..]).ok()?,
+                ))
+            });
+            chunks.extend(text);
+        }
+        pos += len + 12;
+    }
+    chunks
+}
+
+#[cfg(feature = "image")]
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(u32::MAX, |crc, b| {
+        (0..8).fold(crc ^ u32::from(*b), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// Bilinear interpolation on a row major grid, positions are relative to the first grid point.
+fn interpolate(
+    elevations: &[f32],
+    (width, height): (u32, u32),
+    cell_size: f32,
+    x: f64,
+    y: f64,
+) -> Option<f32> {
+    if cell_size <= 0.0 || width == 0 || height == 0 {
+        return None;
+    }
+    let cell_size = f64::from(cell_size);
+    let (gx, gy) = (x / cell_size, y / cell_size);
+    if !(0.0..=f64::from(width - 1)).contains(&gx) || !(0.0..=f64::from(height - 1)).contains(&gy) {
+        return None;
+    }
+
+    let get = |x: u32, y: u32| {
+        elevations
+            .get(y as usize * width as usize + x as usize)
+            .copied()
+    };
+    let (x0, y0) = (gx.floor() as u32, gy.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = ((gx - gx.floor()) as f32, (gy - gy.floor()) as f32);
+    let lerp = |a: f32, b: f32, t: f32| (b - a).mul_add(t, a);
+    let south = lerp(get(x0, y0)?, get(x1, y0)?, tx);
+    let north = lerp(get(x0, y1)?, get(x1, y1)?, tx);
+    Some(lerp(south, north, ty))
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, AffError> {
+    value
+        .parse()
+        .map_err(|_| AffError::ParseError(format!("Invalid number `{value}`")))
+}
+
+impl OPRW {
+    /// Terrain grid, the cell size is derived from the layer grid.
+    #[must_use]
+    pub fn heightmap(&self) -> Heightmap {
+        let (size, cell_size) = self.terrain_grid();
+        Heightmap::new(size.0, size.1, cell_size, self.elevation.clone())
+    }
+
+    /// Terrain elevation at a map position, like [`Heightmap::elevation_at`] without copying the grid.
+    #[must_use]
+    pub fn elevation_at(&self, x: f64, y: f64) -> Option<f32> {
+        let (size, cell_size) = self.terrain_grid();
+        interpolate(&self.elevation, size, cell_size, x, y)
+    }
+
+    fn terrain_grid(&self) -> ((u32, u32), f32) {
+        let width = self.map_size_x.unwrap_or_default();
+        let world_size =
+            self.layer_size_x.unwrap_or_default() as f32 * self.layer_cell_size.unwrap_or_default();
+        let cell_size = if width > 0 {
+            world_size / width as f32
+        } else {
+            0.0
+        };
+        ((width, self.map_size_y.unwrap_or_default()), cell_size)
+    }
+
+    /// Replaces the elevation, the heightmap has to match the terrain grid.
+    pub fn set_heightmap(&mut self, heightmap: &Heightmap) -> Result<(), AffError> {
+        check_grid((self.map_size_x, self.map_size_y), heightmap)?;
+        self.elevation.clone_from(&heightmap.elevations);
+        Ok(())
+    }
+}
+
+impl WVR8 {
+    #[must_use]
+    pub fn heightmap(&self) -> Heightmap {
+        let header = &self.header;
+        let world_size = header.texture_grid_size.x as f32 * header.cell_size;
+        Heightmap::new(
+            header.terrain_grid_size.x,
+            header.terrain_grid_size.y,
+            if header.terrain_grid_size.x > 0 {
+                world_size / header.terrain_grid_size.x as f32
+            } else {
+                0.0
+            },
+            self.elevations.clone(),
+        )
+    }
+
+    pub fn set_heightmap(&mut self, heightmap: &Heightmap) -> Result<(), AffError> {
+        let size = self.header.terrain_grid_size;
+        check_grid((Some(size.x), Some(size.y)), heightmap)?;
+        self.elevations.clone_from(&heightmap.elevations);
+        Ok(())
+    }
+}
+
+fn check_grid(size: (Option<u32>, Option<u32>), heightmap: &Heightmap) -> Result<(), WrpError> {
+    let (width, height) = (size.0.unwrap_or_default(), size.1.unwrap_or_default());
+    if heightmap.width != width || heightmap.height != height {
+        return Err(WrpError::InvalidLength {
+            field: "elevation",
+            expected: grid_size(width, height),
+            actual: grid_size(heightmap.width, heightmap.height),
+        });
+    }
+    check_length(
+        "elevation",
+        grid_size(width, height),
+        heightmap.elevations.len(),
+    )
+}
+
+const fn grid_size(width: u32, height: u32) -> usize {
+    width as usize * height as usize
+}
@@ -1,8 +1,11 @@
+mod heightmap;
+mod objects;
 mod oprw;
 mod quadtree;
+mod roads;
 mod wvr8;
 
-pub use self::quadtree::*;
+pub use self::{heightmap::*, objects::*, quadtree::*, roads::*};
 
 pub use self::{
     oprw::{OPRW, *},
@@ -0,0 +1,175 @@
+use std::io::Write;
+
+use crate::{
+    core::json::Json,
+    errors::AffError,
+    real_virtuality::types::{TransformMatrix, XYZTriplet},
+};
+
+use super::OPRW;
+
+/// Object placed on a terrain.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct PlacedObject {
+    pub object_id: u32,
+    pub model: String,
+
+    /// Map position, `x` east, `y` north and `z` the altitude above sea level.
+    pub position: XYZTriplet,
+
+    /// Degrees clockwise from north.
+    pub yaw: f32,
+    /// Degrees, positive when the front points up.
+    pub pitch: f32,
+    /// Degrees, positive when leaning to the right.
+    pub roll: f32,
+    pub scale: f32,
+
+    /// Terrain elevation below the object, `None` outside the terrain grid.
+    pub surface: Option<f32>,
+}
+
+impl PlacedObject {
+    /// Decomposes a world transform, scaling is expected to be uniform.
+    #[must_use]
+    pub fn from_transform(object_id: u32, model: String, transform: &TransformMatrix) -> Self {
+        let TransformMatrix(aside, up, dir, pos) = transform;
+        let scale = (aside.length() + up.length() + dir.length()) / 3.0;
+        let (aside, up, dir) = (aside.normalized(), up.normalized(), dir.normalized());
+
+        Self {
+            object_id,
+            model,
+            position: XYZTriplet::new(pos.x, pos.z, pos.y),
+            yaw: dir.x.atan2(dir.z).to_degrees().rem_euclid(360.0),
+            // Adding zero turns negative zeros into zeros for the text exports
+            pitch: dir.y.clamp(-1.0, 1.0).asin().to_degrees() + 0.0,
+            roll: (-aside.y).atan2(up.y).to_degrees() + 0.0,
+            scale,
+            surface: None,
+        }
+    }
+
+    /// Model file name without folders and extension, used as template name by Terrain Builder.
+    #[must_use]
+    pub fn model_name(&self) -> &str {
+        let name = self.model.rsplit(['\\', '/']).next().unwrap_or_default();
+        match name.len().checked_sub(4) {
+            Some(i) if name.is_char_boundary(i) && name[i..].eq_ignore_ascii_case(".p3d") => {
+                &name[..i]
+            }
+            _ => name,
+        }
+    }
+
+    /// Height above the terrain, or above sea level if the surface is unknown.
+    #[must_use]
+    pub fn relative_height(&self) -> f32 {
+        self.position.z - self.surface.unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct ObjectExportOptions {
+    /// Added to the map coordinates, Terrain Builder projects use an easting of 200000.
+    pub origin_x: f64,
+    pub origin_y: f64,
+}
+
+impl ObjectExportOptions {
+    #[must_use]
+    pub const fn terrain_builder() -> Self {
+        Self {
+            origin_x: 200_000.0,
+            origin_y: 0.0,
+        }
+    }
+
+    fn coordinates(&self, object: &PlacedObject) -> (f64, f64) {
+        (
+            self.origin_x + f64::from(object.position.x),
+            self.origin_y + f64::from(object.position.y),
+        )
+    }
+}
+
+/// Writes the Terrain Builder object import format, heights are relative to the terrain.
+pub fn write_terrain_builder<'a>(
+    writer: &mut impl Write,
+    objects: impl IntoIterator<Item = &'a PlacedObject>,
+    options: &ObjectExportOptions,
+) -> Result<(), AffError> {
+    for object in objects {
+        let (x, y) = options.coordinates(object);
+        writeln!(
+            writer,
+            "\"{}\";{x:.6};{y:.6};{:.6};{:.6};{:.6};{:.6};{:.6};",
+            object.model_name(),
+            object.yaw,
+            object.pitch,
+            object.roll,
+            object.scale,
+            object.relative_height()
+        )?;
+    }
+    Ok(())
+}
+
+/// `GeoJSON` feature collection with a point per object, heights are above sea level.
+#[must_use]
+pub fn objects_to_geojson<'a>(
+    objects: impl IntoIterator<Item = &'a PlacedObject>,
+    options: &ObjectExportOptions,
+) -> String {
+    let features = objects.into_iter().map(|object| {
+        let (x, y) = options.coordinates(object);
+        Json::object([
+            ("type", "Feature".into()),
+            (
+                "geometry",
+                Json::object([
+                    ("type", "Point".into()),
+                    (
+                        "coordinates",
+                        Json::array([x, y, f64::from(object.position.z)]),
+                    ),
+                ]),
+            ),
+            (
+                "properties",
+                Json::object([
+                    ("id", object.object_id.into()),
+                    ("model", object.model.as_str().into()),
+                    ("yaw", object.yaw.into()),
+                    ("pitch", object.pitch.into()),
+                    ("roll", object.roll.into()),
+                    ("scale", object.scale.into()),
+                ]),
+            ),
+        ])
+    });
+
+    Json::object([
+        ("type", "FeatureCollection".into()),
+        ("features", Json::array(features)),
+    ])
+    .to_string()
+}
+
+impl OPRW {
+    /// Objects with their model path and decomposed transform.
+    pub fn placed_objects(&self) -> impl Iterator<Item = PlacedObject> + '_ {
+        self.objects.iter().map(move |object| {
+            let model = self
+                .models
+                .get(object.model_index as usize)
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let mut placed =
+                PlacedObject::from_transform(object.object_id, model, &object.transform_matrx);
+            placed.surface =
+                self.elevation_at(f64::from(placed.position.x), f64::from(placed.position.y));
+            placed
+        })
+    }
+}
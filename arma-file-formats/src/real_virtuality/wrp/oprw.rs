@@ -1,22 +1,33 @@
-use std::io::{Cursor, Read, Seek};
+use std::{
+    fs::File,
+    io::{Cursor, Read, Seek, Write},
+    path::Path,
+};
 
 use super::QuadTree;
 use crate::{
     core::decompress_lzss_unk_size,
-    errors::AffError,
+    errors::{AffError, WrpError},
     real_virtuality::{
-        binrw_utils::{read_compressed_array_count, read_compressed_data_cond_count},
+        binrw_utils::{
+            compress_array, read_compressed_array_count, read_compressed_data_cond_count,
+        },
         p3d::ODOLArgs,
         types::{BoundingBox, TransformMatrix, XYZTriplet, XY},
     },
 };
-use binrw::{until_eof, Endian, NullString};
+use binrw::{binrw, until_eof, BinResult, BinWrite, Endian, NullString};
 
 use binrw::BinRead;
 use derivative::Derivative;
 
 const OPRW_SIZE_OF_WPROBJECT: u32 = 60;
 
+/// Oldest OPRW version that can be written, older versions use LZSS instead of LZO compression.
+pub const OPRW_MIN_WRITE_VERSION: u32 = 23;
+/// Newest known OPRW version.
+pub const OPRW_MAX_VERSION: u32 = 25;
+
 #[derive(Debug, Default, PartialEq, Clone, BinRead)]
 #[br(magic = b"OPRW")]
 pub struct OPRW {
@@ -50,17 +61,17 @@ pub struct OPRW {
     pub layer_size: Option<u32>,
 
     #[br(args(2))]
-    geography: QuadTree,
+    pub geography: QuadTree,
 
     #[br(args(1))]
-    sound_map: QuadTree,
+    pub sound_map: QuadTree,
 
     mountain_count: u32,
     #[br(count = mountain_count)]
     pub mountains: Vec<XYZTriplet>,
 
     #[br(args(4))]
-    rvmat_layer_index: QuadTree,
+    pub rvmat_layer_index: QuadTree,
 
     #[br(args(version < 21, layer_size.map(|ls| ls * 2).unwrap_or_default() as usize, args,))]
     #[br(parse_with = read_compressed_data_cond_count)]
@@ -105,11 +116,11 @@ pub struct OPRW {
 
     #[br(args(1, layer_size.unwrap_or_default() as usize, args,))]
     #[br(parse_with = read_compressed_array_count)]
-    unknown_bytes_0: Vec<u8>,
+    pub unknown_bytes_0: Vec<u8>,
 
     #[br(args(1, map_size.unwrap_or_default() as usize, args,))]
     #[br(parse_with = read_compressed_array_count)]
-    unknown_bytes_1: Vec<u8>,
+    pub unknown_bytes_1: Vec<u8>,
 
     pub max_object_id: u32,
 
@@ -146,12 +157,281 @@ impl OPRW {
 
     fn read_oprw(reader: &mut (impl Read + Seek)) -> Result<Self, AffError> {
         let mut oprw = Self::read_options(reader, Endian::Little, ())?;
-        oprw.road_net.retain(|rn| rn.road_part_count > 0);
+        for (i, road_net) in oprw.road_net.iter_mut().enumerate() {
+            road_net.cell_index = i;
+        }
+        oprw.road_net.retain(|rn| !rn.road_parts.is_empty());
         Ok(oprw)
     }
+
+    /// Geography flags of a layer cell.
+    #[must_use]
+    pub fn geography_at(&self, x: u32, y: u32) -> Option<u16> {
+        self.layer_value(&self.geography, x, y)
+            .and_then(|v| u16::try_from(v).ok())
+    }
+
+    #[must_use]
+    pub fn sound_map_at(&self, x: u32, y: u32) -> Option<u8> {
+        self.layer_value(&self.sound_map, x, y)
+            .and_then(|v| u8::try_from(v).ok())
+    }
+
+    /// Index into the textures of a layer cell.
+    #[must_use]
+    pub fn rvmat_layer_at(&self, x: u32, y: u32) -> Option<u32> {
+        self.layer_value(&self.rvmat_layer_index, x, y)
+    }
+
+    /// Byte offset of the first object of a layer cell.
+    #[must_use]
+    pub fn object_offset_at(&self, x: u32, y: u32) -> Option<u32> {
+        self.layer_value(&self.object_offsets, x, y)
+    }
+
+    /// Byte offset of the first map info of a layer cell.
+    #[must_use]
+    pub fn map_object_offset_at(&self, x: u32, y: u32) -> Option<u32> {
+        self.layer_value(&self.map_object_offsets, x, y)
+    }
+
+    fn layer_value(&self, tree: &QuadTree, x: u32, y: u32) -> Option<u32> {
+        tree.value_at(x, y, self.layer_size_x?, self.layer_size_y?)
+    }
+
+    pub fn write<W>(&self, writer: &mut W) -> Result<(), AffError>
+    where
+        W: Write + Seek,
+    {
+        if !(OPRW_MIN_WRITE_VERSION..=OPRW_MAX_VERSION).contains(&self.version) {
+            return Err(WrpError::UnsupportedVersion(self.version).into());
+        }
+
+        let map_size = self.map_size_x.unwrap_or_default() as usize
+            * self.map_size_y.unwrap_or_default() as usize;
+        let layer_size = self.layer_size_x.unwrap_or_default() as usize
+            * self.layer_size_y.unwrap_or_default() as usize;
+        check_length("elevation", map_size, self.elevation.len())?;
+        check_length("unknown_bytes_0", layer_size, self.unknown_bytes_0.len())?;
+        check_length("unknown_bytes_1", map_size, self.unknown_bytes_1.len())?;
+        for (field, data) in [("grass", &self.grass), ("tex_index", &self.tex_index)] {
+            check_length(field, map_size, data.as_ref().map_or(0, Vec::len))?;
+        }
+        if let Some(road_net) = self.road_net.iter().find(|rn| rn.cell_index >= layer_size) {
+            return Err(WrpError::InvalidLength {
+                field: "road_net",
+                expected: layer_size,
+                actual: road_net.cell_index + 1,
+            }
+            .into());
+        }
+        if let Some(info) = self
+            .map_infos
+            .iter()
+            .find(|info| matches!(info.data, MapData::Unknown { .. }))
+        {
+            return Err(WrpError::UnknownMapType(info.id).into());
+        }
+
+        self.write_le(writer)?;
+        Ok(())
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<(), AffError> {
+        let mut file = File::create(path)?;
+        self.write(&mut file)
+    }
+
+    pub fn write_data(&self) -> Result<Vec<u8>, AffError> {
+        let mut buf = Vec::new();
+        self.write(&mut Cursor::new(&mut buf))?;
+        Ok(buf)
+    }
+
+    /// Removes an object and its map infos.
+    ///
+    /// The object and map object quadtrees store byte offsets into their sections, offsets
+    /// behind the removed data are moved accordingly.
+    pub fn remove_object(&mut self, object_id: u32) -> Option<Object> {
+        let index = self.objects.iter().position(|o| o.object_id == object_id)?;
+        let object = self.objects.remove(index);
+        let object_size = self.object_size();
+        let offset = index as u32 * object_size;
+        self.object_offsets
+            .map_u32(&|o| if o > offset { o - object_size } else { o });
+
+        let mut offset = 0;
+        let mut removed = Vec::new();
+        for info in &self.map_infos {
+            let size = info.size();
+            if info.data.object_id() == Some(object_id) {
+                removed.push((offset, size));
+            }
+            offset += size;
+        }
+        self.map_infos
+            .retain(|info| info.data.object_id() != Some(object_id));
+        for (offset, size) in removed.into_iter().rev() {
+            self.map_object_offsets
+                .map_u32(&|o| if o > offset { o - size } else { o });
+        }
+
+        Some(object)
+    }
+
+    /// Replaces the model of an object, new model paths are added to the model list.
+    pub fn replace_object_model(&mut self, object_id: u32, model: &str) -> bool {
+        let Some(object) = self.objects.iter_mut().find(|o| o.object_id == object_id) else {
+            return false;
+        };
+
+        let index = self
+            .models
+            .iter()
+            .position(|m| m.to_string().eq_ignore_ascii_case(model))
+            .unwrap_or_else(|| {
+                self.models.push(model.into());
+                self.models.len() - 1
+            });
+        object.model_index = index as u32;
+        true
+    }
+
+    const fn args(&self) -> ODOLArgs {
+        ODOLArgs {
+            version: self.version,
+            use_lzo: self.version >= 23,
+            use_compression_flag: false,
+            skip_lods: false,
+        }
+    }
+
+    const fn object_size(&self) -> u32 {
+        if self.version >= 14 {
+            OPRW_SIZE_OF_WPROBJECT
+        } else {
+            OPRW_SIZE_OF_WPROBJECT - 4
+        }
+    }
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+pub(super) const fn check_length(
+    field: &'static str,
+    expected: usize,
+    actual: usize,
+) -> Result<(), WrpError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(WrpError::InvalidLength {
+            field,
+            expected,
+            actual,
+        })
+    }
+}
+
+/// Writes a section to a buffer to prefix it with its size.
+fn section_data(write: impl FnOnce(&mut Cursor<Vec<u8>>) -> BinResult<()>) -> BinResult<Vec<u8>> {
+    let mut data = Cursor::new(Vec::new());
+    write(&mut data)?;
+    Ok(data.into_inner())
+}
+
+impl BinWrite for OPRW {
+    type Args<'a> = ();
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: Endian,
+        (): Self::Args<'_>,
+    ) -> BinResult<()> {
+        let version = self.version;
+        let args = self.args();
+
+        b"OPRW".write_options(writer, endian, ())?;
+        version.write_options(writer, endian, ())?;
+        if version >= 25 {
+            self.app_id
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+        }
+        if version >= 12 {
+            self.layer_size_x
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+            self.layer_size_y
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+            self.map_size_x
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+            self.map_size_y
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+            self.layer_cell_size
+                .unwrap_or_default()
+                .write_options(writer, endian, ())?;
+        }
+
+        self.geography.write_options(writer, endian, ())?;
+        self.sound_map.write_options(writer, endian, ())?;
+        (self.mountains.len() as u32).write_options(writer, endian, ())?;
+        self.mountains.write_options(writer, endian, ())?;
+        self.rvmat_layer_index.write_options(writer, endian, ())?;
+
+        for data in [&self.grass, &self.tex_index].into_iter().flatten() {
+            compress_array(writer, endian, data, args)?;
+        }
+        compress_array(writer, endian, &self.elevation, args)?;
+
+        (self.texures.len() as u32).write_options(writer, endian, ())?;
+        self.texures.write_options(writer, endian, ())?;
+        (self.models.len() as u32).write_options(writer, endian, ())?;
+        self.models.write_options(writer, endian, ())?;
+        if version >= 15 {
+            let classed_models = self.classed_models.as_deref().unwrap_or_default();
+            (classed_models.len() as u32).write_options(writer, endian, ())?;
+            classed_models.write_options(writer, endian, ())?;
+        }
+
+        let objects = section_data(|data| self.objects.write_options(data, endian, (version,)))?;
+        let map_infos = section_data(|data| self.map_infos.write_options(data, endian, ()))?;
+
+        self.object_offsets.write_options(writer, endian, ())?;
+        (objects.len() as u32).write_options(writer, endian, ())?;
+        self.map_object_offsets.write_options(writer, endian, ())?;
+        (map_infos.len() as u32).write_options(writer, endian, ())?;
+
+        compress_array(writer, endian, &self.unknown_bytes_0, args)?;
+        compress_array(writer, endian, &self.unknown_bytes_1, args)?;
+        self.max_object_id.write_options(writer, endian, ())?;
+
+        // Empty cells are removed on read and restored by their cell index
+        let layer_size = self.layer_size_x.unwrap_or_default() as usize
+            * self.layer_size_y.unwrap_or_default() as usize;
+        let empty = RoadNet::default();
+        let road_net = section_data(|data| {
+            for cell in 0..layer_size {
+                self.road_net
+                    .iter()
+                    .find(|rn| rn.cell_index == cell)
+                    .unwrap_or(&empty)
+                    .write_options(data, endian, (version,))?;
+            }
+            Ok(())
+        })?;
+        (road_net.len() as u32).write_options(writer, endian, ())?;
+        writer.write_all(&road_net)?;
+
+        writer.write_all(&objects)?;
+        writer.write_all(&map_infos)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, BinRead, BinWrite)]
 pub struct ClassedModel {
     pub class_name: NullString,
     pub model_path: NullString,
@@ -159,25 +439,38 @@ pub struct ClassedModel {
     pub obj_id: u32,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 pub struct Texture {
     pub texture_filename: NullString,
     flag: NullString,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[binrw]
+#[derive(Debug, Default, PartialEq, Clone)]
 #[br(import(version: u32))]
+#[bw(import(version: u32))]
 pub struct RoadNet {
+    #[br(temp)]
+    #[bw(calc = road_parts.len() as u32)]
     road_part_count: u32,
 
     #[br(count = road_part_count)]
     #[br(args { inner: (version,) })]
+    #[bw(args(version,))]
     pub road_parts: Vec<RoadPart>,
+
+    /// Layer cell of the road net, empty cells are dropped on read.
+    #[brw(ignore)]
+    pub cell_index: usize,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[binrw]
+#[derive(Debug, Default, PartialEq, Clone)]
 #[br(import(version: u32))]
+#[bw(import(version: u32))]
 pub struct RoadPart {
+    #[br(temp)]
+    #[bw(calc = positions.len() as u16)]
     road_pos_count: u16,
 
     #[br(count = road_pos_count)]
@@ -185,29 +478,42 @@ pub struct RoadPart {
 
     #[br(if(version >= 24))]
     #[br(count = road_pos_count)]
+    #[bw(if(version >= 24))]
+    #[bw(map = |x: &Option<Vec<u8>>| {
+        let mut types = x.clone().unwrap_or_default();
+        types.resize(positions.len(), 0);
+        types
+    })]
     pub types: Option<Vec<u8>>,
 
     pub object_id: u32,
 
     #[br(if(version >= 16))]
+    #[bw(if(version >= 16))]
+    #[bw(map = |x: &Option<NullString>| x.clone().unwrap_or_default())]
     pub p3d_path: Option<NullString>,
 
     #[br(if(version >= 16))]
+    #[bw(if(version >= 16))]
+    #[bw(map = |x: &Option<TransformMatrix>| x.unwrap_or_default())]
     pub transform_matrix: Option<TransformMatrix>,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, BinRead, BinWrite)]
 #[br(import(version: u32))]
+#[bw(import(version: u32))]
 pub struct Object {
     pub object_id: u32,
     pub model_index: u32,
     pub transform_matrx: TransformMatrix,
 
     #[br(if(version >= 14))]
+    #[bw(if(version >= 14))]
+    #[bw(map = |x: &Option<u32>| x.unwrap_or_default())]
     pub shape_params: Option<u32>,
 }
 
-#[derive(Debug, Default, PartialEq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Clone, BinRead, BinWrite)]
 pub struct MapInfo {
     pub id: u32,
     #[br(args(id))]
@@ -219,7 +525,7 @@ const MAP_TYPE_2_IDS: [u32; 3] = [24, 31, 32];
 const MAP_TYPE_3_IDS: [u32; 5] = [25, 33, 41, 42, 43]; // 41, 42, 43 (stratis)
 const MAP_TYPE_4_IDS: [u32; 14] = [3, 4, 8, 9, 18, 19, 20, 21, 28, 29, 36, 37, 38, 39]; // 36 (malden), 37,38 (altis), 39 (stratis) no doc
 
-#[derive(Debug, PartialEq, Clone, BinRead, Derivative)]
+#[derive(Debug, PartialEq, Clone, BinRead, BinWrite, Derivative)]
 #[derivative(Default)]
 #[br(import(id: u32))]
 pub enum MapData {
@@ -258,6 +564,7 @@ pub enum MapData {
     #[br(pre_assert(id == 44))]
     MapTypeRiver {
         object_id: u32,
+        #[bw(map = |_: &u32| polygon.len() as u32)]
         polygon_count: u32,
         #[br(count = polygon_count)]
         polygon: Vec<XY>,
@@ -269,6 +576,40 @@ pub enum MapData {
         first_byte: u8,
 
         #[br(calc = if true { panic!("Unknown map type") } else { 0 })] // bruh
+        #[bw(ignore)]
         _panics: u8,
     },
 }
+
+impl MapInfo {
+    /// Size of the map info in bytes.
+    #[must_use]
+    pub const fn size(&self) -> u32 {
+        let size = match &self.data {
+            MapData::MapType1 { .. } => 12,
+            MapData::MapType2 { .. } => 36,
+            MapData::MapType3 { floats, .. } => 8 + floats.len() * 4,
+            MapData::MapType4 { color, .. } => 36 + color.len(),
+            MapData::MapType5 { floats, .. } => 4 + floats.len() * 4,
+            MapData::MapType35 { line, .. } => 5 + line.len() * 4,
+            MapData::MapTypeRiver { polygon, .. } => 8 + polygon.len() * 8,
+            MapData::Unknown { .. } => 1,
+        };
+        4 + size as u32
+    }
+}
+
+impl MapData {
+    #[must_use]
+    pub const fn object_id(&self) -> Option<u32> {
+        match self {
+            Self::MapType1 { object_id, .. }
+            | Self::MapType2 { object_id, .. }
+            | Self::MapType4 { object_id, .. }
+            | Self::MapType5 { object_id, .. }
+            | Self::MapType35 { object_id, .. }
+            | Self::MapTypeRiver { object_id, .. } => Some(*object_id),
+            Self::MapType3 { .. } | Self::Unknown { .. } => None,
+        }
+    }
+}
@@ -1,20 +1,21 @@
-use std::io::{Cursor, Seek};
+use std::io::{Cursor, Seek, Write};
 
-use binrw::{BinRead, BinResult, Endian};
+use binrw::{BinRead, BinResult, BinWrite, Endian};
 
 use derivative::Derivative;
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, BinRead, BinWrite)]
 #[br(import(element_size: u32))]
 pub struct QuadTree {
     #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| u8::from(*x))]
     flag: bool,
 
     #[br(args(flag, element_size))]
     root: QuadTreeData,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, BinRead, Derivative)]
+#[derive(Debug, PartialEq, Eq, Clone, BinRead, BinWrite, Derivative)]
 #[derivative(Default)]
 #[br(import(flag: bool, element_size: u32))]
 pub enum QuadTreeData {
@@ -58,6 +59,30 @@ impl BinRead for QuadTreeNode {
     }
 }
 
+impl BinWrite for QuadTreeNode {
+    type Args<'a> = ();
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: Endian,
+        (): Self::Args<'_>,
+    ) -> BinResult<()> {
+        let bit_mask = self
+            .sub_trees
+            .iter()
+            .enumerate()
+            .filter(|(_, tree)| matches!(tree, QuadTreeData::Node(_)))
+            .fold(0_u16, |mask, (i, _)| mask | (1 << i));
+        bit_mask.write_options(writer, endian, ())?;
+
+        for tree in &self.sub_trees {
+            tree.write_options(writer, endian, ())?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub struct QuadTreeLeaf {
     element_size: u32,
@@ -82,24 +107,150 @@ impl BinRead for QuadTreeLeaf {
     }
 }
 
-impl QuadTreeLeaf {
-    pub fn get<'a, T: BinRead<Args<'a> = ()>>(&self, x: u32, y: u32) -> BinResult<Option<T>> {
-        let offset = u64::from(match self.element_size {
-            1 => 0,
-            2 => x * 2,
-            4 => (y << 1) + x,
-            _ => todo!(),
-        });
+impl BinWrite for QuadTreeLeaf {
+    type Args<'a> = ();
 
-        let mut data_reader = Cursor::new(&self.data);
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        _: Endian,
+        (): Self::Args<'_>,
+    ) -> BinResult<()> {
+        let mut data = [0_u8; 4];
+        for (d, v) in data.iter_mut().zip(&self.data) {
+            *d = *v;
+        }
+        writer.write_all(&data)?;
+        Ok(())
+    }
+}
 
-        if offset > data_reader.stream_position()? {
-            return Ok(None);
+impl QuadTree {
+    /// Maps every stored value of a tree with 4 byte elements.
+    pub(crate) fn map_u32(&mut self, f: &impl Fn(u32) -> u32) {
+        self.root.map_u32(f);
+    }
+}
+
+impl QuadTreeData {
+    fn map_u32(&mut self, f: &impl Fn(u32) -> u32) {
+        match self {
+            Self::Node(node) => {
+                for tree in &mut node.sub_trees {
+                    tree.map_u32(f);
+                }
+            }
+            Self::Leaf(leaf) => {
+                let mut value = [0_u8; 4];
+                for (v, d) in value.iter_mut().zip(&leaf.data) {
+                    *v = *d;
+                }
+                leaf.data = f(u32::from_le_bytes(value)).to_le_bytes().to_vec();
+            }
         }
+    }
+}
+
+impl QuadTreeLeaf {
+    pub fn get<'a, T: BinRead<Args<'a> = ()>>(&self, x: u32, y: u32) -> BinResult<Option<T>> {
+        let Some(offset) = self.offset(x, y) else {
+            return Ok(None);
+        };
 
+        let mut data_reader = Cursor::new(&self.data);
         data_reader.set_position(offset);
 
         let val = T::read_options(&mut data_reader, Endian::Little, ())?;
         Ok(Some(val))
     }
+
+    /// Element of the leaf widened to `u32`.
+    #[must_use]
+    pub fn value(&self, x: u32, y: u32) -> Option<u32> {
+        let offset = self.offset(x, y)? as usize;
+        let bytes = self.data.get(offset..offset + self.element_size as usize)?;
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, b| (value << 8) | u32::from(*b)),
+        )
+    }
+
+    /// Byte offset of an element, leaves pack 2x2 bytes, 2x1 shorts or a single 4 byte value.
+    fn offset(&self, x: u32, y: u32) -> Option<u64> {
+        let (width, height) = leaf_dimensions(self.element_size)?;
+        if x >= width || y >= height {
+            return None;
+        }
+        Some(u64::from((y * width + x) * self.element_size))
+    }
+}
+
+impl QuadTree {
+    /// Value of cell (`x`, `y`) of a `width` x `height` grid, widened to `u32`.
+    #[must_use]
+    pub fn value_at(&self, x: u32, y: u32, width: u32, height: u32) -> Option<u32> {
+        if x >= width || y >= height {
+            return None;
+        }
+        let (leaf_width, leaf_height) = leaf_dimensions(self.element_size()?)?;
+
+        // The root covers the grid rounded up to leaf size times a power of four
+        let (mut node_width, mut node_height) = (leaf_width, leaf_height);
+        while node_width < width || node_height < height {
+            node_width *= 4;
+            node_height *= 4;
+        }
+
+        let (mut x, mut y) = (x, y);
+        let mut tree = &self.root;
+        loop {
+            match tree {
+                // Leaves above the lowest level cover a uniform area
+                QuadTreeData::Leaf(leaf) => return leaf.value(x % leaf_width, y % leaf_height),
+                QuadTreeData::Node(node) => {
+                    if node_width == leaf_width {
+                        return None;
+                    }
+                    node_width /= 4;
+                    node_height /= 4;
+                    tree = node
+                        .sub_trees
+                        .get(((y / node_height) * 4 + x / node_width) as usize)?;
+                    x %= node_width;
+                    y %= node_height;
+                }
+            }
+        }
+    }
+
+    /// Expands the tree into row major values of a `width` x `height` grid.
+    #[must_use]
+    pub fn to_grid(&self, width: u32, height: u32) -> Vec<u32> {
+        (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| self.value_at(x, y, width, height).unwrap_or_default())
+            })
+            .collect()
+    }
+
+    fn element_size(&self) -> Option<u32> {
+        let mut tree = &self.root;
+        loop {
+            match tree {
+                QuadTreeData::Leaf(leaf) => return Some(leaf.element_size),
+                QuadTreeData::Node(node) => tree = node.sub_trees.first()?,
+            }
+        }
+    }
+}
+
+const fn leaf_dimensions(element_size: u32) -> Option<(u32, u32)> {
+    match element_size {
+        1 => Some((2, 2)),
+        2 => Some((2, 1)),
+        4 => Some((1, 1)),
+        _ => None,
+    }
 }
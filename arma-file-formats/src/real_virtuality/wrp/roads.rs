@@ -0,0 +1,309 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{core::json::Json, real_virtuality::types::XYZTriplet};
+
+use super::{ObjectExportOptions, OPRW};
+
+/// Horizontal distance in meters up to which road part endpoints are joined, their altitude
+/// isn't compared as it differs slightly between adjacent parts.
+const WELD_DISTANCE: f32 = 0.01;
+
+/// Road category, by default derived from the model file name.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum RoadClass {
+    MainRoad,
+    Road,
+    Track,
+    Trail,
+    #[default]
+    Unknown,
+}
+
+/// File name fragments of the vanilla road models, checked in order.
+const ROAD_CLASS_PATTERNS: [(&str, RoadClass); 14] = [
+    ("asf1", RoadClass::MainRoad),
+    ("main", RoadClass::MainRoad),
+    ("highway", RoadClass::MainRoad),
+    ("asf", RoadClass::Road),
+    ("city", RoadClass::Road),
+    ("concrete", RoadClass::Road),
+    ("grav", RoadClass::Track),
+    ("mud", RoadClass::Track),
+    ("dirt", RoadClass::Track),
+    ("track", RoadClass::Track),
+    ("path", RoadClass::Trail),
+    ("trail", RoadClass::Trail),
+    ("bridge", RoadClass::Road),
+    ("road", RoadClass::Road),
+];
+
+impl RoadClass {
+    /// Guesses the class from the model name, configs with a different naming can be mapped with
+    /// [`RoadGraph::from_oprw_with`].
+    #[must_use]
+    pub fn from_model(p3d_path: &str) -> Self {
+        let name = p3d_path
+            .rsplit(['\\', '/'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        ROAD_CLASS_PATTERNS
+            .iter()
+            .find(|(pattern, _)| name.contains(pattern))
+            .map_or(Self::Unknown, |(_, class)| *class)
+    }
+}
+
+impl fmt::Display for RoadClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MainRoad => "main road",
+            Self::Road => "road",
+            Self::Track => "track",
+            Self::Trail => "trail",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// Road network with junctions and dead ends as nodes.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct RoadGraph {
+    /// Map positions, `x` east, `y` north and `z` the altitude above sea level.
+    pub nodes: Vec<XYZTriplet>,
+    pub edges: Vec<RoadEdge>,
+}
+
+/// Road parts of the same class stitched into a polyline between two nodes.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct RoadEdge {
+    pub start: usize,
+    pub end: usize,
+    pub class: RoadClass,
+
+    /// Objects of the road parts in driving order from `start` to `end`.
+    pub object_ids: Vec<u32>,
+    pub points: Vec<XYZTriplet>,
+}
+
+impl RoadEdge {
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.points
+            .windows(2)
+            .map(|p| p[1].sub(&p[0]).length())
+            .sum()
+    }
+}
+
+struct Segment {
+    start: usize,
+    end: usize,
+    class: RoadClass,
+    object_id: u32,
+    points: Vec<XYZTriplet>,
+}
+
+impl RoadGraph {
+    #[must_use]
+    pub fn from_oprw(oprw: &OPRW) -> Self {
+        Self::from_oprw_with(oprw, RoadClass::from_model)
+    }
+
+    /// Builds the graph with a custom classification of the road part models.
+    #[must_use]
+    pub fn from_oprw_with(oprw: &OPRW, classify: impl Fn(&str) -> RoadClass) -> Self {
+        let mut graph = Self::default();
+        // Nodes by grid cell of the weld distance, close endpoints may lie in a neighbouring cell
+        let mut cells: HashMap<[i64; 2], Vec<usize>> = HashMap::new();
+        let mut node = |graph: &mut Self, point: XYZTriplet| {
+            let cell = [point.x, point.y].map(|v| (v / WELD_DISTANCE).floor() as i64);
+            let welded = (-1..=1)
+                .flat_map(|dx| (-1..=1).map(move |dy| [cell[0] + dx, cell[1] + dy]))
+                .filter_map(|c| cells.get(&c))
+                .flatten()
+                .find(|n| {
+                    let other = graph.nodes[**n];
+                    (other.x - point.x).hypot(other.y - point.y) <= WELD_DISTANCE
+                })
+                .copied();
+            welded.unwrap_or_else(|| {
+                graph.nodes.push(point);
+                cells.entry(cell).or_default().push(graph.nodes.len() - 1);
+                graph.nodes.len() - 1
+            })
+        };
+
+        let mut segments = Vec::new();
+        for part in oprw.road_net.iter().flat_map(|rn| &rn.road_parts) {
+            // Road positions are stored like the object transforms with the altitude as `y`
+            let points: Vec<XYZTriplet> = part
+                .positions
+                .iter()
+                .map(|p| XYZTriplet::new(p.x, p.z, p.y))
+                .collect();
+            let [first, .., last] = points.as_slice() else {
+                continue;
+            };
+
+            let model = part
+                .p3d_path
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            segments.push(Segment {
+                start: node(&mut graph, *first),
+                end: node(&mut graph, *last),
+                class: classify(&model),
+                object_id: part.object_id,
+                points,
+            });
+        }
+
+        let mut incident = vec![Vec::new(); graph.nodes.len()];
+        for (i, segment) in segments.iter().enumerate() {
+            incident[segment.start].push(i);
+            incident[segment.end].push(i);
+        }
+        // Nodes only joining two parts of the same class are merged into the polylines
+        let is_through = |n: usize| match incident[n].as_slice() {
+            [a, b] => a != b && segments[*a].class == segments[*b].class,
+            _ => false,
+        };
+
+        let mut visited = vec![false; segments.len()];
+        for n in 0..graph.nodes.len() {
+            if is_through(n) {
+                continue;
+            }
+            for s in &incident[n] {
+                if !visited[*s] {
+                    graph.edges.push(stitch(
+                        &segments,
+                        &incident,
+                        &mut visited,
+                        *s,
+                        n,
+                        &is_through,
+                    ));
+                }
+            }
+        }
+        // Remaining parts form loops without junctions
+        for s in 0..segments.len() {
+            if !visited[s] {
+                let start = segments[s].start;
+                graph.edges.push(stitch(
+                    &segments,
+                    &incident,
+                    &mut visited,
+                    s,
+                    start,
+                    &is_through,
+                ));
+            }
+        }
+        graph
+    }
+
+    /// Edges connected to a node with the node at their other end.
+    pub fn neighbors(&self, node: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.edges.iter().enumerate().filter_map(move |(i, edge)| {
+            if edge.start == node {
+                Some((i, edge.end))
+            } else if edge.end == node {
+                Some((i, edge.start))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// `GeoJSON` feature collection with a line string per edge.
+    #[must_use]
+    pub fn to_geojson(&self, options: &ObjectExportOptions) -> String {
+        let features = self.edges.iter().map(|edge| {
+            let coordinates = edge.points.iter().map(|p| {
+                Json::array([
+                    options.origin_x + f64::from(p.x),
+                    options.origin_y + f64::from(p.y),
+                    f64::from(p.z),
+                ])
+            });
+            Json::object([
+                ("type", "Feature".into()),
+                (
+                    "geometry",
+                    Json::object([
+                        ("type", "LineString".into()),
+                        ("coordinates", Json::array(coordinates)),
+                    ]),
+                ),
+                (
+                    "properties",
+                    Json::object([
+                        ("class", edge.class.to_string().into()),
+                        ("start", edge.start.into()),
+                        ("end", edge.end.into()),
+                        ("length", edge.length().into()),
+                        ("objects", Json::array(edge.object_ids.iter().copied())),
+                    ]),
+                ),
+            ])
+        });
+
+        Json::object([
+            ("type", "FeatureCollection".into()),
+            ("features", Json::array(features)),
+        ])
+        .to_string()
+    }
+}
+
+fn stitch(
+    segments: &[Segment],
+    incident: &[Vec<usize>],
+    visited: &mut [bool],
+    first: usize,
+    start: usize,
+    is_through: &impl Fn(usize) -> bool,
+) -> RoadEdge {
+    let mut edge = RoadEdge {
+        start,
+        end: start,
+        class: segments[first].class,
+        ..Default::default()
+    };
+
+    let mut current = first;
+    loop {
+        visited[current] = true;
+        let segment = &segments[current];
+        let forward = segment.start == edge.end;
+        let points: Box<dyn Iterator<Item = &XYZTriplet>> = if forward {
+            Box::new(segment.points.iter())
+        } else {
+            Box::new(segment.points.iter().rev())
+        };
+        let skip = usize::from(!edge.points.is_empty());
+        edge.points.extend(points.skip(skip));
+        edge.object_ids.push(segment.object_id);
+        edge.end = if forward { segment.end } else { segment.start };
+
+        if edge.end == start || !is_through(edge.end) {
+            break;
+        }
+        match incident[edge.end].iter().find(|s| !visited[**s]) {
+            Some(next) => current = *next,
+            None => break,
+        }
+    }
+    edge
+}
+
+impl OPRW {
+    #[must_use]
+    pub fn road_graph(&self) -> RoadGraph {
+        RoadGraph::from_oprw(self)
+    }
+}
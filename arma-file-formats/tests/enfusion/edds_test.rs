@@ -129,3 +129,19 @@ fn eden_1337_supertexture_test() {
         image::ColorType::Rgba8,
     );
 }
+
+#[cfg(feature = "image")]
+#[test]
+#[serial]
+fn edds_dynamic_image_test() {
+    let file = File::open(format!("{}prop_bc4.edds", INPUT_PATH_PREFIX)).unwrap();
+    let edds = Edds::from(&mut BufReader::new(file)).unwrap();
+
+    let img = image::DynamicImage::try_from(&edds).unwrap();
+    assert_eq!(img.color(), image::ColorType::L8);
+    assert_eq!((img.width(), img.height()), (1024, 2048));
+
+    let smallest = edds.mipmap_to_image(0).unwrap();
+    assert_eq!((smallest.width(), smallest.height()), (1, 1));
+    assert!(edds.mipmap_to_image(12).is_err());
+}
@@ -1,6 +1,23 @@
-use std::fs;
+use std::{collections::HashMap, f32::consts::FRAC_PI_2, fs, io::Cursor};
 
-use arma_file_formats::real_virtuality::p3d::ODOL;
+use arma_file_formats::{
+    errors::{AffError, OdolError},
+    real_virtuality::{
+        p3d::{
+            AnimAddress, AnimBones, AnimType, AnimationClass, AnimationRTPair, AnimationRTWeight,
+            Animations, Anims2Bones, Bone, BoneLink, Bones2Anims, CompressedVertexIndexArray, Gltf,
+            GltfOptions, Lod, LodFace, LodMaterial, LodNameSelection, LodNamedProperty, LodSection,
+            MlodLod, MlodNamedSelection, ModelDependencies, ModelIssue, ModelReport,
+            ModelReportOptions, ODOLOptions, Obj, ObjGrouping, ObjOptions, OdolLazyReader,
+            PixelShaderID, Proxy, Resolution, ResolutionEnum, Rvmat, SkeletonTree, StageTexture,
+            StageTransform, TextureFilterType, UVSet, UVSource, VertexShaderID, VertexShaderIDEnum,
+            MLOD, ODOL, ODOL_MAX_VERSION, ODOL_MIN_MODERN_VERSION, ODOL_MIN_VERSION,
+            ODOL_MIN_WRITE_VERSION,
+        },
+        rap::Cfg,
+        types::{TransformMatrix, XYZTriplet},
+    },
+};
 use serial_test::serial;
 
 const INPUT_PATH_PREFIX: &str = "./tests/real_virtuality/test-data/p3d_in/";
@@ -74,3 +91,1562 @@ fn test_all() {
         }
     });
 }
+
+fn push_tagg(buf: &mut Vec<u8>, name: &str, data: &[u8]) {
+    buf.push(1);
+    buf.extend_from_slice(name.as_bytes());
+    buf.push(0);
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(data);
+}
+
+fn property_data(name: &str, value: &str) -> Vec<u8> {
+    let mut data = vec![0_u8; 128];
+    data[..name.len()].copy_from_slice(name.as_bytes());
+    data[64..64 + value.len()].copy_from_slice(value.as_bytes());
+    data
+}
+
+fn floats(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn ints(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// Single triangle geometry LOD, laid out like Object Builder writes it.
+fn mlod_triangle() -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(b"MLOD");
+    buf.extend_from_slice(&ints(&[0x101, 1]));
+
+    buf.extend_from_slice(b"P3DM");
+    buf.extend_from_slice(&ints(&[0x1C, 0x100, 3, 1, 1, 0]));
+    for (point, flags) in [
+        ([0.0, 0.0, 0.0], 0),
+        ([1.0, 0.0, 0.0], 0),
+        ([0.0, 1.0, 0.0], 0x100),
+    ] {
+        buf.extend_from_slice(&floats(&point));
+        buf.extend_from_slice(&ints(&[flags]));
+    }
+    buf.extend_from_slice(&floats(&[0.0, 0.0, -1.0]));
+
+    buf.extend_from_slice(&ints(&[3]));
+    for (point, u, v) in [(0, 0.0, 0.0), (1, 1.0, 0.0), (2, 0.0, 1.0), (0, 0.0, 0.0)] {
+        buf.extend_from_slice(&ints(&[point, 0]));
+        buf.extend_from_slice(&floats(&[u, v]));
+    }
+    buf.extend_from_slice(&ints(&[0]));
+    buf.extend_from_slice(b"data\\tri_co.paa\0data\\tri.rvmat\0");
+
+    buf.extend_from_slice(b"TAGG");
+    push_tagg(
+        &mut buf,
+        "#UVSet#",
+        &[ints(&[0]), floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0])].concat(),
+    );
+    push_tagg(&mut buf, "#Lock#", &[0, 0, 0, 0]);
+    push_tagg(&mut buf, "component01", &[1, 128, 0, 1]);
+    push_tagg(&mut buf, "#Property#", &property_data("class", "house"));
+    push_tagg(&mut buf, "#Mass#", &floats(&[10.0, 20.0, 30.0]));
+    push_tagg(&mut buf, "#SharpEdges#", &ints(&[0, 1]));
+    push_tagg(&mut buf, "#EndOfFile#", &[]);
+    buf.extend_from_slice(&floats(&[1e13]));
+
+    buf
+}
+
+#[test]
+fn mlod_read_test() {
+    let mlod = MLOD::from_stream(&mut Cursor::new(mlod_triangle())).unwrap();
+    assert_eq!(mlod.lods.len(), 1);
+
+    let lod = &mlod.lods[0];
+    assert_eq!(lod.resolution.res, ResolutionEnum::Geometry);
+    assert_eq!(lod.points.len(), 3);
+    assert_eq!(lod.points[1].position.x, 1.0);
+    assert_eq!(lod.points[2].flags, 0x100);
+    assert_eq!(lod.normals[0].z, -1.0);
+
+    let face = &lod.faces[0];
+    assert_eq!(face.vertices.len(), 3);
+    assert_eq!(face.vertices[2].point_index, 2);
+    assert_eq!(face.vertices[1].u, 1.0);
+    assert_eq!(face.texture.to_string(), "data\\tri_co.paa");
+    assert_eq!(face.material.to_string(), "data\\tri.rvmat");
+
+    let selection = lod.named_selection("Component01").unwrap();
+    assert!(selection.contains_point(0));
+    assert!(!selection.contains_point(2));
+    assert!(selection.contains_face(0));
+    assert_eq!(selection.point_weight(0), 1.0);
+    assert!((selection.point_weight(1) - 128.0 / 255.0).abs() < 1e-6);
+    assert_eq!(
+        MlodNamedSelection::encode_weight(selection.point_weight(1)),
+        128
+    );
+
+    assert_eq!(lod.named_property("Class").as_deref(), Some("house"));
+    assert_eq!(lod.taggs.mass.as_deref(), Some(&[10.0, 20.0, 30.0][..]));
+    assert_eq!(lod.taggs.sharp_edges, vec![(0, 1)]);
+    assert_eq!(lod.taggs.uv_sets[0].uvs.len(), 3);
+    assert_eq!(lod.taggs.other[0].name.to_string(), "#Lock#");
+}
+
+#[test]
+fn mlod_invalid_test() {
+    let mut data = mlod_triangle();
+    data[4] = 0x02;
+    assert!(MLOD::from_stream(&mut Cursor::new(data)).is_err());
+    assert!(MLOD::from_stream(&mut Cursor::new(b"ODOL\x01\0\0\0")).is_err());
+
+    let mut mlod = MLOD::from_stream(&mut Cursor::new(mlod_triangle())).unwrap();
+    let face = &mut mlod.lods[0].faces[0];
+    face.vertices.extend([face.vertices[0], face.vertices[1]]);
+    assert!(mlod.write_data().is_err());
+    mlod.lods[0].faces[0].vertices.truncate(2);
+    assert!(mlod.write_data().is_err());
+}
+
+#[test]
+#[should_panic(expected = "Invalid MLOD face vertex count 2")]
+fn mlod_add_face_invalid_test() {
+    let mut lod = MlodLod::new(0.0);
+    let a = lod.add_point(XYZTriplet::default());
+    lod.add_face(&[(a, 0.0, 0.0); 2], "", "");
+}
+
+#[test]
+fn mlod_roundtrip_test() {
+    let data = mlod_triangle();
+    let mlod = MLOD::from_stream(&mut Cursor::new(&data)).unwrap();
+    assert_eq!(mlod.write_data().unwrap(), data);
+}
+
+#[test]
+fn mlod_generate_test() {
+    let mut visual = MlodLod::new(0.0);
+    let a = visual.add_point(XYZTriplet::default());
+    let b = visual.add_point(XYZTriplet {
+        x: 1.0,
+        ..Default::default()
+    });
+    let c = visual.add_point(XYZTriplet {
+        y: 1.0,
+        ..Default::default()
+    });
+    let face = visual.add_face(
+        &[(a, 0.0, 0.0), (b, 1.0, 0.0), (c, 0.0, 1.0)],
+        "data\\old_co.paa",
+        "data\\old.rvmat",
+    );
+    visual.add_named_selection("door", &[a, b], &[face]);
+    let proxy = visual.add_proxy(
+        "\\a3\\data_f\\proxies\\crew.p3d",
+        XYZTriplet {
+            z: 1.0,
+            ..Default::default()
+        },
+    );
+    assert_eq!(proxy, "proxy:\\a3\\data_f\\proxies\\crew.001");
+    assert!(visual.rename_named_selection("DOOR", "door_1"));
+
+    let mut geometry = MlodLod::new(1e13);
+    geometry.taggs.mass = Some(Vec::new());
+    let component = geometry.add_box(
+        XYZTriplet {
+            x: -1.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        XYZTriplet {
+            x: 1.0,
+            y: 2.0,
+            z: 1.0,
+        },
+    );
+    assert_eq!(component, "Component01");
+    geometry.set_named_property("class", "house");
+    geometry.taggs.mass = Some(vec![12.5; geometry.points.len()]);
+
+    // Box normals point away from the center
+    for face in &geometry.faces {
+        let normal = geometry.normals[face.vertices[0].normal_index as usize];
+        let p = geometry.points[face.vertices[0].point_index as usize].position;
+        assert!(normal.x * p.x + normal.y * (p.y - 1.0) + normal.z * p.z > 0.0);
+    }
+
+    let mut mlod = MLOD::new();
+    mlod.lods = vec![visual, geometry];
+    assert_eq!(
+        mlod.replace_texture("DATA\\old_co.paa", "data\\new_co.paa"),
+        1
+    );
+
+    let data = mlod.write_data().unwrap();
+    let read = MLOD::from_stream(&mut Cursor::new(&data)).unwrap();
+    assert_eq!(read, mlod);
+
+    let visual = &read.lods[0];
+    assert_eq!(visual.faces[0].texture.to_string(), "data\\new_co.paa");
+    assert_eq!(visual.points.len(), 6);
+    assert!(visual.named_selection("door_1").unwrap().contains_face(0));
+    assert!(!visual.named_selection("door_1").unwrap().contains_face(1));
+    assert!(visual.named_selection(&proxy).unwrap().contains_face(1));
+
+    let geometry = read.lod(ResolutionEnum::Geometry).unwrap();
+    assert_eq!(geometry.faces.len(), 6);
+    assert_eq!(geometry.named_property("class").as_deref(), Some("house"));
+    assert_eq!(
+        geometry.named_selection("component01").unwrap().points,
+        vec![1; 8]
+    );
+}
+
+/// Quad and triangle in two sections, skinned to a two bone skeleton.
+fn odol_quad_and_triangle() -> ODOL {
+    let mut lod = Lod::default();
+    lod.vertices = [
+        (0.0, 0.0, 0.0),
+        (1.0, 0.0, 0.0),
+        (1.0, 1.0, 0.0),
+        (0.0, 1.0, 0.0),
+        (2.0, 0.0, 0.0),
+    ]
+    .into_iter()
+    .map(|(x, y, z)| XYZTriplet { x, y, z })
+    .collect();
+    lod.normals = vec![
+        XYZTriplet {
+            z: -1.0,
+            ..Default::default()
+        };
+        5
+    ];
+    lod.default_uv_set.uv_data = Some(
+        [0.0_f32, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 2.0, 0.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect(),
+    );
+    lod.faces = vec![
+        LodFace {
+            face_type: 4,
+            vertex_indices: vec![0, 1, 2, 3],
+        },
+        LodFace {
+            face_type: 3,
+            vertex_indices: vec![1, 4, 2],
+        },
+    ];
+    lod.textures = vec![
+        "data\\wall_co.paa".into(),
+        "#(argb,8,8,3)color(1,0,0,1)".into(),
+    ];
+    // Section bounds are offsets, (4 + 1) * 4 bytes for the quad and (3 + 1) * 4 for the triangle
+    lod.sections = vec![
+        LodSection {
+            face_lower_index: 0,
+            face_upper_index: 20,
+            common_texture_index: 0,
+            material_index: -1,
+            ..Default::default()
+        },
+        LodSection {
+            face_lower_index: 20,
+            face_upper_index: 36,
+            common_texture_index: 1,
+            material_index: -1,
+            ..Default::default()
+        },
+    ];
+    lod.lod_items = vec![1, 0];
+    lod.vertex_bone_ref = (0..5)
+        .map(|i| AnimationRTWeight {
+            small_count: 1,
+            animation_rt_pairs: vec![AnimationRTPair {
+                selection_index: u8::from(i == 4),
+                weight: 255,
+            }],
+            ..Default::default()
+        })
+        .collect();
+
+    let mut odol = ODOL::new();
+    odol.resolutions = vec![Resolution::from_value(1.0)];
+    odol.lods = vec![lod];
+    odol.model_info.skeleton.skeleton_bones = vec![
+        Bone {
+            bone_name: "body".into(),
+            bone_parent: "".into(),
+        },
+        Bone {
+            bone_name: "door".into(),
+            bone_parent: "Body".into(),
+        },
+    ];
+    odol
+}
+
+#[test]
+fn gltf_export_test() {
+    let odol = odol_quad_and_triangle();
+    let options = GltfOptions {
+        texture_extension: Some("png".to_string()),
+        ..Default::default()
+    };
+    let gltf = Gltf::from_odol(&odol, &options).unwrap();
+    let json = gltf.to_json();
+
+    // Positions, normals, uvs, joints, weights and two index buffers
+    assert_eq!(
+        gltf.buffer.len(),
+        5 * 12 + 5 * 12 + 5 * 8 + 5 * 8 + 5 * 16 + 6 * 4 + 3 * 4
+    );
+    assert!(json.contains(r#""type":"SCALAR""#));
+    assert!(json.contains(r#""count":6"#));
+    assert!(json.contains(r#""count":3"#));
+    assert!(json.contains(r#""min":[-2,0,0],"max":[0,1,0]"#));
+    assert!(json.contains(r#""images":[{"uri":"data/wall_co.png"}]"#));
+    assert!(json.contains(r#""name":"door""#));
+    assert!(json.contains(r#""joints":[0,1]"#));
+    assert!(json.contains(r#""children":[1]"#));
+    assert!(json.contains(r#""JOINTS_0""#));
+    assert!(json.contains(r#""TEXCOORD_0""#));
+    // Procedural texture gets a material without texture
+    assert_eq!(json.matches(r#""baseColorTexture""#).count(), 1);
+
+    let glb = gltf.to_glb();
+    assert_eq!(&glb[..4], b"glTF");
+    assert_eq!(
+        u32::from_le_bytes([glb[8], glb[9], glb[10], glb[11]]) as usize,
+        glb.len()
+    );
+    assert_eq!(glb.len() % 4, 0);
+
+    let embedded = gltf.to_gltf_embedded();
+    assert!(embedded.contains(r#""uri":"data:application/octet-stream;base64,AAAAgAAAAAAAAAAA"#));
+
+    assert!(Gltf::from_lod(&odol, 1, &options).is_err());
+}
+
+#[test]
+fn obj_export_test() {
+    let mut odol = odol_quad_and_triangle();
+    let mut door = LodNameSelection::default();
+    door.name = "door".into();
+    door.selected_faces = CompressedVertexIndexArray { edges: vec![1] };
+    odol.lods[0].named_selection = vec![door];
+
+    let obj = Obj::from_odol(&odol, &ObjOptions::default());
+    let text = obj.obj(Some("model.mtl"));
+    assert!(text.starts_with("mtllib model.mtl\no 1.000\n"));
+    assert_eq!(text.lines().filter(|l| l.starts_with("v ")).count(), 5);
+    assert!(text.contains("\nv -2 0 0\n"));
+    assert!(text.contains("\nvt 1 0\n"));
+    assert!(text.contains("\nvn -0 0 -1\n"));
+    // Quads stay quads
+    assert!(text.contains("\nf 1/1/1 2/2/2 3/3/3 4/4/4\n"));
+    assert!(text.contains("\nf 2/2/2 5/5/5 3/3/3\n"));
+    assert_eq!(text.matches("usemtl ").count(), 2);
+    assert!(!text.contains("\ng "));
+    assert!(obj
+        .mtl
+        .contains("newmtl mat0_wall_co\nKd 1 1 1\nmap_Kd data/wall_co.paa\n"));
+    assert_eq!(obj.mtl.matches("map_Kd").count(), 1);
+
+    let options = ObjOptions {
+        grouping: ObjGrouping::NamedSelection,
+        texture_extension: Some("png".to_string()),
+        ..Default::default()
+    };
+    let obj = Obj::from_odol(&odol, &options);
+    let text = obj.obj(None);
+    assert!(text.contains("g default\nusemtl mat0_wall_co\nf 1/1/1"));
+    assert!(text.contains("g door\nusemtl mat1\nf 2/2/2"));
+    assert!(obj.mtl.contains("map_Kd data/wall_co.png"));
+
+    // Geometry LODs are only exported on request
+    let mlod = MLOD::from_stream(&mut Cursor::new(mlod_triangle())).unwrap();
+    assert!(Obj::from_mlod(&mlod, &ObjOptions::default())
+        .obj(None)
+        .is_empty());
+
+    let options = ObjOptions {
+        grouping: ObjGrouping::NamedSelection,
+        all_lods: true,
+        ..Default::default()
+    };
+    let obj = Obj::from_mlod(&mlod, &options);
+    let text = obj.obj(None);
+    assert!(text.starts_with("o Geometry\n"));
+    assert!(text.contains("\nvt 0 1\nvt 1 1\nvt 0 0\n"));
+    assert!(text.contains("g component01\nusemtl mat0_tri_co\nf 1/1/1 2/2/1 3/3/1\n"));
+    assert!(obj.mtl.contains("# rvmat data\\tri.rvmat\n"));
+}
+
+#[test]
+fn material_export_test() {
+    let mut odol = odol_quad_and_triangle();
+    let mut material = LodMaterial::default();
+    material.material_name = "data\\wall.rvmat".into();
+    material.diffuse.r = 0.5;
+    material.specular_power = 40.0;
+    material.stage_textures = vec![
+        StageTexture {
+            texture: "data\\wall_nohq.paa".into(),
+            ..Default::default()
+        },
+        StageTexture {
+            texture: "data\\wall_smdi.paa".into(),
+            ..Default::default()
+        },
+    ];
+    odol.lods[0].materials = vec![material];
+    odol.lods[0].sections[0].material_index = 0;
+
+    let options = GltfOptions {
+        texture_extension: Some("png".to_string()),
+        ..Default::default()
+    };
+    let json = Gltf::from_odol(&odol, &options).unwrap().to_json();
+    assert!(json.contains(r#""images":[{"uri":"data/wall_co.png"},{"uri":"data/wall_nohq.png"}]"#));
+    assert!(json.contains(r#""normalTexture":{"index":1}"#));
+    assert!(json.contains(r#""specularTexture":"data\\wall_smdi.paa""#));
+    assert!(json.contains(r#""baseColorFactor":[0.5,"#));
+
+    let obj = Obj::from_odol(&odol, &ObjOptions::default());
+    assert!(obj.mtl.contains("newmtl mat0_wall_co\nKd 0.5 "));
+    assert!(obj.mtl.contains("\nNs 40\n# rvmat data\\wall.rvmat\n"));
+    assert!(obj.mtl.contains("\nmap_Bump data/wall_nohq.paa\n"));
+    assert!(obj.mtl.contains("\nmap_Ks data/wall_smdi.paa\n"));
+    // Sections without a material keep the plain white one
+    assert!(obj.mtl.contains("newmtl mat1\nKd 1 1 1\n"));
+}
+
+#[test]
+fn lod_helpers_test() {
+    let mut odol = odol_quad_and_triangle();
+    let lod = &mut odol.lods[0];
+
+    let mut uv_set = UVSet::default();
+    uv_set.is_discretized = true;
+    uv_set.min_u = Some(0.0);
+    uv_set.min_v = Some(-1.0);
+    uv_set.max_u = Some(2.0);
+    uv_set.max_v = Some(1.0);
+    uv_set.default_fill = true;
+    uv_set.default_value = Some([(-32767_i16).to_le_bytes(), 32767_i16.to_le_bytes()].concat());
+    lod.uv_sets = vec![uv_set];
+
+    let sections = lod.section_indices();
+    assert_eq!(sections.len(), 2);
+    assert_eq!(sections[0].1, vec![0, 1, 2, 0, 2, 3]);
+    assert_eq!(sections[1].1, vec![1, 4, 2]);
+    assert_eq!(sections[1].0.unwrap().common_texture_index, 1);
+
+    assert_eq!(lod.uv_set_count(), 2);
+    assert_eq!(lod.uv_coords(0).unwrap()[4], [2.0, 0.0]);
+    assert_eq!(lod.uv_coords(1).unwrap(), vec![[0.0, 1.0]; 5]);
+    assert!(lod.uv_coords(2).is_none());
+
+    let vertices: Vec<_> = lod.vertices_iter().collect();
+    assert_eq!(vertices.len(), 5);
+    assert_eq!(
+        vertices[2].position,
+        XYZTriplet {
+            x: 1.0,
+            y: 1.0,
+            z: 0.0
+        }
+    );
+    assert_eq!(vertices[2].normal.unwrap().z, -1.0);
+    assert_eq!(vertices[2].uvs, vec![[1.0, 1.0], [0.0, 1.0]]);
+    assert_eq!(vertices[4].bone_weights[0].selection_index, 1);
+
+    lod.normals.clear();
+    lod.sections.clear();
+    assert!(lod.vertices_iter().all(|v| v.normal.is_none()));
+    assert_eq!(lod.section_indices()[0].1.len(), 9);
+}
+
+#[test]
+fn odol_read_lod_errors_test() {
+    let odol = odol_quad_and_triangle();
+    let mut cursor = Cursor::new(Vec::new());
+
+    let geometry = Resolution::from_value(1e13);
+    assert!(matches!(
+        odol.read_lod(&mut cursor, geometry),
+        Err(AffError::OdolError(OdolError::ResolutionNotFound(res))) if res == geometry
+    ));
+    assert!(matches!(
+        odol.read_lod(&mut cursor, Resolution::from_value(1.0)),
+        Err(AffError::LodNotFound(0))
+    ));
+
+    assert!(OdolLazyReader::from_reader(Cursor::new(b"MLOD\0\0\0\0".to_vec())).is_err());
+}
+
+#[test]
+fn odol_lazy_read_test() {
+    let mut odol = odol_quad_and_triangle();
+    odol.version = ODOL_MAX_VERSION;
+    for (value, texture) in [(2.0, "data\\lod2_co.paa"), (1e13, "data\\geometry_co.paa")] {
+        let mut lod = odol.lods[0].clone();
+        lod.textures[0] = texture.into();
+        odol.lods.push(lod);
+        odol.resolutions.push(Resolution::from_value(value));
+    }
+
+    // Models inside a pbo do not start at the beginning of the stream
+    let mut data = vec![0xAA; 16];
+    data.extend(odol.write_data().unwrap());
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(16);
+
+    let read = ODOL::from_stream(&mut cursor).unwrap();
+    assert_eq!(read.lods.len(), 3);
+    assert_eq!(read.lods[1].textures, odol.lods[1].textures);
+
+    cursor.set_position(16);
+    let lod = read
+        .read_lod(&mut cursor, Resolution::from_value(1e13))
+        .unwrap();
+    assert_eq!(lod.textures, odol.lods[2].textures);
+
+    cursor.set_position(16);
+    let mut lazy = OdolLazyReader::from_reader(cursor).unwrap();
+    assert!(lazy.odol.lods.is_empty());
+    for (i, value) in [2.0, 1.0, 2.0].into_iter().enumerate() {
+        let lod = lazy.read_lod(Resolution::from_value(value)).unwrap();
+        assert_eq!(lod.textures, odol.lods[(i + 1) % 2].textures);
+    }
+    assert_eq!(lazy.lods.len(), 2);
+    assert!(lazy.read_lod(Resolution::from_value(3.0)).is_err());
+}
+
+fn lzss_literals(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for chunk in data.chunks(8) {
+        out.push(0xFF);
+        out.extend_from_slice(chunk);
+    }
+    let checksum = data
+        .iter()
+        .fold(0_i32, |sum, byte| sum.wrapping_add(i32::from(*byte)));
+    out.extend(checksum.to_le_bytes());
+    out
+}
+
+#[test]
+fn odol_lazy_lzss_read_test() {
+    let mut odol = odol_quad_and_triangle();
+    odol.version = ODOL_MAX_VERSION;
+    for (value, texture) in [(2.0, "data\\lod2_co.paa"), (1e13, "data\\geometry_co.paa")] {
+        let mut lod = odol.lods[0].clone();
+        lod.textures[0] = texture.into();
+        odol.lods.push(lod);
+        odol.resolutions.push(Resolution::from_value(value));
+    }
+
+    let data = lzss_literals(&odol.write_data().unwrap());
+    let mut lazy = OdolLazyReader::from_reader(Cursor::new(data)).unwrap();
+    assert!(lazy.odol.lods.is_empty());
+    assert_eq!(lazy.odol.resolutions.len(), 3);
+    for value in [1e13, 1.0, 1e13, 2.0] {
+        let index = lazy.odol.lod_index(Resolution::from_value(value)).unwrap();
+        let lod = lazy.read_lod(Resolution::from_value(value)).unwrap();
+        assert_eq!(lod.textures, odol.lods[index].textures);
+    }
+    assert_eq!(lazy.lods.len(), 3);
+    assert!(lazy.read_lod(Resolution::from_value(3.0)).is_err());
+}
+
+/// Quad and triangle LOD in the legacy layout, followed by a geometry LOD with LZSS compressed vertices.
+fn legacy_odol_quad_and_triangle(version: u32) -> Vec<u8> {
+    fn u32s(data: &mut Vec<u8>, values: &[u32]) {
+        data.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+    }
+    fn f32s(data: &mut Vec<u8>, values: &[f32]) {
+        data.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+    }
+    fn u16s(data: &mut Vec<u8>, values: &[u16]) {
+        u32s(data, &[values.len() as u32]);
+        data.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+    }
+    fn string(data: &mut Vec<u8>, value: &str) {
+        data.extend(value.as_bytes());
+        data.push(0);
+    }
+    fn face(data: &mut Vec<u8>, texture: i16, indices: &[u16]) {
+        u32s(data, &[0]);
+        data.extend(texture.to_le_bytes());
+        data.push(indices.len() as u8);
+        data.extend(indices.iter().flat_map(|v| v.to_le_bytes()));
+    }
+    fn section(data: &mut Vec<u8>, faces: [u32; 2], texture: i16) {
+        u32s(data, &[faces[0], faces[1], 0]);
+        data.extend(texture.to_le_bytes());
+        u32s(data, &[0]);
+    }
+
+    let mut data = b"ODOL".to_vec();
+    u32s(&mut data, &[version, 2]);
+
+    // Point flags, UVs, vertices and normals
+    u32s(&mut data, &[5, 0, 0, 0, 0, 0, 5]);
+    f32s(
+        &mut data,
+        &[0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 2.0, 0.0],
+    );
+    u32s(&mut data, &[5]);
+    f32s(
+        &mut data,
+        &[
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0,
+        ],
+    );
+    u32s(&mut data, &[5]);
+    data.extend(
+        (0..5)
+            .flat_map(|_| [0.0_f32, 0.0, -1.0])
+            .flat_map(f32::to_le_bytes),
+    );
+    // Hints and bounds
+    u32s(&mut data, &[0, 0]);
+    f32s(
+        &mut data,
+        &[0.0, 0.0, 0.0, 2.0, 1.0, 0.0, 1.0, 0.5, 0.0, 1.2],
+    );
+    u32s(&mut data, &[2]);
+    string(&mut data, "data\\wall_co.paa");
+    string(&mut data, "#(argb,8,8,3)color(1,0,0,1)");
+    // Edges
+    u32s(&mut data, &[0, 0]);
+    u32s(&mut data, &[2, 0]);
+    face(&mut data, 0, &[0, 1, 2, 3]);
+    face(&mut data, 1, &[1, 4, 2]);
+    u32s(&mut data, &[2]);
+    section(&mut data, [0, 1], 0);
+    section(&mut data, [1, 2], 1);
+    u32s(&mut data, &[1]);
+    string(&mut data, "door");
+    u16s(&mut data, &[0]);
+    u32s(&mut data, &[0]);
+    data.push(0);
+    u32s(&mut data, &[0]);
+    u16s(&mut data, &[0, 1, 2, 3]);
+    u32s(&mut data, &[4]);
+    data.extend([255, 255, 128, 0]);
+    u32s(&mut data, &[1]);
+    string(&mut data, "autocenter");
+    string(&mut data, "0");
+    // Frames, colors and special
+    u32s(&mut data, &[0, 0, 0, 0]);
+    u32s(&mut data, &[1]);
+    string(&mut data, "\\ca\\data\\proxy_gunner");
+    f32s(
+        &mut data,
+        &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 2.0, 3.0],
+    );
+    u32s(&mut data, &[0, 0]);
+
+    // Geometry LOD with 1200 bytes of vertices
+    u32s(&mut data, &[0, 0, 100]);
+    let vertices: Vec<u8> = (0..300_u16)
+        .flat_map(|v| f32::from(v).to_le_bytes())
+        .collect();
+    data.extend(lzss_literals(&vertices));
+    u32s(&mut data, &[0, 0, 0]);
+    f32s(&mut data, &[0.0; 10]);
+    u32s(&mut data, &[0; 13]);
+
+    // Resolutions and model info
+    f32s(&mut data, &[1.0, 1e13]);
+    u32s(&mut data, &[0]);
+    f32s(&mut data, &[2.5, 1.5]);
+    u32s(&mut data, &[0, 0, 0]);
+    f32s(&mut data, &[0.0, 0.5, 0.0]);
+    u32s(&mut data, &[0, 0]);
+    f32s(&mut data, &[1.0; 25]);
+    data.extend([1, 0, 1, 1, 0]);
+    u32s(&mut data, &[0]);
+    f32s(&mut data, &[1500.0, 1.0 / 1500.0, 0.0, 0.0]);
+    data.extend([255, 1, 255, 255, 255, 255, 0, 255, 255, 255, 255, 255]);
+    data
+}
+
+#[test]
+fn odol_legacy_read_test() {
+    let data = legacy_odol_quad_and_triangle(ODOL_MIN_VERSION);
+    let odol = ODOL::from_stream(&mut Cursor::new(data.clone())).unwrap();
+    assert_eq!(odol.lods.len(), 2);
+    assert_eq!(
+        odol.resolutions,
+        vec![Resolution::from_value(1.0), Resolution::from_value(1e13)]
+    );
+    assert_eq!(odol.model_info.mass, 1500.0);
+    assert_eq!(odol.model_info.mem_lod_sphere, 2.5);
+    assert!(odol.model_info.auto_center && !odol.model_info.animated);
+    assert_eq!(odol.model_info.geometry, 1);
+
+    let lod = &odol.lods[0];
+    assert_eq!(lod.b_radius, 1.2);
+    assert_eq!(lod.uv_coords(0).unwrap()[4], [2.0, 0.0]);
+    assert_eq!(lod.normals.len(), 5);
+    assert_eq!(lod.named_property("autocenter").as_deref(), Some("0"));
+
+    // Face ranges of the sections are converted to face data offsets
+    let sections = lod.section_faces();
+    assert_eq!(sections.len(), 2);
+    assert_eq!(sections[0].1, vec![0]);
+    assert_eq!(sections[1].1, vec![1]);
+    assert_eq!(sections[1].0.unwrap().common_texture_index, 1);
+    assert_eq!(lod.triangulate(&[1]), vec![1, 4, 2]);
+
+    let selection = lod.selection("door").unwrap();
+    assert!(selection.contains_face(0));
+    assert_eq!(selection.vertex_weight(2), 128.0 / 255.0);
+
+    let proxies = lod.proxies();
+    assert_eq!(proxies[0].model, "\\ca\\data\\proxy_gunner");
+    assert_eq!(proxies[0].selection.as_deref(), Some("door"));
+    assert_eq!(proxies[0].bone, None);
+    assert_eq!(proxies[0].position(), XYZTriplet::new(1.0, 2.0, 3.0));
+
+    assert_eq!(odol.lods[1].vertices.len(), 100);
+    assert_eq!(
+        odol.lods[1].vertices[99],
+        XYZTriplet::new(297.0, 298.0, 299.0)
+    );
+
+    let mut lazy = OdolLazyReader::from_reader(Cursor::new(data)).unwrap();
+    assert!(lazy.odol.lods.is_empty());
+    let geometry = lazy.read_lod(Resolution::from_value(1e13)).unwrap();
+    assert_eq!(geometry.vertices, odol.lods[1].vertices);
+    let lod = lazy.read_lod(Resolution::from_value(1.0)).unwrap();
+    assert_eq!(lod.faces, odol.lods[0].faces);
+    assert_eq!(lazy.lods.len(), 2);
+
+    // Writing needs the LZO compression of newer versions
+    assert!(matches!(
+        odol.write_data(),
+        Err(AffError::OdolError(OdolError::UnsupportedVersion(7)))
+    ));
+}
+
+#[test]
+fn odol_version_matrix_test() {
+    let header = |version: u32| [b"ODOL".as_slice(), &version.to_le_bytes()].concat();
+    let newer = ODOLOptions {
+        allow_newer_versions: true,
+        ..Default::default()
+    };
+
+    for version in [1, 6, 8, 19] {
+        assert!(matches!(
+            ODOL::from_stream(&mut Cursor::new(header(version))),
+            Err(AffError::OdolError(OdolError::UnsupportedVersion(v))) if v == version
+        ));
+        assert!(!ODOL::is_supported_version(version, newer));
+    }
+
+    // Known versions get past the version check and fail on the truncated header
+    for version in [
+        ODOL_MIN_VERSION,
+        20,
+        ODOL_MIN_MODERN_VERSION,
+        40,
+        54,
+        68,
+        ODOL_MAX_VERSION,
+    ] {
+        assert!(ODOL::is_supported_version(version, ODOLOptions::default()));
+        assert!(matches!(
+            ODOL::from_stream(&mut Cursor::new(header(version))),
+            Err(AffError::BinrwError(_))
+        ));
+    }
+
+    // Full bodies of the legacy layout
+    for version in [ODOL_MIN_VERSION, 20, 27] {
+        let data = legacy_odol_quad_and_triangle(version);
+        let read = ODOL::from_stream(&mut Cursor::new(data)).unwrap();
+        assert_eq!(read.version, version);
+        assert_eq!(read.resolutions.len(), 2);
+        assert_eq!(read.lods[0].vertices.len(), 5);
+        assert_eq!(read.lods[0].faces.len(), 2);
+        assert_eq!(read.lods[0].textures.len(), 2);
+        assert_eq!(read.lods[0].sections.len(), 2);
+    }
+
+    // Full bodies for every layout change of the writable versions
+    for version in [
+        ODOL_MIN_WRITE_VERSION,
+        45,
+        50,
+        51,
+        58,
+        59,
+        64,
+        67,
+        68,
+        69,
+        ODOL_MAX_VERSION,
+    ] {
+        let mut odol = odol_quad_and_triangle();
+        odol.version = version;
+        let read = ODOL::from_stream(&mut Cursor::new(odol.write_data().unwrap())).unwrap();
+        assert_eq!(read.version, version);
+        assert_eq!(read.resolutions, odol.resolutions);
+        assert_eq!(read.lods[0].vertices, odol.lods[0].vertices);
+        assert_eq!(read.lods[0].faces, odol.lods[0].faces);
+        assert_eq!(read.lods[0].textures, odol.lods[0].textures);
+        assert_eq!(read.lods[0].sections.len(), 2);
+    }
+
+    for version in [ODOL_MAX_VERSION + 1, 75, 100] {
+        assert!(matches!(
+            ODOL::from_stream(&mut Cursor::new(header(version))),
+            Err(AffError::OdolError(OdolError::UnknownVersion(v))) if v == version
+        ));
+        assert!(matches!(
+            ODOL::from_stream_with_options(&mut Cursor::new(header(version)), newer),
+            Err(AffError::BinrwError(_))
+        ));
+    }
+}
+
+#[test]
+fn odol_write_test() {
+    let mut odol = odol_quad_and_triangle();
+    odol.version = ODOL_MAX_VERSION;
+    odol.model_info.skeleton.name = "door_skeleton".into();
+    odol.lods[0].proxies = vec![Proxy {
+        proxy_model: "\\a3\\data_f\\proxies\\gunner".into(),
+        transofrmation: TransformMatrix::translation(XYZTriplet::new(1.0, 2.0, 3.0)),
+        named_selection_index: 0,
+        ..Default::default()
+    }];
+    let mut selection = LodNameSelection::default();
+    selection.name = "door".into();
+    selection.selected_vertices.edges = vec![4];
+    selection.selected_vertices_weights = vec![255];
+    odol.lods[0].named_selection = vec![selection];
+
+    // Large enough to be LZO compressed
+    let mut geometry = odol.lods[0].clone();
+    geometry.vertices = (0..200)
+        .map(|i| XYZTriplet::new(i as f32, 0.5, -1.0))
+        .collect();
+    geometry.normals = vec![XYZTriplet::new(0.0, 1.0, 0.0); 200];
+    geometry.default_uv_set.uv_data = Some(Vec::new());
+    geometry.vertex_bone_ref.clear();
+    odol.resolutions.push(Resolution::from_value(1e13));
+    odol.lods.push(geometry);
+    odol.model_info.geometry = 1;
+
+    odol.animations = Some(Animations {
+        animation_classes: vec![
+            AnimationClass {
+                anim_transform_type: AnimType::RotationX,
+                anim_class_name: "door_open".into(),
+                anim_source: "door".into(),
+                max_value: 1.0,
+                source_address: AnimAddress::AnimClamp,
+                angle_0: Some(0.0),
+                angle_1: Some(FRAC_PI_2),
+                ..Default::default()
+            },
+            AnimationClass {
+                anim_transform_type: AnimType::Hide,
+                anim_class_name: "door_hide".into(),
+                anim_source: "door".into(),
+                max_value: 1.0,
+                source_address: AnimAddress::AnimClamp,
+                ..Default::default()
+            },
+        ],
+        bones_2_anims: vec![Bones2Anims::default(); 2],
+        anims_2_bones: vec![
+            Anims2Bones {
+                animation_class_indices: vec![
+                    AnimBones {
+                        skeleton_bone_name_index: 1,
+                        axis_pos: Some(XYZTriplet::new(1.0, 0.0, 0.0)),
+                        axis_dir: Some(XYZTriplet::new(1.0, 0.0, 0.0)),
+                    },
+                    // Hide animations have no axis, it isn't written
+                    AnimBones {
+                        skeleton_bone_name_index: 1,
+                        axis_pos: Some(XYZTriplet::new(1.0, 0.0, 0.0)),
+                        axis_dir: None,
+                    },
+                ],
+            },
+            // Missing axes are written as zero, missing classes without a bone
+            Anims2Bones {
+                animation_class_indices: vec![AnimBones {
+                    skeleton_bone_name_index: 1,
+                    axis_pos: None,
+                    axis_dir: None,
+                }],
+            },
+        ],
+        ..Default::default()
+    });
+
+    let data = odol.write_data().unwrap();
+    let read = ODOL::from_stream(&mut Cursor::new(&data)).unwrap();
+
+    assert_eq!(read.version, ODOL_MAX_VERSION);
+    assert_eq!(read.resolutions, odol.resolutions);
+    assert_eq!(read.model_info.skeleton.skeleton_bones.len(), 2);
+    assert_eq!(read.model_info.geometry, 1);
+    assert_eq!(read.animations.as_ref().unwrap().animation_classes.len(), 2);
+    let bones = |index, axis: Option<XYZTriplet>| AnimBones {
+        skeleton_bone_name_index: index,
+        axis_pos: axis,
+        axis_dir: axis,
+    };
+    assert_eq!(
+        read.animations.as_ref().unwrap().anims_2_bones,
+        vec![
+            Anims2Bones {
+                animation_class_indices: vec![
+                    bones(1, Some(XYZTriplet::new(1.0, 0.0, 0.0))),
+                    bones(1, None),
+                ],
+            },
+            Anims2Bones {
+                animation_class_indices: vec![
+                    bones(1, Some(XYZTriplet::default())),
+                    bones(-1, None)
+                ],
+            },
+        ]
+    );
+    assert_eq!(read.start_address_of_lods.len(), 2);
+    assert_eq!(read.end_address_of_lods[1] as usize, data.len());
+    // Face count, face data size matching the section bounds, zero and the first face type
+    let faces_header = [&2_u32.to_le_bytes()[..], &36_u32.to_le_bytes(), &[0, 0, 4]].concat();
+    assert!(data.windows(faces_header.len()).any(|w| w == faces_header));
+
+    for (written, read) in odol.lods.iter().zip(&read.lods) {
+        assert_eq!(read.vertices, written.vertices);
+        assert_eq!(read.faces, written.faces);
+        assert_eq!(read.textures, written.textures);
+        assert_eq!(read.sections.len(), written.sections.len());
+        assert_eq!(read.proxies, written.proxies);
+        assert_eq!(read.named_selection, written.named_selection);
+        assert_eq!(read.lod_items, written.lod_items);
+        assert_eq!(read.default_uv_set.uv_data, written.default_uv_set.uv_data);
+        for (a, b) in read.normals.iter().zip(&written.normals) {
+            assert_close(*a, *b);
+        }
+        let pairs = |lod: &Lod| {
+            lod.vertex_bone_ref
+                .iter()
+                .map(|w| w.animation_rt_pairs.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pairs(read), pairs(written));
+    }
+
+    // Patching a texture and stripping a LOD
+    let mut patched = read;
+    patched.lods[0].textures[0] = "data\\wall_new_co.paa".into();
+    patched.remove_lod(0).unwrap();
+    assert_eq!(patched.model_info.geometry, 0);
+    let patched = ODOL::from_stream(&mut Cursor::new(patched.write_data().unwrap())).unwrap();
+    assert_eq!(patched.resolutions.len(), 1);
+    assert_eq!(patched.resolutions[0].res, ResolutionEnum::Geometry);
+    assert_eq!(patched.lods[0].vertices.len(), 200);
+    assert!(matches!(
+        ODOL::new().remove_lod(0),
+        Err(AffError::LodNotFound(0))
+    ));
+
+    odol.version = 43;
+    assert!(matches!(
+        odol.write_data(),
+        Err(AffError::OdolError(OdolError::UnsupportedVersion(43)))
+    ));
+    odol.version = ODOL_MAX_VERSION + 1;
+    assert!(matches!(
+        odol.write_data(),
+        Err(AffError::OdolError(OdolError::UnknownVersion(_)))
+    ));
+}
+
+#[test]
+fn lod_query_test() {
+    let mut lod = odol_quad_and_triangle().lods.remove(0);
+    let mut zasleh = LodNameSelection::default();
+    zasleh.name = "Zasleh".into();
+    zasleh.selected_faces.edges = vec![1];
+    zasleh.selected_vertices.edges = vec![1, 4, 2];
+    zasleh.selected_vertices_weights = vec![255, 255, 51];
+    let mut proxy_selection = LodNameSelection::default();
+    proxy_selection.name = "proxy:\\a3\\data_f\\proxies\\gunner.001".into();
+    proxy_selection.selected_vertices.edges = vec![0];
+    lod.named_selection = vec![zasleh, proxy_selection];
+    lod.proxies = vec![
+        Proxy {
+            proxy_model: "\\a3\\data_f\\proxies\\gunner".into(),
+            transofrmation: TransformMatrix::translation(XYZTriplet::new(1.0, 2.0, 3.0)),
+            named_selection_index: 1,
+            bone_index: 1,
+            ..Default::default()
+        },
+        Proxy {
+            named_selection_index: -1,
+            bone_index: -1,
+            ..Default::default()
+        },
+    ];
+    lod.named_properties = vec![LodNamedProperty {
+        property: "autocenter".into(),
+        value: "0".into(),
+    }];
+
+    let selection = lod.selection("zasleh").unwrap();
+    assert_eq!(selection.name, "Zasleh");
+    assert!(selection.contains_face(1));
+    assert!(!selection.contains_face(0));
+    assert_eq!(selection.vertex_weight(4), 1.0);
+    assert_eq!(selection.vertex_weight(2), 0.2);
+    assert_eq!(selection.vertex_weight(0), 0.0);
+    assert!(lod.selection("missing").is_none());
+
+    // Selections without weights are fully selected
+    let proxy_selection = lod
+        .selection("PROXY:\\a3\\data_f\\proxies\\gunner.001")
+        .unwrap();
+    assert_eq!(proxy_selection.vertices, vec![(0, 1.0)]);
+
+    let proxies = lod.proxies();
+    assert_eq!(proxies.len(), 2);
+    assert_eq!(proxies[0].model, "\\a3\\data_f\\proxies\\gunner");
+    assert_eq!(
+        proxies[0].selection.as_deref(),
+        Some("proxy:\\a3\\data_f\\proxies\\gunner.001")
+    );
+    assert_eq!(proxies[0].position(), XYZTriplet::new(1.0, 2.0, 3.0));
+    assert_eq!(proxies[0].bone, Some(1));
+    assert_eq!(proxies[1].selection, None);
+    assert_eq!(proxies[1].bone, None);
+
+    assert_eq!(lod.named_property("AutoCenter").as_deref(), Some("0"));
+    assert_eq!(lod.named_property("lodnoshadow"), None);
+}
+
+#[test]
+fn model_dependencies_test() {
+    let mut odol = odol_quad_and_triangle();
+    odol.model_info.skeleton.name = "OFP2_ManSkeleton".into();
+    let mut material = LodMaterial::default();
+    material.material_name = "\\data\\wall.rvmat".into();
+    material.surface_file = Some("data\\concrete.bisurf".into());
+    material.stage_textures = vec![
+        StageTexture {
+            texture: "data\\wall_nohq.paa".into(),
+            ..Default::default()
+        },
+        StageTexture {
+            texture: "#(argb,8,8,3)color(0.5,0.5,1,1)".into(),
+            ..Default::default()
+        },
+        StageTexture {
+            texture: "DATA\\WALL_CO.PAA".into(),
+            ..Default::default()
+        },
+    ];
+    odol.lods[0].materials = vec![material];
+    odol.lods[0].sections[1].material = Some("data\\glass.rvmat".into());
+    odol.lods[0].proxies = vec![Proxy {
+        proxy_model: "\\a3\\data_f\\proxies\\gunner".into(),
+        ..Default::default()
+    }];
+
+    let deps = ModelDependencies::from_odol(&odol);
+    assert_eq!(
+        deps.textures,
+        vec!["data\\wall_co.paa", "data\\wall_nohq.paa"]
+    );
+    assert_eq!(
+        deps.materials,
+        vec!["data\\wall.rvmat", "data\\glass.rvmat"]
+    );
+    assert_eq!(deps.surfaces, vec!["data\\concrete.bisurf"]);
+    assert_eq!(deps.proxies, vec!["a3\\data_f\\proxies\\gunner.p3d"]);
+    assert_eq!(deps.skeleton.as_deref(), Some("OFP2_ManSkeleton"));
+    assert_eq!(deps.files().count(), 6);
+
+    let mut visual = MlodLod::new(0.0);
+    let a = visual.add_point(XYZTriplet::default());
+    let triangle = [(a, 0.0, 0.0); 3];
+    visual.add_face(&triangle, "data\\old_co.paa", "data\\old.rvmat");
+    visual.add_face(&triangle, "", "");
+    visual.add_proxy("\\a3\\data_f\\proxies\\crew.p3d", XYZTriplet::default());
+    let mut mlod = MLOD::new();
+    mlod.lods = vec![visual];
+
+    let mut deps = ModelDependencies::from_mlod(&mlod);
+    assert_eq!(deps.textures, vec!["data\\old_co.paa"]);
+    assert_eq!(deps.materials, vec!["data\\old.rvmat"]);
+    assert_eq!(deps.proxies, vec!["a3\\data_f\\proxies\\crew.p3d"]);
+    assert_eq!(deps.skeleton, None);
+
+    let rvmat = Rvmat::from_cfg(&Cfg::parse_config(SUPER_RVMAT).unwrap());
+    deps.add_rvmat(&rvmat);
+    assert!(deps.textures.len() > 1);
+    assert!(deps.textures.iter().all(|t| !t.starts_with('#')));
+}
+
+#[test]
+fn model_report_test() {
+    let mut odol = odol_quad_and_triangle();
+    odol.model_info.bbox_max_pos = XYZTriplet::new(2.0, 1.0, 1.0);
+
+    let report = ModelReport::from_odol(&odol);
+    assert_eq!(report.lods.len(), 1);
+    let stats = &report.lods[0];
+    assert_eq!(stats.vertex_count, 5);
+    assert_eq!(stats.face_count, 2);
+    assert_eq!(stats.triangle_count, 3);
+    assert_eq!(stats.section_count, 2);
+    assert_eq!(stats.texture_count, 2);
+    assert_eq!(
+        stats.bounds,
+        Some((XYZTriplet::default(), XYZTriplet::new(2.0, 1.0, 0.0)))
+    );
+    assert_eq!(
+        report.issues,
+        vec![
+            ModelIssue::MissingLod(ResolutionEnum::Geometry),
+            ModelIssue::MissingLod(ResolutionEnum::FireGeometry),
+        ]
+    );
+    assert!(!report.is_valid());
+
+    // Closed tetrahedron as geometry, the fire geometry lacks its bottom face
+    let mut geometry = Lod::default();
+    geometry.vertices = vec![
+        XYZTriplet::new(0.0, 0.0, 0.0),
+        XYZTriplet::new(1.0, 0.0, 0.0),
+        XYZTriplet::new(0.0, 1.0, 0.0),
+        XYZTriplet::new(0.0, 0.0, 1.0),
+    ];
+    geometry.faces = [[0, 1, 3], [1, 2, 3], [2, 0, 3], [0, 2, 1]]
+        .into_iter()
+        .map(|f| LodFace {
+            face_type: 3,
+            vertex_indices: f.to_vec(),
+        })
+        .collect();
+    let mut fire_geometry = geometry.clone();
+    fire_geometry.faces.pop();
+    odol.resolutions.push(Resolution::from_value(1E13));
+    odol.resolutions.push(Resolution::from_value(7E15));
+    odol.lods.push(geometry);
+    odol.lods.push(fire_geometry);
+
+    let report = ModelReport::from_odol(&odol);
+    assert_eq!(
+        report.issues,
+        vec![
+            ModelIssue::ZeroMass,
+            ModelIssue::OpenGeometry {
+                lod: 2,
+                open_edges: 3
+            },
+        ]
+    );
+    assert_eq!(
+        report.issues[1].to_string(),
+        "LOD 2 is not closed, 3 open edges"
+    );
+
+    odol.model_info.mass = 4.0;
+    odol.model_info.mass_array = vec![1.0, 1.0, 1.0, 1.0];
+    odol.model_info.bbox_max_pos = XYZTriplet::new(1.0, 1.0, 1.0);
+    let report = ModelReport::from_odol_with_options(&odol, ModelReportOptions { max_sections: 1 });
+    assert_eq!(report.mass.total, 4.0);
+    assert_eq!(report.mass.point_count, 4);
+    assert_eq!(report.mass.center, Some(XYZTriplet::new(0.25, 0.25, 0.25)));
+    assert_eq!(
+        report.issues,
+        vec![
+            ModelIssue::TooManySections { lod: 0, count: 2 },
+            ModelIssue::OutsideBoundingBox { lod: 0 },
+            ModelIssue::OpenGeometry {
+                lod: 2,
+                open_edges: 3
+            },
+        ]
+    );
+
+    // Without loaded LODs only the model wide checks apply
+    let options = ODOLOptions {
+        skip_lods: true,
+        ..Default::default()
+    };
+    odol.version = ODOL_MAX_VERSION;
+    let skipped =
+        ODOL::from_stream_with_options(&mut Cursor::new(odol.write_data().unwrap()), options)
+            .unwrap();
+    assert!(skipped.lods.is_empty());
+    let report = ModelReport::from_odol(&skipped);
+    assert!(report.lods.is_empty());
+    assert!(report.is_valid());
+}
+
+#[test]
+fn resolution_test() {
+    let names = [
+        (1.0, "1.000"),
+        (1.5, "1.500"),
+        (1.2E3, "View Cargo"),
+        (1E4, "ShadowVolume 0"),
+        (1.1E4, "ShadowVolume 1000"),
+        (2E4, "Edit 0"),
+        (1E13, "Geometry"),
+        (4E13, "Geometry PhysX"),
+        (7E15, "Fire Geometry"),
+        (5E15, "Hit-points"),
+    ];
+    for (value, name) in names {
+        let res = Resolution::from_value(value);
+        assert_eq!(res.to_string(), name);
+        assert_eq!(Resolution::from_name(name), Some(res));
+    }
+    assert_eq!(
+        Resolution::from_name("view cargo").unwrap().res,
+        ResolutionEnum::ViewCargo
+    );
+    assert_eq!(
+        Resolution::from_name("ShadowVolume 10").unwrap().value,
+        1.001E4
+    );
+    assert_eq!(Resolution::from_name("Lod 1"), None);
+
+    // Unknown resolutions keep their value
+    let unknown = Resolution::from_value(5E13);
+    assert_eq!(unknown.res, ResolutionEnum::Unknown(5E13));
+    assert_ne!(unknown.res, ResolutionEnum::Unknown(6E13));
+    assert!(!unknown.is_geometry());
+
+    for (value, res) in [
+        (2E13, ResolutionEnum::GeometryBuoyancy),
+        (3E13, ResolutionEnum::GeometryPhysxOld),
+        (4E13, ResolutionEnum::GeometryPhysx),
+    ] {
+        let resolution = Resolution::from_value(value);
+        assert_eq!(resolution.res, res);
+        assert!(resolution.is_geometry());
+        assert_eq!(
+            Resolution::from_name(&resolution.to_string()),
+            Some(resolution)
+        );
+    }
+
+    let visual = Resolution::from_value(1.0);
+    let shadow = Resolution::from_value(1E4);
+    let geometry = Resolution::from_value(1E13);
+    let view_commander = Resolution::from_value(1E16);
+    assert!(visual.is_visual() && !visual.is_shadow() && !visual.is_geometry());
+    assert!(shadow.is_shadow() && !shadow.is_visual());
+    assert!(geometry.is_geometry() && !geometry.is_visual());
+    assert!(view_commander.is_visual());
+    assert!(Resolution::from_value(1.8E16).is_shadow());
+    assert!(Resolution::from_value(1.5E16).is_geometry());
+    assert!(!Resolution::from_value(1E15).is_geometry());
+
+    let mut resolutions = vec![geometry, shadow, view_commander, visual];
+    resolutions.sort();
+    assert_eq!(resolutions, vec![visual, shadow, geometry, view_commander]);
+}
+
+#[test]
+fn skeleton_tree_test() {
+    let mut odol = odol_quad_and_triangle();
+    let skeleton = &mut odol.model_info.skeleton;
+    skeleton.skeleton_bones.extend([
+        Bone {
+            bone_name: "handle".into(),
+            bone_parent: "DOOR".into(),
+        },
+        Bone {
+            bone_name: "loop_a".into(),
+            bone_parent: "loop_b".into(),
+        },
+        Bone {
+            bone_name: "loop_b".into(),
+            bone_parent: "loop_a".into(),
+        },
+        Bone {
+            bone_name: "wheel".into(),
+            bone_parent: "missing".into(),
+        },
+    ]);
+
+    assert_eq!(skeleton.bone_index("Handle"), Some(2));
+    assert_eq!(skeleton.bone_index("missing"), None);
+
+    let tree = skeleton.tree();
+    assert_eq!(tree.bones.len(), 6);
+    assert_eq!(tree.bone("BODY").unwrap().children, vec![1]);
+    assert_eq!(tree.get(2).unwrap().parent, Some(1));
+    assert_eq!(
+        tree.ancestors(2)
+            .map(|b| b.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["door", "body"]
+    );
+    assert_eq!(
+        tree.roots().map(|b| b.index).collect::<Vec<_>>(),
+        vec![0, 3, 5]
+    );
+    // Cycles are broken at the first bone
+    assert_eq!(tree.get(4).unwrap().parent, Some(3));
+    assert_eq!(tree.depth_first(), vec![0, 1, 2, 3, 4, 5]);
+
+    let lod = &mut odol.lods[0];
+    lod.bone_links = vec![BoneLink::default(); 2];
+    lod.bone_links[0].values = vec![1];
+    lod.bone_links[1].values = vec![0];
+    lod.sections[1].min_bone_index = 0;
+    lod.sections[1].bone_count = 2;
+
+    assert_eq!(lod.skeleton_bone(0), Some(1));
+    assert_eq!(lod.skeleton_bone(2), None);
+    assert_eq!(lod.lod_bones(0), &[1]);
+    assert!(lod.lod_bones(3).is_empty());
+    assert_eq!(lod.section_skeleton_bones(&lod.sections[1]), vec![1, 0]);
+}
+
+#[test]
+fn skeleton_tree_cycle_test() {
+    let bone = |name: &str, parent: &str| Bone {
+        bone_name: name.into(),
+        bone_parent: parent.into(),
+    };
+    // The child is visited before the cycle it hangs off
+    let tree = SkeletonTree::from_bones(&[
+        bone("grandchild", "child"),
+        bone("child", "loop_a"),
+        bone("loop_a", "loop_b"),
+        bone("loop_b", "loop_a"),
+    ]);
+
+    assert_eq!(tree.get(0).unwrap().parent, Some(1));
+    assert_eq!(tree.get(1).unwrap().parent, Some(2));
+    assert_eq!(tree.get(2).unwrap().parent, None);
+    assert_eq!(tree.get(3).unwrap().parent, Some(2));
+    assert_eq!(tree.roots().map(|b| b.index).collect::<Vec<_>>(), vec![2]);
+    assert_eq!(tree.depth_first(), vec![2, 1, 0, 3]);
+}
+
+fn assert_close(a: XYZTriplet, b: XYZTriplet) {
+    assert!(
+        (a.x - b.x).abs() < 1e-5 && (a.y - b.y).abs() < 1e-5 && (a.z - b.z).abs() < 1e-5,
+        "{a:?} != {b:?}"
+    );
+}
+
+#[test]
+fn animation_evaluate_test() {
+    let mut odol = odol_quad_and_triangle();
+
+    odol.animations = Some(Animations {
+        animation_classes: vec![
+            AnimationClass {
+                anim_transform_type: AnimType::RotationZ,
+                anim_class_name: "door_open".into(),
+                anim_source: "Door".into(),
+                max_value: 1.0,
+                angle_0: Some(0.0),
+                angle_1: Some(FRAC_PI_2),
+                ..Default::default()
+            },
+            AnimationClass {
+                anim_transform_type: AnimType::Hide,
+                anim_class_name: "body_hide".into(),
+                anim_source: "damage".into(),
+                max_value: 1.0,
+                hide_value: Some(0.5),
+                ..Default::default()
+            },
+        ],
+        anims_2_bones: vec![Anims2Bones {
+            animation_class_indices: vec![
+                AnimBones {
+                    skeleton_bone_name_index: 1,
+                    axis_pos: Some(XYZTriplet::new(1.0, 0.0, 0.0)),
+                    axis_dir: Some(XYZTriplet::new(0.0, 0.0, 1.0)),
+                },
+                AnimBones {
+                    skeleton_bone_name_index: 0,
+                    ..Default::default()
+                },
+            ],
+        }],
+        ..Default::default()
+    });
+
+    let class = &odol.animations.as_ref().unwrap().animation_classes[0];
+    assert_eq!(class.phase(-1.0), 0.0);
+    assert_eq!(class.phase(0.25), 0.25);
+    assert_eq!(class.phase(2.0), 1.0);
+
+    let mut hide = odol.animations.as_ref().unwrap().animation_classes[1].clone();
+    let bone = AnimBones::default();
+    assert!(hide.transform(0.4, &bone).is_some());
+    assert!(hide.transform(1.0, &bone).is_none());
+    hide.unhide_value = Some(0.75);
+    assert!(hide.transform(0.5, &bone).is_none());
+    assert!(hide.transform(0.75, &bone).is_some());
+    // Unhide values below the hide value are ignored
+    hide.unhide_value = Some(0.25);
+    assert!(hide.transform(1.0, &bone).is_none());
+
+    let rest = odol.animated_lod(0, &HashMap::new()).unwrap();
+    assert_eq!(rest.vertices, odol.lods[0].vertices);
+
+    let sources = HashMap::from([("door".to_string(), 1.0)]);
+    let poses = odol.bone_poses(0, &sources).unwrap();
+    assert_eq!(poses.len(), 2);
+    assert!(!poses[1].hidden);
+
+    // Vertices 0..4 belong to the door, vertex 4 to the body
+    let lod = odol.animated_lod(0, &sources).unwrap();
+    assert_close(lod.vertices[0], XYZTriplet::new(1.0, -1.0, 0.0));
+    assert_close(lod.vertices[2], XYZTriplet::new(0.0, 0.0, 0.0));
+    assert_close(lod.vertices[4], XYZTriplet::new(2.0, 0.0, 0.0));
+    assert_close(lod.normals[0], XYZTriplet::new(0.0, 0.0, -1.0));
+
+    // Hiding the body hides the door as well
+    let sources = HashMap::from([("door".to_string(), 1.0), ("damage".to_string(), 1.0)]);
+    let poses = odol.bone_poses(0, &sources).unwrap();
+    assert!(poses.iter().all(|p| p.hidden));
+    let lod = odol.animated_lod(0, &sources).unwrap();
+    assert!(lod.vertices.iter().all(|v| *v == XYZTriplet::default()));
+
+    assert!(odol.bone_poses(3, &sources).is_err());
+}
+
+const SUPER_RVMAT: &str = r##"
+ambient[] = {1, 1, 1, 1};
+diffuse[] = {0.5, 0.5, 0.5, 1};
+forcedDiffuse[] = {0, 0, 0, 0};
+emmisive[] = {0, 0, 0, 1};
+specular[] = {0.3, 0.3, 0.3, 1};
+specularPower = 60;
+PixelShaderID = "Super";
+VertexShaderID = "Super";
+renderFlags[] = {"NoZWrite"};
+class Stage2
+{
+    texture = "#(argb,8,8,3)color(0.5,0.5,0.5,1,DT)";
+    uvSource = "tex";
+};
+class Stage1
+{
+    texture = "data\wall_nohq.paa";
+    uvSource = "tex1";
+    Filter = "Anisotropic";
+    class uvTransform
+    {
+        aside[] = {2, 0, 0};
+        up[] = {0, 2, 0};
+        dir[] = {0, 0, 0};
+        pos[] = {0.5, 0, 0};
+    };
+};
+"##;
+
+#[test]
+fn rvmat_test() {
+    let cfg = Cfg::parse_config(SUPER_RVMAT).unwrap();
+    let rvmat = Rvmat::from_cfg(&cfg);
+
+    assert_eq!(rvmat.diffuse.r, 0.5);
+    assert_eq!(rvmat.emissive.a, 1.0);
+    assert_eq!(rvmat.specular_power, 60.0);
+    assert_eq!(rvmat.pixel_shader, PixelShaderID::PSSuper);
+    assert_eq!(rvmat.vertex_shader.e, VertexShaderIDEnum::VSSuper);
+    assert_eq!(rvmat.vertex_shader.value, 23);
+    assert_eq!(rvmat.render_flags, vec!["NoZWrite".to_string()]);
+
+    assert_eq!(rvmat.stages.len(), 2);
+    let stage = rvmat.stage(1).unwrap();
+    assert_eq!(stage.texture, "data\\wall_nohq.paa");
+    assert_eq!(stage.uv_source, UVSource::UVTex1);
+    assert_eq!(stage.filter, Some(TextureFilterType::Anisotropic));
+    assert_eq!(stage.uv_transform.0, XYZTriplet::new(2.0, 0.0, 0.0));
+    assert_eq!(stage.uv_transform.3, XYZTriplet::new(0.5, 0.0, 0.0));
+    assert_eq!(rvmat.stage(2).unwrap().uv_transform.1.y, 1.0);
+    assert_eq!(
+        rvmat.textures().collect::<Vec<_>>(),
+        vec!["data\\wall_nohq.paa"]
+    );
+
+    let empty = Rvmat::from_cfg(&Cfg::parse_config("foo = 1;").unwrap());
+    assert_eq!(empty, Rvmat::new());
+
+    let mut material = LodMaterial::default();
+    material.pixel_shader = PixelShaderID::PSNormalMap as i32;
+    material.vertex_shader = 1;
+    material.specular_power = 20.0;
+    material.surface_file = Some("data\\concrete.bisurf".into());
+    let stage = StageTexture {
+        texture: "data\\wall_nohq.paa".into(),
+        stage_id: Some(1),
+        ..Default::default()
+    };
+    material.stage_textures = vec![StageTexture::default(), stage];
+    material.stage_transforms = vec![
+        StageTransform::default(),
+        StageTransform {
+            uv_source: 1,
+            transformation: Default::default(),
+        },
+    ];
+
+    let rvmat = Rvmat::from(&material);
+    assert_eq!(rvmat.pixel_shader, PixelShaderID::PSNormalMap);
+    assert_eq!(rvmat.vertex_shader.e, VertexShaderIDEnum::VSNormalMap);
+    assert_eq!(rvmat.surface_info.as_deref(), Some("data\\concrete.bisurf"));
+    assert_eq!(rvmat.stages[1].uv_source, UVSource::UVTex);
+    assert_eq!(rvmat.stages[0].uv_source, UVSource::UVNone);
+    assert_eq!(rvmat.textures().count(), 1);
+
+    assert_eq!(
+        PixelShaderID::from_value(-1),
+        PixelShaderID::PSUninitialized
+    );
+    assert_eq!(
+        PixelShaderID::from_value(1000),
+        PixelShaderID::PSUninitialized
+    );
+    assert_eq!(
+        VertexShaderID::from_value(35).e,
+        VertexShaderIDEnum::NVertexShaderID
+    );
+    assert!(matches!(
+        VertexShaderID::from_value(36).e,
+        VertexShaderIDEnum::Unknown(_)
+    ));
+    assert_eq!(
+        VertexShaderID::from_name("simulweathercloudscpu").map(|vs| vs.value),
+        Some(34)
+    );
+    assert_eq!(VertexShaderID::from_name("NVertexShaderID"), None);
+    assert_eq!(VertexShaderID::from_name("Missing"), None);
+    assert_eq!(UVSource::from_value(9), Some(UVSource::NUVSource));
+    assert_eq!(UVSource::from_value(10), None);
+}
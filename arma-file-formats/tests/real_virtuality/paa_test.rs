@@ -6,7 +6,14 @@ use std::{
 use arma_file_formats::{
     self,
     core::types::PixelType,
-    real_virtuality::paa::{Paa, Tagg},
+    errors::PaaError,
+    real_virtuality::{
+        paa::{
+            ChannelSwizzle, ConversionProfile, MipmapFilter, MipmapOptions, Paa, PaaReader,
+            SwizzleSource, Tagg, TextureSuffix,
+        },
+        types::PaaType,
+    },
 };
 use image::ImageBuffer;
 use serial_test::serial;
@@ -203,3 +210,361 @@ fn argb4444_staszow_decoding() {
 
     assert_eq!(paa.pixel_type, PixelType::Rgba);
 }
+
+#[test]
+fn texture_suffix_test() {
+    assert_eq!(
+        TextureSuffix::from_path("a3/data/tank_co.png"),
+        TextureSuffix::Co
+    );
+    assert_eq!(TextureSuffix::from_path("tank_CA.paa"), TextureSuffix::Ca);
+    assert_eq!(
+        TextureSuffix::from_path("tank_nohq.tga"),
+        TextureSuffix::Nohq
+    );
+    assert_eq!(
+        TextureSuffix::from_path("tank_smdi.png"),
+        TextureSuffix::Smdi
+    );
+    assert_eq!(TextureSuffix::from_path("tank_as.png"), TextureSuffix::As);
+    assert_eq!(TextureSuffix::from_path("tank_mc.png"), TextureSuffix::Mc);
+    assert_eq!(TextureSuffix::from_path("tank.png"), TextureSuffix::Unknown);
+
+    assert_eq!(
+        ConversionProfile::from_path("x_ca.png").paa_type,
+        Some(PaaType::DXT5)
+    );
+    assert_eq!(ConversionProfile::from_path("x_co.png").paa_type, None);
+    assert!(ConversionProfile::from_path("x_nohq.png").normal_map);
+}
+
+#[test]
+fn swizzle_tagg_test() {
+    let swizzle = ChannelSwizzle {
+        a: SwizzleSource::Red,
+        r: SwizzleSource::One,
+        g: SwizzleSource::InvGreen,
+        b: SwizzleSource::Zero,
+    };
+
+    let tagg = swizzle.to_tagg();
+    assert_eq!(tagg.signature, "GGATZIWS");
+    assert_eq!(ChannelSwizzle::from_tagg(&tagg), Some(swizzle));
+
+    let mut data = vec![10, 20, 30, 40];
+    swizzle.apply(&mut data);
+    assert_eq!(data, vec![0xFF, 235, 0, 10]);
+
+    swizzle.revert(&mut data);
+    assert_eq!(data, vec![10, 20, 0xFF, 0xFF]);
+}
+
+#[test]
+fn smdi_profile_roundtrip() {
+    let data: Vec<u8> = (0..16 * 16).flat_map(|_| [30, 200, 100, 50]).collect();
+    let source = Paa::from_image(16, 16, data.clone());
+
+    let profile = ConversionProfile::from_suffix(TextureSuffix::Smdi);
+    let mut buf = Vec::new();
+    profile.encode(&source, &mut Cursor::new(&mut buf)).unwrap();
+    // The source is not swizzled, encoding it again gives the same texture
+    assert_eq!(source.mipmaps[0].data, data);
+    let mut again = Vec::new();
+    profile
+        .encode(&source, &mut Cursor::new(&mut again))
+        .unwrap();
+    let read = |buf: &Vec<u8>| Paa::from_reader(&mut Cursor::new(buf), None).unwrap();
+    assert_eq!(read(&again).mipmaps, read(&buf).mipmaps);
+    assert_eq!(read(&again).swizzle(), Some(profile.swizzle));
+
+    let mut paa = Paa::from_reader(&mut Cursor::new(&buf), None).unwrap();
+    assert_eq!(paa.magic_number, PaaType::DXT1);
+    assert_eq!(paa.swizzle(), Some(profile.swizzle));
+    assert!(paa.get_tagg("GGATGALF").is_none());
+
+    profile.decode(&mut paa);
+    assert!(paa.swizzle().is_none());
+
+    let pixel = &paa.mipmaps[0].data[0..4];
+    assert_eq!(pixel[0], 0xFF);
+    assert!(pixel[1].abs_diff(200) <= 4);
+    assert!(pixel[2].abs_diff(100) <= 4);
+    assert_eq!(pixel[3], 0xFF);
+
+    // Without a swizzle tag the data is not touched
+    let data: Vec<u8> = (0..4 * 4).flat_map(|_| [30, 200, 100, 50]).collect();
+    let mut paa = Paa::from_image(4, 4, data.clone());
+    profile.decode(&mut paa);
+    assert_eq!(paa.mipmaps[0].data, data);
+    ConversionProfile::from_suffix(TextureSuffix::Nohq).decode(&mut paa);
+    assert_eq!(paa.mipmaps[0].data, data);
+}
+
+#[test]
+fn nohq_profile_roundtrip() {
+    // Slightly tilted, non normalized normal
+    let data: Vec<u8> = (0..16 * 16).flat_map(|_| [160, 128, 200, 0xFF]).collect();
+    let paa = Paa::from_image(16, 16, data);
+
+    let profile = ConversionProfile::from_path("wall_nohq.png");
+    let mut buf = Vec::new();
+    profile.encode(&paa, &mut Cursor::new(&mut buf)).unwrap();
+
+    let mut paa = Paa::from_reader(&mut Cursor::new(&buf), None).unwrap();
+    assert_eq!(paa.magic_number, PaaType::DXT5);
+    assert!(paa.get_tagg("GGATGALF").is_none());
+
+    profile.decode(&mut paa);
+    for mipmap in &paa.mipmaps {
+        let pixel = &mipmap.data[0..4];
+        let x = f32::from(pixel[0]) / 127.5 - 1.0;
+        let y = f32::from(pixel[1]) / 127.5 - 1.0;
+        let z = f32::from(pixel[2]) / 127.5 - 1.0;
+        assert!(((x * x + y * y + z * z).sqrt() - 1.0).abs() < 0.05);
+        assert!(x > 0.3);
+    }
+}
+
+#[test]
+fn mipmap_level_count_test() {
+    assert_eq!(Paa::mipmap_level_count(1024, 1024), 9);
+    assert_eq!(Paa::mipmap_level_count(1024, 512), 8);
+    assert_eq!(Paa::mipmap_level_count(64, 16), 3);
+    assert_eq!(Paa::mipmap_level_count(400, 400), 7);
+    assert_eq!(Paa::mipmap_level_count(4, 4), 1);
+    assert_eq!(Paa::mipmap_level_count(2, 2), 1);
+}
+
+fn checkerboard(width: u16, height: u16) -> Vec<u8> {
+    (0..height as usize)
+        .flat_map(|y| {
+            (0..width as usize).flat_map(move |x| {
+                let v = if (x + y) % 2 == 0 { 0xFF } else { 0 };
+                [v, v, v, 0xFF]
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn non_square_mipmaps_test() {
+    for filter in [
+        MipmapFilter::Box,
+        MipmapFilter::Triangle,
+        MipmapFilter::Kaiser,
+        MipmapFilter::Lanczos,
+    ] {
+        let mut paa = Paa::from_image(64, 16, checkerboard(64, 16));
+        let options = MipmapOptions {
+            filter,
+            ..Default::default()
+        };
+        paa.write_with_options(&mut Cursor::new(Vec::new()), None, &options)
+            .unwrap();
+
+        let dims: Vec<(u16, u16)> = paa.mipmaps.iter().map(|m| (m.width, m.height)).collect();
+        assert_eq!(dims, vec![(64, 16), (32, 8), (16, 4)]);
+
+        for mipmap in &paa.mipmaps[1..] {
+            assert_eq!(
+                mipmap.data.len(),
+                mipmap.width as usize * mipmap.height as usize * 4
+            );
+            let center = (mipmap.height as usize / 2 * mipmap.width as usize
+                + mipmap.width as usize / 2)
+                * 4;
+            assert!(mipmap.data[center].abs_diff(128) <= 2, "{filter:?}");
+        }
+    }
+}
+
+#[test]
+fn linear_light_mipmaps_test() {
+    let mut paa = Paa::from_image(8, 8, checkerboard(8, 8));
+    paa.write_with_options(&mut Cursor::new(Vec::new()), None, &MipmapOptions::color())
+        .unwrap();
+
+    // 50% linear intensity is ~188 in sRGB
+    assert!(paa.mipmaps[1].data[0].abs_diff(188) <= 1);
+    assert_eq!(paa.mipmaps[1].data[3], 0xFF);
+}
+
+#[test]
+fn alpha_coverage_mipmaps_test() {
+    // Noisy foliage like cutout
+    let data: Vec<u8> = (0..64_u32)
+        .flat_map(|y| {
+            (0..64_u32).flat_map(move |x| {
+                let noise = (x * 37 + y * 91) ^ (x * y);
+                [0, 0xFF, 0, (noise & 0xFF) as u8]
+            })
+        })
+        .collect();
+
+    let coverage = |data: &[u8]| {
+        data.chunks_exact(4).filter(|p| p[3] > 200).count() as f32 / (data.len() / 4) as f32
+    };
+    let target = coverage(&data);
+
+    let mut plain = Paa::from_image(64, 64, data.clone());
+    plain.write(&mut Cursor::new(Vec::new()), None).unwrap();
+
+    let mut paa = Paa::from_image(64, 64, data);
+    let options = MipmapOptions {
+        alpha_coverage: Some(200),
+        ..Default::default()
+    };
+    paa.write_with_options(&mut Cursor::new(Vec::new()), None, &options)
+        .unwrap();
+
+    for (preserved, plain) in paa.mipmaps.iter().zip(&plain.mipmaps).skip(1) {
+        let preserved = (coverage(&preserved.data) - target).abs();
+        let plain = (coverage(&plain.data) - target).abs();
+        assert!(preserved < 0.1);
+        assert!(preserved < plain);
+    }
+}
+
+#[test]
+fn alpha_coverage_opaque_and_cutout_test() {
+    let options = MipmapOptions {
+        alpha_coverage: Some(127),
+        ..Default::default()
+    };
+
+    // Opaque textures stay opaque
+    let mut opaque = Paa::from_image(16, 16, checkerboard(16, 16));
+    opaque
+        .write_with_options(&mut Cursor::new(Vec::new()), None, &options)
+        .unwrap();
+    assert!(opaque
+        .mipmaps
+        .iter()
+        .all(|m| m.data.chunks_exact(4).all(|p| p[3] == 0xFF)));
+
+    // Fully transparent textures have nothing to preserve
+    let data: Vec<u8> = (0..16 * 16).flat_map(|_| [0xFF, 0, 0, 0]).collect();
+    let mut clear = Paa::from_image(16, 16, data);
+    clear
+        .write_with_options(&mut Cursor::new(Vec::new()), None, &options)
+        .unwrap();
+    assert!(clear
+        .mipmaps
+        .iter()
+        .all(|m| m.data.chunks_exact(4).all(|p| p[3] == 0)));
+
+    // Half covered cutout, alternating columns average to 127.5 and are scaled up to stay covered
+    let data: Vec<u8> = (0..16 * 16)
+        .flat_map(|i| [0, 0xFF, 0, if i % 2 == 0 { 0xFF } else { 0 }])
+        .collect();
+    let mut cutout = Paa::from_image(16, 16, data);
+    cutout
+        .write_with_options(&mut Cursor::new(Vec::new()), None, &options)
+        .unwrap();
+    for mipmap in &cutout.mipmaps[1..] {
+        let covered = mipmap.data.chunks_exact(4).filter(|p| p[3] > 127).count();
+        let coverage = covered as f32 / (mipmap.data.len() / 4) as f32;
+        assert!(coverage >= 0.5, "{}x{}", mipmap.width, mipmap.height);
+    }
+}
+
+#[test]
+fn non_power_of_two_write_test() {
+    let mut paa = Paa::from_image(12, 16, checkerboard(12, 16));
+    assert!(matches!(
+        paa.write(&mut Cursor::new(Vec::new()), None),
+        Err(PaaError::InvalidDimensions(12, 16))
+    ));
+}
+
+#[test]
+fn paa_reader_test() {
+    let mut paa = Paa::from_image(256, 128, checkerboard(256, 128));
+    let mut buf = Vec::new();
+    paa.write(&mut Cursor::new(&mut buf), None).unwrap();
+
+    let mut reader = PaaReader::from_stream(Cursor::new(&buf)).unwrap();
+    assert_eq!(reader.mipmap_count(), 6);
+    assert_eq!(reader.mipmap_dimensions()[0], (256, 128));
+    assert_eq!(reader.mipmap_dimensions()[5], (8, 4));
+    assert!(reader.paa.mipmaps.iter().all(|m| m.data.is_empty()));
+
+    let mipmap = reader.get_mipmap_for_width(20).unwrap().unwrap();
+    assert_eq!((mipmap.width, mipmap.height), (32, 16));
+    assert_eq!(mipmap.data.len(), 32 * 16 * 4);
+    assert_eq!(
+        reader
+            .paa
+            .mipmaps
+            .iter()
+            .filter(|m| !m.data.is_empty())
+            .count(),
+        1
+    );
+
+    let largest = reader.get_mipmap_for_width(1024).unwrap().unwrap();
+    assert_eq!(largest.width, 256);
+
+    let smallest = reader.get_mipmap_for_width(1).unwrap().unwrap();
+    assert_eq!(smallest.width, 8);
+
+    assert!(reader.get_mipmap(6).unwrap().is_none());
+
+    let eager = Paa::from_reader(&mut Cursor::new(&buf), Some(&[3])).unwrap();
+    assert_eq!(
+        eager.mipmaps[3].data,
+        reader.get_mipmap(3).unwrap().unwrap().data
+    );
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn paa_dynamic_image_test() {
+    let gray = image::GrayImage::from_fn(32, 16, |x, _| image::Luma([(x * 8) as u8]));
+    let mut paa = Paa::from_dynamic_image(&image::DynamicImage::ImageLuma8(gray)).unwrap();
+    assert_eq!((paa.mipmaps[0].width, paa.mipmaps[0].height), (32, 16));
+    assert_eq!(paa.mipmaps[0].data.len(), 32 * 16 * 4);
+
+    let mut buf = Vec::new();
+    paa.write(&mut Cursor::new(&mut buf), None).unwrap();
+
+    let paa = Paa::from_reader(&mut Cursor::new(&buf), None).unwrap();
+    let img = image::DynamicImage::try_from(&paa).unwrap();
+    assert_eq!(img.color(), image::ColorType::Rgba8);
+    assert_eq!((img.width(), img.height()), (32, 16));
+    assert!(img.to_rgba8().get_pixel(16, 8)[0].abs_diff(128) <= 8);
+
+    let level = paa.mipmap_to_image(2).unwrap();
+    assert_eq!((level.width(), level.height()), (8, 4));
+
+    let too_large = image::DynamicImage::new_rgba8(70_000, 1);
+    assert!(Paa::from_dynamic_image(&too_large).is_err());
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn gray_alpha_dynamic_image_test() {
+    let mut paa = Paa::new();
+    paa.pixel_type = PixelType::GrayAlpha;
+    let mut mipmap = arma_file_formats::real_virtuality::paa::Mipmap::new();
+    mipmap.width = 2;
+    mipmap.height = 1;
+    mipmap.data = vec![10, 20, 30, 40];
+    paa.mipmaps.push(mipmap);
+
+    let img = image::DynamicImage::try_from(&paa).unwrap();
+    assert_eq!(img.color(), image::ColorType::La8);
+    assert_eq!(img.as_bytes(), &[10, 20, 30, 40]);
+
+    paa.pixel_type = PixelType::Unknown;
+    assert!(matches!(
+        image::DynamicImage::try_from(&paa),
+        Err(arma_file_formats::errors::AffError::UnsupportedPixelType(
+            PixelType::Unknown
+        ))
+    ));
+    assert!(matches!(
+        paa.mipmap_to_image(1),
+        Err(arma_file_formats::errors::AffError::MipmapNotFound(1))
+    ));
+}
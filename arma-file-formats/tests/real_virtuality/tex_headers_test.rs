@@ -0,0 +1,79 @@
+use std::io::Cursor;
+
+use arma_file_formats::real_virtuality::{
+    paa::Paa,
+    tex_headers::{TexHeaders, TexturePixelFormat},
+    types::PaaType,
+};
+
+fn write_paa(size: u16, alpha: u8) -> Vec<u8> {
+    let data = [0x40, 0x80, 0xC0, alpha].repeat(size as usize * size as usize);
+    let mut paa = Paa::from_image(size, size, data);
+    let mut buf = Vec::new();
+    paa.write(&mut Cursor::new(&mut buf), None).unwrap();
+    buf
+}
+
+#[test]
+fn tex_headers_generate_test() {
+    let co = write_paa(32, 0xFF);
+    let ca = write_paa(16, 0x40);
+
+    let mut tex_headers = TexHeaders::new();
+    tex_headers
+        .add_paa_stream("data\\foo_co.paa", &mut Cursor::new(&co))
+        .unwrap();
+    tex_headers
+        .add_paa_stream("data\\foo_ca.paa", &mut Cursor::new(&ca))
+        .unwrap();
+    // Replaces the existing entry
+    tex_headers
+        .add_paa_stream("Data/Foo_CA.paa", &mut Cursor::new(&ca))
+        .unwrap();
+    assert_eq!(tex_headers.textures.len(), 2);
+
+    let co_header = tex_headers.get("DATA/foo_co.paa").unwrap();
+    assert_eq!(co_header.pixel_format, TexturePixelFormat::Dxt1);
+    assert_eq!(co_header.file_size as usize, co.len());
+    assert!(!co_header.is_alpha);
+    assert_eq!(co_header.mipmaps.len(), Paa::mipmap_level_count(32, 32));
+
+    // Offsets point at the mipmap headers inside the paa
+    for mipmap in &co_header.mipmaps {
+        let offset = mipmap.data_offset as usize;
+        assert_eq!(
+            u16::from_le_bytes([co[offset], co[offset + 1]]) & 0x7FFF,
+            mipmap.width
+        );
+        assert_eq!(
+            u16::from_le_bytes([co[offset + 2], co[offset + 3]]),
+            mipmap.height
+        );
+    }
+
+    let ca_header = tex_headers.get("data\\foo_ca.paa").unwrap();
+    assert_eq!(ca_header.pixel_format, TexturePixelFormat::Dxt5);
+    assert!(ca_header.is_alpha);
+    assert!(ca_header.is_alpha_non_opaque);
+    assert_eq!(ca_header.path.to_string(), "Data/Foo_CA.paa");
+
+    let data = tex_headers.write_data().unwrap();
+    assert_eq!(&data[..4], b"0DHT");
+
+    let read = TexHeaders::from_stream(&mut Cursor::new(&data)).unwrap();
+    assert_eq!(read, tex_headers);
+    assert_eq!(read.write_data().unwrap(), data);
+}
+
+#[test]
+fn tex_headers_pixel_format_test() {
+    assert_eq!(
+        TexturePixelFormat::from(PaaType::GRAYwAlpha),
+        TexturePixelFormat::Ai88
+    );
+    assert_eq!(
+        TexturePixelFormat::from(PaaType::RGBA4444),
+        TexturePixelFormat::Argb4444
+    );
+    assert!(TexHeaders::from_stream(&mut Cursor::new(b"0DHX\x01\0\0\0\0\0\0\0")).is_err());
+}
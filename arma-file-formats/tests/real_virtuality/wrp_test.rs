@@ -1,6 +1,20 @@
-use std::{fs::File, io::BufReader};
+use std::{
+    fs::File,
+    io::{BufReader, Cursor},
+};
 
-use arma_file_formats::real_virtuality::wrp::{MapData, MapInfo, OPRW, WVR8};
+use arma_file_formats::{
+    errors::{AffError, WrpError},
+    real_virtuality::{
+        types::{TransformMatrix, XYZTriplet, XY},
+        wrp::{
+            objects_to_geojson, write_terrain_builder, Heightmap, MapData, MapInfo, Object,
+            ObjectExportOptions, PlacedObject, QuadTree, RoadClass, RoadGraph, RoadNet, RoadPart,
+            Texture, OPRW, WVR8,
+        },
+    },
+};
+use binrw::BinRead;
 use serial_test::serial;
 
 const INPUT_PATH_PREFIX: &str = "./tests/real_virtuality/test-data/wrp_in/";
@@ -116,3 +130,430 @@ fn angel_test() {
     assert_eq!(wvr8.rvmat_layer.materials.len(), 238);
     assert_eq!(wvr8.objects.len(), 62249);
 }
+
+/// Quadtree node with one 4 byte leaf per child.
+fn quadtree_u32(values: [u32; 16]) -> QuadTree {
+    let mut data = vec![1, 0, 0];
+    data.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+    QuadTree::read_le_args(&mut Cursor::new(data), (4,)).unwrap()
+}
+
+fn oprw_sample() -> OPRW {
+    let mut oprw = OPRW::default();
+    oprw.version = 25;
+    oprw.app_id = Some(107410);
+    oprw.layer_size_x = Some(2);
+    oprw.layer_size_y = Some(2);
+    oprw.map_size_x = Some(32);
+    oprw.map_size_y = Some(32);
+    oprw.layer_cell_size = Some(40.0);
+    oprw.mountains = vec![XYZTriplet::new(10.0, 20.0, 130.0)];
+    oprw.grass = Some(vec![1; 1024]);
+    oprw.tex_index = Some((0..1024).map(|i| (i % 7) as u8).collect());
+    oprw.elevation = (0..1024).map(|i| i as f32 * 0.5).collect();
+    oprw.unknown_bytes_0 = vec![0; 4];
+    oprw.unknown_bytes_1 = vec![0; 1024];
+
+    let mut texture = Texture::default();
+    texture.texture_filename = "a3\\map_stratis\\data\\layers\\p_000-000_l00.rvmat".into();
+    oprw.texures = vec![texture];
+    oprw.models = vec!["a3\\rocks_f\\sharp\\sharprock_apart.p3d".into()];
+    oprw.classed_models = Some(Vec::new());
+
+    oprw.objects = (0..3)
+        .map(|i| Object {
+            object_id: i,
+            model_index: 0,
+            transform_matrx: TransformMatrix::translation(XYZTriplet::new(i as f32, 0.0, 0.0)),
+            shape_params: Some(0),
+        })
+        .collect();
+    oprw.object_offsets = quadtree_u32([
+        0, 0, 60, 120, 180, 180, 180, 180, 180, 180, 180, 180, 180, 180, 180, 180,
+    ]);
+    oprw.map_infos = vec![
+        MapInfo {
+            id: 0,
+            data: MapData::MapType1 {
+                object_id: 0,
+                x: 1.0,
+                y: 2.0,
+            },
+        },
+        MapInfo {
+            id: 44,
+            data: MapData::MapTypeRiver {
+                object_id: 1,
+                polygon_count: 0,
+                polygon: vec![XY { x: 0.0, y: 0.0 }, XY { x: 1.0, y: 1.0 }],
+            },
+        },
+        MapInfo {
+            id: 25,
+            data: MapData::MapType3 {
+                color: 0,
+                indicator: 1,
+                floats: vec![0.0; 4],
+            },
+        },
+    ];
+    oprw.map_object_offsets = quadtree_u32([
+        0, 16, 16, 44, 72, 72, 72, 72, 72, 72, 72, 72, 72, 72, 72, 72,
+    ]);
+    oprw.max_object_id = 2;
+
+    oprw.road_net = vec![RoadNet {
+        cell_index: 3,
+        road_parts: vec![RoadPart {
+            positions: vec![XYZTriplet::default(), XYZTriplet::new(5.0, 5.0, 0.0)],
+            types: Some(vec![0, 1]),
+            object_id: 2,
+            p3d_path: Some("a3\\roads_f\\road_01.p3d".into()),
+            transform_matrix: Some(TransformMatrix::IDENTITY),
+        }],
+    }];
+    oprw
+}
+
+#[test]
+fn oprw_write_test() {
+    let oprw = oprw_sample();
+    let data = oprw.write_data().unwrap();
+    let read = OPRW::from_read(&mut Cursor::new(&data)).unwrap();
+
+    assert_eq!(read.version, 25);
+    assert_eq!(read.app_id, Some(107410));
+    assert_eq!(read.mountains, oprw.mountains);
+    assert_eq!(read.grass, oprw.grass);
+    assert_eq!(read.tex_index, oprw.tex_index);
+    assert_eq!(read.elevation, oprw.elevation);
+    assert_eq!(read.texures, oprw.texures);
+    assert_eq!(read.models, oprw.models);
+    assert_eq!(read.objects, oprw.objects);
+    assert_eq!(read.object_offsets, oprw.object_offsets);
+    assert_eq!(read.map_infos.len(), 3);
+    assert_eq!(
+        read.map_infos[1].data,
+        MapData::MapTypeRiver {
+            object_id: 1,
+            polygon_count: 2,
+            polygon: vec![XY { x: 0.0, y: 0.0 }, XY { x: 1.0, y: 1.0 }],
+        }
+    );
+    assert_eq!(read.road_net, oprw.road_net);
+    assert_eq!(read.write_data().unwrap(), data);
+
+    // Patch elevation, replace and remove objects
+    let mut patched = read;
+    patched.elevation[0] = 42.0;
+    assert!(patched.replace_object_model(0, "a3\\rocks_f\\blunt\\bluntrock_apart.p3d"));
+    assert!(!patched.replace_object_model(7, "missing.p3d"));
+    assert_eq!(patched.models.len(), 2);
+    assert_eq!(patched.objects[0].model_index, 1);
+
+    let removed = patched.remove_object(1).unwrap();
+    assert_eq!(removed.object_id, 1);
+    assert!(patched.remove_object(1).is_none());
+    assert_eq!(patched.objects.len(), 2);
+    assert_eq!(
+        patched.object_offsets,
+        quadtree_u32([0, 0, 60, 60, 120, 120, 120, 120, 120, 120, 120, 120, 120, 120, 120, 120])
+    );
+    assert_eq!(patched.map_infos.len(), 2);
+    assert_eq!(
+        patched.map_object_offsets,
+        quadtree_u32([0, 16, 16, 16, 44, 44, 44, 44, 44, 44, 44, 44, 44, 44, 44, 44])
+    );
+
+    let read = OPRW::from_read(&mut Cursor::new(patched.write_data().unwrap())).unwrap();
+    assert_eq!(read.elevation[0], 42.0);
+    assert_eq!(read.objects, patched.objects);
+    assert_eq!(read.map_infos, patched.map_infos);
+
+    let mut invalid = oprw_sample();
+    invalid.elevation.pop();
+    assert!(matches!(
+        invalid.write_data(),
+        Err(AffError::WrpError(WrpError::InvalidLength {
+            field: "elevation",
+            expected: 1024,
+            actual: 1023
+        }))
+    ));
+    invalid.version = 20;
+    assert!(matches!(
+        invalid.write_data(),
+        Err(AffError::WrpError(WrpError::UnsupportedVersion(20)))
+    ));
+}
+
+#[test]
+fn quadtree_lookup_test() {
+    // Bytes are packed 2x2 per leaf, the root node covers 8x8 cells
+    let mut data = vec![1, 0, 0];
+    data.extend(0..64_u8);
+    let bytes = QuadTree::read_le_args(&mut Cursor::new(data), (1,)).unwrap();
+    for (y, x) in (0..8).flat_map(|y| (0..8).map(move |x| (y, x))) {
+        let expected = 4 * ((y / 2) * 4 + x / 2) + (y % 2) * 2 + x % 2;
+        assert_eq!(bytes.value_at(x, y, 8, 8), Some(expected));
+    }
+    assert_eq!(bytes.value_at(8, 0, 8, 8), None);
+    let grid = bytes.to_grid(8, 8);
+    assert_eq!(grid.len(), 64);
+    assert_eq!(&grid[..4], &[0, 1, 4, 5]);
+    assert_eq!(&grid[8..12], &[2, 3, 6, 7]);
+
+    // Nested node in the first child of a 16x16 grid
+    let mut data = vec![1, 1, 0, 0, 0];
+    data.extend((100..116_u32).flat_map(u32::to_le_bytes));
+    data.extend((1..16_u32).flat_map(u32::to_le_bytes));
+    let nested = QuadTree::read_le_args(&mut Cursor::new(data), (4,)).unwrap();
+    assert_eq!(nested.value_at(1, 2, 16, 16), Some(109));
+    assert_eq!(nested.value_at(5, 0, 16, 16), Some(1));
+    assert_eq!(nested.value_at(7, 3, 16, 16), Some(1));
+    assert_eq!(nested.value_at(15, 15, 16, 16), Some(15));
+    assert_eq!(nested.value_at(16, 0, 16, 16), None);
+
+    // A leaf root is uniform
+    let uniform = QuadTree::read_le_args(&mut Cursor::new([0, 7, 0, 7, 0]), (2,)).unwrap();
+    assert_eq!(uniform.value_at(99, 49, 100, 50), Some(7));
+    assert_eq!(uniform.to_grid(3, 2), vec![7; 6]);
+
+    let mut oprw = oprw_sample();
+    oprw.geography = QuadTree::read_le_args(&mut Cursor::new([0, 3, 0, 3, 0]), (2,)).unwrap();
+    oprw.rvmat_layer_index = quadtree_u32([0, 1, 0, 0, 2, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(oprw.geography_at(1, 1), Some(3));
+    assert_eq!(oprw.geography_at(2, 0), None);
+    assert_eq!(oprw.sound_map_at(0, 0), None);
+    assert_eq!(oprw.rvmat_layer_at(1, 0), Some(1));
+    assert_eq!(oprw.rvmat_layer_at(0, 1), Some(2));
+    assert_eq!(oprw.rvmat_layer_at(1, 1), Some(3));
+    assert_eq!(oprw.object_offset_at(0, 1), Some(180));
+    assert_eq!(oprw.map_object_offset_at(1, 0), Some(16));
+}
+
+#[test]
+fn heightmap_test() {
+    let mut oprw = oprw_sample();
+    let mut heightmap = oprw.heightmap();
+    assert_eq!((heightmap.width, heightmap.height), (32, 32));
+    assert_eq!(heightmap.cell_size, 2.5);
+    assert_eq!(heightmap.get(1, 0), Some(0.5));
+    assert_eq!(heightmap.get(0, 1), Some(16.0));
+    assert_eq!(heightmap.get(32, 0), None);
+    assert_eq!(heightmap.range(), (0.0, 511.5));
+    assert_eq!(heightmap.elevation_at(1.25, 1.25), Some(8.25));
+    assert_eq!(oprw.elevation_at(1.25, 1.25), Some(8.25));
+    assert_eq!(heightmap.elevation_at(-0.1, 0.0), None);
+    assert_eq!(
+        Heightmap::new(0, 0, 1.0, vec![]).elevation_at(0.0, 0.0),
+        None
+    );
+    assert_eq!(
+        Heightmap::new(1, 1, 1.0, vec![3.0]).elevation_at(0.0, 0.0),
+        Some(3.0)
+    );
+    heightmap.origin_x = 200000.0;
+
+    let mut asc = Vec::new();
+    heightmap.write_asc(&mut asc).unwrap();
+    let text = String::from_utf8(asc.clone()).unwrap();
+    let mut lines = text.lines();
+    assert_eq!(lines.next(), Some("ncols 32"));
+    assert_eq!(lines.nth(1), Some("xllcorner 200000"));
+    // The northern row comes first
+    assert!(lines.nth(3).unwrap().starts_with("496 496.5 497"));
+    assert_eq!(Heightmap::from_asc(Cursor::new(asc)).unwrap(), heightmap);
+
+    let mut xyz = Vec::new();
+    heightmap.write_xyz(&mut xyz).unwrap();
+    let text = String::from_utf8(xyz.clone()).unwrap();
+    assert_eq!(text.lines().nth(1), Some("200002.5 0 0.5"));
+    assert_eq!(Heightmap::from_xyz(Cursor::new(xyz)).unwrap(), heightmap);
+
+    heightmap.elevations[0] = -3.0;
+    oprw.set_heightmap(&heightmap).unwrap();
+    assert_eq!(oprw.elevation[0], -3.0);
+
+    let small = Heightmap::new(2, 2, 1.0, vec![0.0; 4]);
+    assert!(matches!(
+        oprw.set_heightmap(&small),
+        Err(AffError::WrpError(WrpError::InvalidLength {
+            expected: 1024,
+            actual: 4,
+            ..
+        }))
+    ));
+    assert!(Heightmap::from_asc(Cursor::new("ncols 2\nnrows 2\n1 2 3\n")).is_err());
+
+    let asc = "ncols 2\nnrows 2\ncellsize 1\nNODATA_value -9999\n-9999 2\n3 -9999.0\n";
+    let with_holes = Heightmap::from_asc(Cursor::new(asc)).unwrap();
+    assert_eq!(with_holes.elevations, vec![3.0, 0.0, 0.0, 2.0]);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn heightmap_png_test() {
+    let mut heightmap = oprw_sample().heightmap();
+    heightmap.elevations[5] = -20.0;
+
+    let mut png = Vec::new();
+    heightmap.write_png(&mut png).unwrap();
+    let read = Heightmap::from_png(Cursor::new(png)).unwrap();
+    assert_eq!((read.width, read.height), (32, 32));
+    assert_eq!(read.cell_size, 2.5);
+    assert_eq!(read.range(), heightmap.range());
+    for (a, b) in read.elevations.iter().zip(&heightmap.elevations) {
+        assert!((a - b).abs() < 0.01);
+    }
+}
+
+#[test]
+fn placed_objects_test() {
+    let mut oprw = oprw_sample();
+    oprw.objects[1].transform_matrx = TransformMatrix(
+        XYZTriplet::new(0.0, 0.0, -2.0),
+        XYZTriplet::new(0.0, 2.0, 0.0),
+        XYZTriplet::new(2.0, 0.0, 0.0),
+        XYZTriplet::new(10.0, 20.0, 5.0),
+    );
+
+    let objects: Vec<PlacedObject> = oprw.placed_objects().collect();
+    assert_eq!(objects.len(), 3);
+    assert_eq!(objects[0].model, "a3\\rocks_f\\sharp\\sharprock_apart.p3d");
+    assert_eq!(objects[0].model_name(), "sharprock_apart");
+    assert_eq!((objects[0].yaw, objects[0].scale), (0.0, 1.0));
+
+    let rotated = &objects[1];
+    assert_eq!(rotated.position, XYZTriplet::new(10.0, 5.0, 20.0));
+    assert_eq!((rotated.yaw, rotated.pitch, rotated.roll), (90.0, 0.0, 0.0));
+    assert_eq!(rotated.scale, 2.0);
+    assert_eq!(rotated.surface, Some(34.0));
+    assert_eq!(rotated.relative_height(), -14.0);
+
+    let (sin, cos) = 30f32.to_radians().sin_cos();
+    let pitched = PlacedObject::from_transform(
+        0,
+        String::new(),
+        &TransformMatrix(
+            XYZTriplet::new(1.0, 0.0, 0.0),
+            XYZTriplet::new(0.0, cos, -sin),
+            XYZTriplet::new(0.0, sin, cos),
+            XYZTriplet::default(),
+        ),
+    );
+    assert!((pitched.pitch - 30.0).abs() < 1e-4);
+    assert!(pitched.roll.abs() < 1e-4);
+
+    let mut tb = Vec::new();
+    write_terrain_builder(
+        &mut tb,
+        &objects[1..2],
+        &ObjectExportOptions::terrain_builder(),
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(tb).unwrap(),
+        "\"sharprock_apart\";200010.000000;5.000000;90.000000;0.000000;0.000000;2.000000;-14.000000;\n"
+    );
+
+    let geojson = objects_to_geojson(&objects[1..2], &ObjectExportOptions::default());
+    assert_eq!(
+        geojson,
+        "{\"type\":\"FeatureCollection\",\"features\":[{\"type\":\"Feature\",\"geometry\":{\"type\":\"Point\",\"coordinates\":[10,5,20]},\"properties\":{\"id\":1,\"model\":\"a3\\\\rocks_f\\\\sharp\\\\sharprock_apart.p3d\",\"yaw\":90,\"pitch\":0,\"roll\":0,\"scale\":2}}]}"
+    );
+}
+
+#[test]
+fn road_graph_test() {
+    let part = |object_id: u32, p3d_path: &str, points: &[(f32, f32)]| RoadPart {
+        positions: points
+            .iter()
+            .map(|(x, y)| XYZTriplet::new(*x, 1.0, *y))
+            .collect(),
+        types: Some(vec![0; points.len()]),
+        object_id,
+        p3d_path: Some(p3d_path.into()),
+        transform_matrix: Some(TransformMatrix::IDENTITY),
+    };
+    let mut oprw = oprw_sample();
+    oprw.road_net = vec![
+        RoadNet {
+            cell_index: 0,
+            road_parts: vec![
+                part(
+                    1,
+                    "a3\\roads_f\\asf1_25.p3d",
+                    &[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0)],
+                ),
+                part(2, "a3\\roads_f\\asf1_12.p3d", &[(20.0, 0.0), (10.0, 0.0)]),
+            ],
+        },
+        RoadNet {
+            cell_index: 1,
+            road_parts: vec![
+                part(3, "a3\\roads_f\\grav1_10.p3d", &[(20.0, 0.0), (20.0, 10.0)]),
+                part(4, "a3\\roads_f\\asf1_10.p3d", &[(20.0, 0.0), (30.0, 0.0)]),
+            ],
+        },
+    ];
+
+    let graph = oprw.road_graph();
+    assert_eq!(graph.nodes.len(), 5);
+    assert_eq!(graph.edges.len(), 3);
+
+    let main = &graph.edges[0];
+    assert_eq!(main.class, RoadClass::MainRoad);
+    assert_eq!(main.object_ids, vec![1, 2]);
+    assert_eq!(
+        main.points,
+        vec![
+            XYZTriplet::new(0.0, 0.0, 1.0),
+            XYZTriplet::new(5.0, 0.0, 1.0),
+            XYZTriplet::new(10.0, 0.0, 1.0),
+            XYZTriplet::new(20.0, 0.0, 1.0),
+        ]
+    );
+    assert_eq!(main.length(), 20.0);
+    assert_eq!(graph.edges[1].class, RoadClass::Track);
+
+    let junction = main.end;
+    assert_eq!(graph.nodes[junction], XYZTriplet::new(20.0, 0.0, 1.0));
+    assert_eq!(graph.neighbors(junction).count(), 3);
+
+    assert_eq!(
+        RoadClass::from_model("ca\\roads2\\path_6.p3d"),
+        RoadClass::Trail
+    );
+    assert_eq!(
+        RoadClass::from_model("a3\\rocks_f\\stone.p3d"),
+        RoadClass::Unknown
+    );
+    let graph = RoadGraph::from_oprw_with(&oprw, |_| RoadClass::Road);
+    assert_eq!(graph.edges.len(), 3);
+
+    let geojson = graph.to_geojson(&ObjectExportOptions::default());
+    assert!(geojson.starts_with(
+        "{\"type\":\"FeatureCollection\",\"features\":[{\"type\":\"Feature\",\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[0,0,1],[5,0,1],[10,0,1],[20,0,1]]},\"properties\":{\"class\":\"road\""
+    ));
+
+    // Endpoints a few millimetres apart across a weld cell border, at different altitudes
+    let mut offset = part(
+        5,
+        "a3\\roads_f\\asf1_10.p3d",
+        &[(30.004, -0.003), (40.0, 0.0)],
+    );
+    offset.positions[0].y = 1.2;
+    oprw.road_net[1].road_parts.push(offset);
+    let mut apart = part(6, "a3\\roads_f\\asf1_10.p3d", &[(40.02, 0.0), (50.0, 0.0)]);
+    apart.positions[0].y = 0.9;
+    oprw.road_net[1].road_parts.push(apart);
+    let graph = oprw.road_graph();
+    assert_eq!(graph.nodes.len(), 8);
+    let east = graph
+        .edges
+        .iter()
+        .find(|e| e.object_ids.contains(&5))
+        .unwrap();
+    assert_eq!(east.object_ids, vec![4, 5]);
+}